@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Source of the current time for [`PriorityAging`](crate::PriorityAging).
+///
+/// The queue uses [`SystemClock`] unless configured otherwise; tests can
+/// swap in a [`MockClock`] to move time forward deterministically.
+pub trait Clock: Send + Sync + Debug {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tauri_queue::{Clock, MockClock};
+///
+/// let clock = MockClock::new(chrono::Utc::now());
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!((clock.now() - start).num_seconds(), 60);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let step =
+            chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::days(365));
+        if let Ok(mut now) = self.now.lock() {
+            *now += step;
+        }
+    }
+
+    /// Set the clock to `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        if let Ok(mut current) = self.now.lock() {
+            *current = now;
+        }
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+            .lock()
+            .map(|now| *now)
+            .unwrap_or_else(|_| Utc::now())
+    }
+}
@@ -1,10 +1,15 @@
+use crate::clock::{Clock, SystemClock};
+use crate::types::{PriorityAging, RetryPolicy};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Configuration for the queue system.
 ///
 /// Use [`QueueConfig::builder()`] for ergonomic construction, or
-/// [`QueueConfig::default()`] for sensible defaults (in-memory DB, no cooldown).
+/// [`QueueConfig::default()`] for sensible defaults (in-memory DB, no cooldown,
+/// one job at a time).
 #[derive(Debug, Clone)]
 pub struct QueueConfig {
     /// Path to SQLite database file. `None` = in-memory database.
@@ -16,8 +21,53 @@ pub struct QueueConfig {
     /// Maximum consecutive jobs before a forced cooldown (0 = unlimited).
     pub max_consecutive: u32,
 
-    /// Polling interval for checking pending jobs.
+    /// Polling interval for checking pending jobs. Jobs added through the
+    /// [`QueueManager`](crate::QueueManager) wake the executor immediately, so
+    /// this only bounds how long rows written from outside the queue wait.
     pub poll_interval: Duration,
+
+    /// Retry policy for jobs that don't set their own (default: no retries).
+    pub retry_policy: RetryPolicy,
+
+    /// Maximum number of jobs processed in parallel (minimum 1).
+    pub concurrency: usize,
+
+    /// Per-job-type concurrency caps, keyed by [`JobHandler::type_key()`](crate::JobHandler::type_key).
+    /// Types without an entry are only limited by `concurrency`.
+    pub job_type_limits: HashMap<String, usize>,
+
+    /// Minimum time between progress writes to the database for one job.
+    /// Progress events are still emitted on every update.
+    pub progress_persist_interval: Duration,
+
+    /// How long a claimed job stays leased to this worker without a
+    /// heartbeat. Running jobs renew their lease every third of this; a job
+    /// whose lease runs out (e.g. because its process died) is requeued.
+    pub lease_duration: Duration,
+
+    /// Identifies this queue's executor in the `worker_id` column when
+    /// several processes share one database. `None` = a random UUID.
+    pub worker_id: Option<String>,
+
+    /// Window that [`QueueManager::stats()`](crate::QueueManager::stats)
+    /// computes durations, failure rate and throughput over.
+    pub stats_window: Duration,
+
+    /// How often the executor emits `queue:stats`. `None` = never.
+    pub stats_interval: Option<Duration>,
+
+    /// Raise the effective priority of pending jobs as they wait.
+    /// `None` = strict priority order.
+    pub priority_aging: Option<PriorityAging>,
+
+    /// Time source for [priority aging](Self::priority_aging) (default:
+    /// [`SystemClock`]). Leases, delays and retries always use the wall clock.
+    pub clock: Arc<dyn Clock>,
+
+    /// Settings of named queues. Queues without an entry, including
+    /// [`DEFAULT_QUEUE`](crate::types::DEFAULT_QUEUE), use `cooldown`,
+    /// `max_consecutive` and `concurrency` above, each on their own.
+    pub queues: HashMap<String, NamedQueueConfig>,
 }
 
 impl Default for QueueConfig {
@@ -27,6 +77,17 @@ impl Default for QueueConfig {
             cooldown: Duration::from_secs(0),
             max_consecutive: 0,
             poll_interval: Duration::from_secs(3),
+            retry_policy: RetryPolicy::none(),
+            concurrency: 1,
+            job_type_limits: HashMap::new(),
+            progress_persist_interval: Duration::from_millis(500),
+            lease_duration: Duration::from_secs(30),
+            worker_id: None,
+            stats_window: Duration::from_secs(3600),
+            stats_interval: None,
+            priority_aging: None,
+            clock: Arc::new(SystemClock),
+            queues: HashMap::new(),
         }
     }
 }
@@ -36,6 +97,62 @@ impl QueueConfig {
     pub fn builder() -> QueueConfigBuilder {
         QueueConfigBuilder::default()
     }
+
+    /// Settings of the named queue `queue`.
+    pub fn queue_config(&self, queue: &str) -> NamedQueueConfig {
+        self.queues
+            .get(queue)
+            .cloned()
+            .unwrap_or(NamedQueueConfig {
+                cooldown: self.cooldown,
+                max_consecutive: self.max_consecutive,
+                concurrency: self.concurrency,
+            })
+    }
+}
+
+/// Scheduling settings of one named queue. Each queue keeps its own
+/// cooldown, consecutive count and worker slots.
+#[derive(Debug, Clone)]
+pub struct NamedQueueConfig {
+    /// Cooldown duration between job executions (0 = no cooldown).
+    pub cooldown: Duration,
+
+    /// Maximum consecutive jobs before a forced cooldown (0 = unlimited).
+    pub max_consecutive: u32,
+
+    /// Maximum number of jobs from this queue processed in parallel (minimum 1).
+    pub concurrency: usize,
+}
+
+impl Default for NamedQueueConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::from_secs(0),
+            max_consecutive: 0,
+            concurrency: 1,
+        }
+    }
+}
+
+impl NamedQueueConfig {
+    /// Set the cooldown duration between consecutive job executions.
+    pub fn with_cooldown(mut self, duration: Duration) -> Self {
+        self.cooldown = duration;
+        self
+    }
+
+    /// Set the maximum consecutive jobs before a forced cooldown.
+    pub fn with_max_consecutive(mut self, max: u32) -> Self {
+        self.max_consecutive = max;
+        self
+    }
+
+    /// Set the maximum number of jobs processed in parallel.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
 }
 
 /// Builder for [`QueueConfig`].
@@ -69,6 +186,72 @@ impl QueueConfigBuilder {
         self
     }
 
+    /// Set the default retry policy for jobs that don't set their own.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry_policy = policy;
+        self
+    }
+
+    /// Set the maximum number of jobs processed in parallel.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Limit how many jobs of one type may run at the same time.
+    pub fn with_job_type_limit(mut self, job_type: impl Into<String>, limit: usize) -> Self {
+        self.config.job_type_limits.insert(job_type.into(), limit);
+        self
+    }
+
+    /// Set the minimum time between progress writes to the database.
+    pub fn with_progress_persist_interval(mut self, interval: Duration) -> Self {
+        self.config.progress_persist_interval = interval;
+        self
+    }
+
+    /// Set how long a claimed job stays leased without a heartbeat.
+    pub fn with_lease_duration(mut self, duration: Duration) -> Self {
+        self.config.lease_duration = duration;
+        self
+    }
+
+    /// Set the worker ID recorded on jobs this queue claims.
+    pub fn with_worker_id(mut self, worker_id: impl Into<String>) -> Self {
+        self.config.worker_id = Some(worker_id.into());
+        self
+    }
+
+    /// Set the window statistics are computed over.
+    pub fn with_stats_window(mut self, window: Duration) -> Self {
+        self.config.stats_window = window;
+        self
+    }
+
+    /// Emit a `queue:stats` event every `interval` while the executor runs.
+    pub fn with_stats_interval(mut self, interval: Duration) -> Self {
+        self.config.stats_interval = Some(interval);
+        self
+    }
+
+    /// Move pending jobs up one priority level for every `interval` they wait.
+    pub fn with_priority_aging(mut self, interval: Duration) -> Self {
+        self.config.priority_aging = Some(PriorityAging::new(interval));
+        self
+    }
+
+    /// Set the time source used for priority aging.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.config.clock = Arc::new(clock);
+        self
+    }
+
+    /// Configure the named queue `queue`.
+    pub fn with_queue(mut self, queue: impl Into<String>, config: NamedQueueConfig) -> Self {
+        self.config.queues.insert(queue.into(), config);
+        self
+    }
+
     /// Build the final [`QueueConfig`].
     pub fn build(self) -> QueueConfig {
         self.config
@@ -1,8 +1,33 @@
+use crate::schedule::ScheduleInfo;
+use crate::store::{
+    build_metrics, resolve_conflict, restrict_statuses, scheduled_job, CANCELLABLE_STATUSES,
+    REQUEUEABLE_STATUSES,
+};
+pub use crate::store::{ClaimedJob, JobOptions, NewJob, StoredJob, UniqueInsert};
+use crate::types::{
+    DependencyFailurePolicy, JobAttempt, JobFilter, JobLogEntry, JobProgress, LogLevel,
+    PriorityAging, QueueJobStatus, QueueMetrics, QueuePriority, RetryPolicy, UniqueConflictPolicy,
+    DEFAULT_QUEUE,
+};
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde_json::Value;
+use std::time::Duration;
 
-const SCHEMA: &str = r#"
+/// One step of the schema history. Steps run in order, each in its own
+/// transaction, and bump `PRAGMA user_version` to `version` on success.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    sql: &'static str,
+}
+
+/// The schema history. Never edit a released step; append a new one.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: r#"
 CREATE TABLE IF NOT EXISTS queue_jobs (
     id              TEXT PRIMARY KEY,
     priority        INTEGER DEFAULT 2,
@@ -15,9 +40,172 @@ CREATE TABLE IF NOT EXISTS queue_jobs (
 );
 
 CREATE INDEX IF NOT EXISTS idx_queue_status_priority ON queue_jobs(status, priority);
-"#;
+"#,
+    },
+    Migration {
+        version: 2,
+        description: "retries, scheduling, job types, dependencies, results and progress",
+        // Rebuilds queue_jobs to drop the status CHECK constraint, so new
+        // statuses don't need another table rebuild.
+        sql: r#"
+CREATE TABLE queue_jobs_v2 (
+    id              TEXT PRIMARY KEY,
+    priority        INTEGER DEFAULT 2,
+    status          TEXT NOT NULL DEFAULT 'pending',
+    data_json       TEXT NOT NULL,
+    created_at      DATETIME DEFAULT CURRENT_TIMESTAMP,
+    started_at      DATETIME,
+    completed_at    DATETIME,
+    error_message   TEXT,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    retry_policy    TEXT,
+    next_run_at     DATETIME,
+    job_type        TEXT,
+    dependency_policy TEXT NOT NULL DEFAULT 'cancel',
+    result_json     TEXT,
+    progress_current INTEGER,
+    progress_total  INTEGER,
+    progress_stage  TEXT,
+    progress_message TEXT,
+    progress_updated_at DATETIME
+);
+
+INSERT INTO queue_jobs_v2
+    (id, priority, status, data_json, created_at, started_at, completed_at, error_message,
+     attempts)
+SELECT id, priority, COALESCE(status, 'pending'), data_json, created_at, started_at,
+       completed_at, error_message,
+       CASE WHEN started_at IS NULL THEN 0 ELSE 1 END
+FROM queue_jobs;
+
+DROP TABLE queue_jobs;
+ALTER TABLE queue_jobs_v2 RENAME TO queue_jobs;
+
+CREATE INDEX idx_queue_status_priority ON queue_jobs(status, priority);
+CREATE INDEX idx_queue_next_run_at ON queue_jobs(status, next_run_at);
+CREATE INDEX idx_queue_type_created ON queue_jobs(job_type, created_at);
+
+CREATE TABLE queue_job_dependencies (
+    job_id          TEXT NOT NULL REFERENCES queue_jobs(id) ON DELETE CASCADE,
+    depends_on      TEXT NOT NULL REFERENCES queue_jobs(id) ON DELETE CASCADE,
+    PRIMARY KEY (job_id, depends_on)
+);
+
+CREATE INDEX idx_queue_dependencies_parent ON queue_job_dependencies(depends_on);
+
+CREATE TABLE queue_schedules (
+    id              TEXT PRIMARY KEY,
+    spec_json       TEXT NOT NULL,
+    job_type        TEXT,
+    priority        INTEGER DEFAULT 2,
+    data_json       TEXT NOT NULL,
+    retry_policy    TEXT,
+    paused          INTEGER NOT NULL DEFAULT 0,
+    next_run_at     DATETIME NOT NULL,
+    last_run_at     DATETIME,
+    created_at      DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_queue_schedules_due ON queue_schedules(paused, next_run_at);
+"#,
+    },
+    Migration {
+        version: 3,
+        description: "per-job timeouts",
+        sql: r#"
+ALTER TABLE queue_jobs ADD COLUMN timeout_ms INTEGER;
+"#,
+    },
+    Migration {
+        version: 4,
+        description: "attempt history",
+        sql: r#"
+CREATE TABLE queue_job_attempts (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id          TEXT NOT NULL REFERENCES queue_jobs(id) ON DELETE CASCADE,
+    attempt         INTEGER NOT NULL,
+    outcome         TEXT NOT NULL,
+    error           TEXT,
+    started_at      DATETIME,
+    finished_at     DATETIME NOT NULL
+);
+
+CREATE INDEX idx_queue_job_attempts_job ON queue_job_attempts(job_id);
+"#,
+    },
+    Migration {
+        version: 5,
+        description: "unique job keys",
+        sql: r#"
+ALTER TABLE queue_jobs ADD COLUMN unique_key TEXT;
+
+CREATE UNIQUE INDEX idx_queue_jobs_unique_key ON queue_jobs(unique_key)
+    WHERE unique_key IS NOT NULL
+    AND status IN ('blocked', 'pending', 'processing', 'interrupted');
+"#,
+    },
+    Migration {
+        version: 6,
+        description: "worker leases",
+        sql: r#"
+ALTER TABLE queue_jobs ADD COLUMN worker_id TEXT;
+ALTER TABLE queue_jobs ADD COLUMN lease_expires_at TEXT;
+
+CREATE INDEX idx_queue_jobs_lease ON queue_jobs(status, lease_expires_at);
+"#,
+    },
+    Migration {
+        version: 7,
+        description: "named queues",
+        sql: r#"
+ALTER TABLE queue_jobs ADD COLUMN queue TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX idx_queue_jobs_queue ON queue_jobs(queue, status, priority);
+"#,
+    },
+    Migration {
+        version: 8,
+        description: "job checkpoints",
+        sql: r#"
+CREATE TABLE queue_job_checkpoints (
+    job_id          TEXT PRIMARY KEY REFERENCES queue_jobs(id) ON DELETE CASCADE,
+    data_json       TEXT NOT NULL,
+    updated_at      DATETIME NOT NULL
+);
+"#,
+    },
+    Migration {
+        version: 9,
+        description: "job logs",
+        sql: r#"
+CREATE TABLE queue_job_logs (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id          TEXT NOT NULL REFERENCES queue_jobs(id) ON DELETE CASCADE,
+    level           TEXT NOT NULL,
+    message         TEXT NOT NULL,
+    fields_json     TEXT,
+    created_at      DATETIME NOT NULL
+);
+
+CREATE INDEX idx_queue_job_logs_job ON queue_job_logs(job_id, id);
+"#,
+    },
+    Migration {
+        version: 10,
+        description: "payload versions",
+        sql: r#"
+ALTER TABLE queue_jobs ADD COLUMN payload_version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE queue_schedules ADD COLUMN payload_version INTEGER NOT NULL DEFAULT 1;
+"#,
+    },
+];
+
+/// The schema version this build of the crate writes.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
 
 /// Open (or create) the queue database. Pass `None` for an in-memory database.
+///
+/// Brings the schema up to [`SCHEMA_VERSION`] with [`migrate()`].
 pub fn open_database(path: Option<&std::path::Path>) -> Result<Connection> {
     let conn = match path {
         Some(p) => Connection::open(p).context("Failed to open queue database")?,
@@ -31,36 +219,239 @@ pub fn open_database(path: Option<&std::path::Path>) -> Result<Connection> {
     )
     .context("Failed to set PRAGMA options")?;
 
-    conn.execute_batch(SCHEMA)
-        .context("Failed to create queue schema")?;
+    migrate(&conn)?;
 
     Ok(conn)
 }
 
+/// Get the schema version of the database (`PRAGMA user_version`).
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .context("Failed to read schema version")
+}
+
+/// Apply every migration newer than the database's schema version.
+/// Returns the number of migrations applied.
+///
+/// Databases written before versioning was introduced report version 0;
+/// the first step only creates what is missing, so they upgrade like any
+/// other v1 database. Fails if the database was written by a newer build.
+pub fn migrate(conn: &Connection) -> Result<u32> {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        anyhow::bail!(
+            "Queue database is at schema version {}, but this build only supports up to {}",
+            current,
+            SCHEMA_VERSION
+        );
+    }
+
+    let mut applied = 0;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .unchecked_transaction()
+            .context("Failed to begin migration")?;
+        tx.execute_batch(migration.sql).with_context(|| {
+            format!(
+                "Failed to apply schema migration {} ({})",
+                migration.version, migration.description
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .context("Failed to update schema version")?;
+        tx.commit().context("Failed to commit migration")?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
 /// Insert a new job into the queue.
 pub fn insert_job(conn: &Connection, job_id: &str, priority: i32, data: &Value) -> Result<()> {
-    conn.execute(
-        "INSERT INTO queue_jobs (id, priority, status, data_json)
-         VALUES (?1, ?2, 'pending', ?3)",
-        params![job_id, priority, serde_json::to_string(data)?],
-    )
-    .context("Failed to insert queue job")?;
+    insert_job_with_options(conn, job_id, priority, data, &JobOptions::default())?;
     Ok(())
 }
 
-/// Get the next pending job (highest priority, oldest first).
-/// Returns the job ID and its data as a JSON value.
+/// Insert a new job into the queue with per-job options.
+///
+/// Returns the status the job was inserted with: `pending`, or `blocked` if
+/// it has unfinished dependencies. If a dependency has already failed or
+/// been cancelled, the job's dependency policy is applied straight away and
+/// `cancelled` or `failed` is returned.
+pub fn insert_job_with_options(
+    conn: &Connection,
+    job_id: &str,
+    priority: i32,
+    data: &Value,
+    options: &JobOptions,
+) -> Result<String> {
+    let retry_policy = options
+        .retry_policy
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let data_json = serde_json::to_string(data)?;
+
+    in_transaction(conn, |conn| {
+        let mut status = "pending";
+        let mut failed_dependency = None;
+        for parent_id in &options.depends_on {
+            let parent_status: String = conn
+                .query_row(
+                    "SELECT status FROM queue_jobs WHERE id = ?1",
+                    params![parent_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .context("Failed to read dependency status")?
+                .ok_or_else(|| anyhow::anyhow!("Dependency '{}' not found", parent_id))?;
+            match parent_status.as_str() {
+                "completed" => {}
+                "failed" | "dead" | "cancelled" | "timed_out" | "incompatible" => {
+                    failed_dependency = Some((parent_id, parent_status))
+                }
+                _ => status = "blocked",
+            }
+        }
+
+        conn.execute(
+            "INSERT INTO queue_jobs
+                (id, priority, status, data_json, retry_policy, next_run_at, job_type,
+                 dependency_policy, timeout_ms, unique_key, queue, payload_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                job_id,
+                priority,
+                status,
+                data_json,
+                retry_policy,
+                options.next_run_at,
+                options.job_type,
+                options.dependency_policy.as_str(),
+                options.timeout.map(duration_ms),
+                options.unique_key,
+                options.queue.as_deref().unwrap_or(DEFAULT_QUEUE),
+                options.payload_version.unwrap_or(1)
+            ],
+        )
+        .context("Failed to insert queue job")?;
+
+        for parent_id in &options.depends_on {
+            conn.execute(
+                "INSERT OR IGNORE INTO queue_job_dependencies (job_id, depends_on) VALUES (?1, ?2)",
+                params![job_id, parent_id],
+            )
+            .context("Failed to insert job dependency")?;
+        }
+
+        match failed_dependency {
+            Some((parent_id, parent_status)) => {
+                let status = apply_dependency_policy(conn, job_id, parent_id, &parent_status)?;
+                Ok(status.to_string())
+            }
+            None => Ok(status.to_string()),
+        }
+    })
+}
+
+/// Insert a job that carries `options.unique_key`, checking for an active
+/// job (blocked, pending, processing or interrupted) that already holds the key.
+///
+/// On a conflict the existing job is left alone, unless `replace_pending` is
+/// set and it hasn't started yet: it is then cancelled and the new job takes
+/// its place. The check and the insert run in one transaction.
+pub fn insert_unique_job(
+    conn: &Connection,
+    job_id: &str,
+    priority: i32,
+    data: &Value,
+    options: &JobOptions,
+    replace_pending: bool,
+) -> Result<UniqueInsert> {
+    in_transaction(conn, |conn| {
+        let existing: Option<(String, String)> = match &options.unique_key {
+            Some(key) => conn
+                .query_row(
+                    "SELECT id, status FROM queue_jobs
+                     WHERE unique_key = ?1
+                     AND status IN ('blocked', 'pending', 'processing', 'interrupted')",
+                    params![key],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+                .context("Failed to look up unique key")?,
+            None => None,
+        };
+
+        let replaced = match existing {
+            Some((existing_id, status))
+                if replace_pending && matches!(status.as_str(), "blocked" | "pending") =>
+            {
+                cancel_job(conn, &existing_id)?;
+                Some(existing_id)
+            }
+            Some((existing_id, _)) => return Ok(UniqueInsert::Conflict { existing_id }),
+            None => None,
+        };
+
+        let status = insert_job_with_options(conn, job_id, priority, data, options)?;
+        Ok(UniqueInsert::Inserted { status, replaced })
+    })
+}
+
+/// Insert one job of an [`insert_many()`](crate::JobStore::insert_many)
+/// batch, settling a unique key conflict by the job's `on_conflict` policy.
+pub fn insert_new_job(conn: &Connection, job: &NewJob) -> Result<UniqueInsert> {
+    if job.options.unique_key.is_none() {
+        let status = insert_job_with_options(conn, &job.id, job.priority, &job.data, &job.options)?;
+        return Ok(UniqueInsert::Inserted {
+            status,
+            replaced: None,
+        });
+    }
+    let replace_pending = job.on_conflict == UniqueConflictPolicy::ReplacePending;
+    match insert_unique_job(
+        conn,
+        &job.id,
+        job.priority,
+        &job.data,
+        &job.options,
+        replace_pending,
+    )? {
+        UniqueInsert::Conflict { existing_id } => resolve_conflict(job, existing_id),
+        inserted => Ok(inserted),
+    }
+}
+
+/// Run `f` inside a transaction, or directly if one is already open.
+pub fn in_transaction<T>(conn: &Connection, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    if !conn.is_autocommit() {
+        return f(conn);
+    }
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to begin transaction")?;
+    let value = f(&tx)?;
+    tx.commit().context("Failed to commit transaction")?;
+    Ok(value)
+}
+
+/// Get the next pending job (highest priority, oldest first) whose
+/// `next_run_at` has passed. Returns the job ID and its data as a JSON value.
 pub fn get_next_pending(conn: &Connection) -> Result<Option<(String, Value)>> {
+    let now = chrono::Utc::now().to_rfc3339();
     let mut stmt = conn
         .prepare(
             "SELECT id, data_json FROM queue_jobs
              WHERE status = 'pending'
+             AND (next_run_at IS NULL OR next_run_at <= ?1)
              ORDER BY priority ASC, created_at ASC
              LIMIT 1",
         )
         .context("Failed to prepare get_next_pending query")?;
 
-    let mut rows = stmt.query([]).context("Failed to query next pending job")?;
+    let mut rows = stmt
+        .query(params![now])
+        .context("Failed to query next pending job")?;
 
     if let Some(row) = rows.next().context("Failed to read next pending row")? {
         let id: String = row.get(0)?;
@@ -73,39 +464,615 @@ pub fn get_next_pending(conn: &Connection) -> Result<Option<(String, Value)>> {
     }
 }
 
-/// Mark a job as processing and set started_at.
+/// Atomically pick the next runnable pending job and mark it as processing,
+/// without a lease. Such a job is only recovered by [`requeue_interrupted()`];
+/// the executor uses [`claim_next_leased()`].
+pub fn claim_next(
+    conn: &Connection,
+    job_types: &[String],
+    include_untyped: bool,
+) -> Result<Option<ClaimedJob>> {
+    claim(
+        conn,
+        None,
+        job_types,
+        include_untyped,
+        None,
+        None,
+    )
+}
+
+/// Atomically pick the next runnable pending job of the named queue `queue`,
+/// mark it as processing and lease it to `worker_id` for `lease`.
+///
+/// Only jobs whose type is listed in `job_types` are considered, plus
+/// legacy rows with no recorded type if `include_untyped` is set. This lets
+/// the executor skip types it has no handler for, or that have reached
+/// their concurrency cap. Because selection and update happen in a single
+/// statement, two workers can never claim the same job, even from different
+/// processes sharing the database. The worker keeps the job by renewing the
+/// lease with [`renew_lease()`]; once it runs out, [`reclaim_expired_leases()`]
+/// puts the job back in the queue.
+pub fn claim_next_leased(
+    conn: &Connection,
+    queue: &str,
+    job_types: &[String],
+    include_untyped: bool,
+    worker_id: &str,
+    lease: Duration,
+) -> Result<Option<ClaimedJob>> {
+    claim_next_aged(
+        conn,
+        queue,
+        job_types,
+        include_untyped,
+        (worker_id, lease),
+        None,
+    )
+}
+
+/// Like [`claim_next_leased()`] with `lease` as `(worker_id, duration)`, but
+/// if `aging` is set, picking jobs by their effective priority: a pending job
+/// moves up one level for every `aging.interval` it has waited since its
+/// `next_run_at` (or creation) until the given time, up to High.
+///
+/// Only aging uses that time, so it can come from a [`Clock`](crate::Clock);
+/// due checks and leases always use the wall clock.
+pub fn claim_next_aged(
+    conn: &Connection,
+    queue: &str,
+    job_types: &[String],
+    include_untyped: bool,
+    lease: (&str, Duration),
+    aging: Option<(&PriorityAging, chrono::DateTime<chrono::Utc>)>,
+) -> Result<Option<ClaimedJob>> {
+    claim(
+        conn,
+        Some(queue),
+        job_types,
+        include_untyped,
+        Some(lease),
+        aging,
+    )
+}
+
+fn claim(
+    conn: &Connection,
+    queue: Option<&str>,
+    job_types: &[String],
+    include_untyped: bool,
+    lease: Option<(&str, Duration)>,
+    aging: Option<(&PriorityAging, chrono::DateTime<chrono::Utc>)>,
+) -> Result<Option<ClaimedJob>> {
+    let now = chrono::Utc::now();
+    let job_types = serde_json::to_string(job_types)?;
+    let (worker_id, lease_expires_at) = match lease {
+        Some((worker_id, duration)) => (Some(worker_id), Some(lease_expiry(now, duration))),
+        None => (None, None),
+    };
+    let mut stmt = conn
+        .prepare(
+            "UPDATE queue_jobs
+             SET status = 'processing', started_at = ?1, attempts = attempts + 1,
+                 worker_id = ?4, lease_expires_at = ?5
+             WHERE status = 'pending' AND id = (
+                 SELECT id FROM queue_jobs
+                 WHERE status = 'pending'
+                 AND (next_run_at IS NULL OR next_run_at <= ?1)
+                 AND (job_type IN (SELECT value FROM json_each(?2))
+                      OR (job_type IS NULL AND ?3))
+                 AND (?6 IS NULL OR queue = ?6)
+                 ORDER BY
+                    CASE WHEN ?7 IS NULL THEN priority
+                    ELSE MAX(1, priority - MAX(0, CAST(
+                        (julianday(?8) - julianday(COALESCE(next_run_at, created_at)))
+                            * 86400000.0 / ?7 AS INTEGER)))
+                    END ASC,
+                    created_at ASC,
+                    rowid ASC
+                 LIMIT 1
+             )
+             RETURNING id, job_type, data_json, timeout_ms, attempts, payload_version",
+        )
+        .context("Failed to prepare claim_next query")?;
+
+    let mut rows = stmt
+        .query(params![
+            now.to_rfc3339(),
+            job_types,
+            include_untyped,
+            worker_id,
+            lease_expires_at,
+            queue,
+            aging.map(|(aging, _)| duration_ms(aging.interval).max(1)),
+            aging.map(|(_, aged_at)| aged_at.to_rfc3339())
+        ])
+        .context("Failed to claim next pending job")?;
+
+    if let Some(row) = rows.next().context("Failed to read claimed row")? {
+        let data_json: String = row.get(2)?;
+        Ok(Some(ClaimedJob {
+            id: row.get(0)?,
+            job_type: row.get(1)?,
+            data: serde_json::from_str(&data_json).context("Failed to parse job data JSON")?,
+            timeout: row.get::<_, Option<i64>>(3)?.map(ms_duration),
+            attempt: row.get(4)?,
+            payload_version: row.get(5)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+fn lease_expiry(now: chrono::DateTime<chrono::Utc>, lease: Duration) -> String {
+    (now + chrono::Duration::from_std(lease).unwrap_or_else(|_| chrono::Duration::days(365)))
+        .to_rfc3339()
+}
+
+/// Named queues that have pending jobs ready to run, in name order.
+pub fn runnable_queues(conn: &Connection) -> Result<Vec<String>> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT queue FROM queue_jobs
+             WHERE status = 'pending' AND (next_run_at IS NULL OR next_run_at <= ?1)
+             ORDER BY queue",
+        )
+        .context("Failed to prepare runnable_queues query")?;
+    let rows = stmt
+        .query_map(params![now], |row| row.get(0))
+        .context("Failed to query runnable queues")?;
+    rows.collect::<rusqlite::Result<_>>()
+        .context("Failed to read queue row")
+}
+
+/// Number of jobs per named queue and status, as `(queue, status, count)`.
+pub fn queue_status_counts(conn: &Connection) -> Result<Vec<(String, String, u32)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT queue, status, COUNT(*) FROM queue_jobs
+             GROUP BY queue, status ORDER BY queue",
+        )
+        .context("Failed to prepare queue_status_counts query")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .context("Failed to query queue counts")?;
+    rows.collect::<rusqlite::Result<_>>()
+        .context("Failed to read queue count row")
+}
+
+/// Compute queue statistics, with durations, failure rate and throughput
+/// taken from jobs that finished within the last `window`.
+pub fn queue_metrics(conn: &Connection, window: Duration) -> Result<QueueMetrics> {
+    let now = chrono::Utc::now();
+    let since = (now
+        - chrono::Duration::from_std(window).unwrap_or_else(|_| chrono::Duration::days(365)))
+    .to_rfc3339();
+    let mut stmt = conn
+        .prepare(
+            "SELECT COALESCE(job_type, ''), status, COUNT(*) FROM queue_jobs
+             GROUP BY 1, 2",
+        )
+        .context("Failed to prepare status count query")?;
+    let counts = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u32>(2)?,
+            ))
+        })
+        .context("Failed to query status counts")?;
+    let counts = counts
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read status count row")?;
+
+    // Jobs that finished within the window, with the duration of their last
+    // attempt in milliseconds
+    let mut stmt = conn
+        .prepare(
+            "SELECT COALESCE(job_type, ''), status,
+                    (julianday(completed_at) - julianday(started_at)) * 86400000.0
+             FROM queue_jobs
+             WHERE status IN ('completed', 'failed', 'dead', 'timed_out', 'incompatible')
+             AND completed_at IS NOT NULL
+             AND julianday(completed_at) >= julianday(?1)",
+        )
+        .context("Failed to prepare finished jobs query")?;
+    let finished = stmt
+        .query_map(params![since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+            ))
+        })
+        .context("Failed to query finished jobs")?;
+    let finished = finished
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Failed to read finished job row")?;
+
+    Ok(build_metrics(now, window, counts, finished))
+}
+
+/// Extend the lease `worker_id` holds on a job by `lease` from now.
+///
+/// Returns `false` if the job no longer belongs to this worker, because
+/// the lease ran out and the job was reclaimed, or if it is no longer
+/// processing, e.g. because it was cancelled from another connection.
+pub fn renew_lease(
+    conn: &Connection,
+    job_id: &str,
+    worker_id: &str,
+    lease: Duration,
+) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE queue_jobs SET lease_expires_at = ?1
+             WHERE id = ?2 AND worker_id = ?3 AND status = 'processing'",
+            params![lease_expiry(chrono::Utc::now(), lease), job_id, worker_id],
+        )
+        .context("Failed to renew job lease")?;
+    Ok(updated > 0)
+}
+
+/// Put processing jobs whose lease has run out back into the queue, recording
+/// the lost attempt. Returns the IDs of the reclaimed jobs.
+pub fn reclaim_expired_leases(conn: &Connection) -> Result<Vec<String>> {
+    let now = chrono::Utc::now().to_rfc3339();
+    in_transaction(conn, |conn| {
+        let job_ids: Vec<String> = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id FROM queue_jobs
+                     WHERE status = 'processing'
+                     AND lease_expires_at IS NOT NULL AND lease_expires_at < ?1",
+                )
+                .context("Failed to prepare expired lease query")?;
+            let rows = stmt
+                .query_map(params![now], |row| row.get(0))
+                .context("Failed to query expired leases")?;
+            rows.collect::<rusqlite::Result<_>>()
+                .context("Failed to read expired lease row")?
+        };
+
+        for job_id in &job_ids {
+            record_attempt(
+                conn,
+                job_id,
+                "lease_expired",
+                Some("Worker lease expired"),
+                &now,
+            )?;
+            conn.execute(
+                "UPDATE queue_jobs
+                 SET status = 'pending', worker_id = NULL, lease_expires_at = NULL
+                 WHERE id = ?1",
+                params![job_id],
+            )
+            .context("Failed to reclaim job")?;
+        }
+        Ok(job_ids)
+    })
+}
+
+/// Mark a job as processing, set started_at and count the attempt.
 pub fn mark_processing(conn: &Connection, job_id: &str) -> Result<()> {
     let now = chrono::Utc::now().to_rfc3339();
     conn.execute(
-        "UPDATE queue_jobs SET status = 'processing', started_at = ?1 WHERE id = ?2",
+        "UPDATE queue_jobs SET status = 'processing', started_at = ?1, attempts = attempts + 1
+         WHERE id = ?2",
         params![now, job_id],
     )
     .context("Failed to mark job as processing")?;
     Ok(())
 }
 
-/// Mark a job as completed and set completed_at.
+/// Mark a processing job as completed and set completed_at.
 pub fn mark_completed(conn: &Connection, job_id: &str) -> Result<()> {
+    mark_completed_with_output(conn, job_id, None)?;
+    Ok(())
+}
+
+/// Mark a processing job as completed and store its output in `result_json`.
+///
+/// Returns `false` if the job was no longer processing (e.g. it was
+/// cancelled while running), in which case it is left unchanged.
+pub fn mark_completed_with_output(
+    conn: &Connection,
+    job_id: &str,
+    output: Option<&str>,
+) -> Result<bool> {
     let now = chrono::Utc::now().to_rfc3339();
+    in_transaction(conn, |conn| {
+        let updated = conn
+            .execute(
+                "UPDATE queue_jobs
+                 SET status = 'completed', completed_at = ?1, error_message = NULL,
+                     result_json = ?2
+                 WHERE id = ?3 AND status = 'processing'",
+                params![now, output, job_id],
+            )
+            .context("Failed to mark job as completed")?;
+        if updated > 0 {
+            record_attempt(conn, job_id, "completed", None, &now)?;
+            clear_checkpoint(conn, job_id)?;
+        }
+        Ok(updated > 0)
+    })
+}
+
+/// Mark a processing job as failed with an error message and set
+/// completed_at. Jobs that are no longer processing are left unchanged.
+pub fn mark_failed(conn: &Connection, job_id: &str, error: &str) -> Result<()> {
+    finish_with_error(conn, job_id, "failed", "failed", error)?;
+    Ok(())
+}
+
+/// Move a processing job that has used up all of its attempts to the
+/// dead-letter state. Jobs that are no longer processing are left unchanged.
+pub fn mark_dead(conn: &Connection, job_id: &str, error: &str) -> Result<()> {
+    finish_with_error(conn, job_id, "dead", "failed", error)?;
+    Ok(())
+}
+
+/// Mark a processing job as interrupted by a shutdown, so that
+/// [`requeue_interrupted()`] runs it again on the next start.
+/// Returns `false` if the job was no longer processing.
+pub fn mark_interrupted(conn: &Connection, job_id: &str) -> Result<bool> {
+    finish_with_error(
+        conn,
+        job_id,
+        "interrupted",
+        "interrupted",
+        "Interrupted by shutdown",
+    )
+}
+
+/// Mark a processing job as incompatible: its payload couldn't be upcast or
+/// deserialized into its handler type. Returns `false` if the job was no
+/// longer processing.
+pub fn mark_incompatible(conn: &Connection, job_id: &str, error: &str) -> Result<bool> {
+    finish_with_error(conn, job_id, "incompatible", "incompatible", error)
+}
+
+/// Replace a job's payload with `data`, migrated to `payload_version`.
+pub fn update_payload(
+    conn: &Connection,
+    job_id: &str,
+    data: &Value,
+    payload_version: u32,
+) -> Result<()> {
     conn.execute(
-        "UPDATE queue_jobs SET status = 'completed', completed_at = ?1 WHERE id = ?2",
-        params![now, job_id],
+        "UPDATE queue_jobs SET data_json = ?1, payload_version = ?2 WHERE id = ?3",
+        params![serde_json::to_string(data)?, payload_version, job_id],
     )
-    .context("Failed to mark job as completed")?;
+    .context("Failed to update job payload")?;
     Ok(())
 }
 
-/// Mark a job as failed with an error message and set completed_at.
-pub fn mark_failed(conn: &Connection, job_id: &str, error: &str) -> Result<()> {
+/// Mark a processing job as timed out after running longer than `timeout`.
+/// Returns `false` if the job was no longer processing.
+pub fn mark_timed_out(conn: &Connection, job_id: &str, timeout: Duration) -> Result<bool> {
+    let error = format!("Timed out after {:?}", timeout);
+    finish_with_error(conn, job_id, "timed_out", "timed_out", &error)
+}
+
+/// End the current attempt of a processing job with `error`, setting the
+/// job to `status` and recording the attempt as `outcome`.
+fn finish_with_error(
+    conn: &Connection,
+    job_id: &str,
+    status: &str,
+    outcome: &str,
+    error: &str,
+) -> Result<bool> {
     let now = chrono::Utc::now().to_rfc3339();
+    in_transaction(conn, |conn| {
+        let updated = conn
+            .execute(
+                "UPDATE queue_jobs SET status = ?1, completed_at = ?2, error_message = ?3
+                 WHERE id = ?4 AND status = 'processing'",
+                params![status, now, error, job_id],
+            )
+            .with_context(|| format!("Failed to mark job as {}", status))?;
+        if updated > 0 {
+            record_attempt(conn, job_id, outcome, Some(error), &now)?;
+            // An interrupted job resumes from its checkpoint on the next start
+            if status != "interrupted" {
+                clear_checkpoint(conn, job_id)?;
+            }
+        }
+        Ok(updated > 0)
+    })
+}
+
+/// Append the job's current attempt to its history.
+fn record_attempt(
+    conn: &Connection,
+    job_id: &str,
+    outcome: &str,
+    error: Option<&str>,
+    finished_at: &str,
+) -> Result<()> {
     conn.execute(
-        "UPDATE queue_jobs SET status = 'failed', completed_at = ?1, error_message = ?2 WHERE id = ?3",
-        params![now, error, job_id],
+        "INSERT INTO queue_job_attempts (job_id, attempt, outcome, error, started_at, finished_at)
+         SELECT id, attempts, ?2, ?3, started_at, ?4 FROM queue_jobs WHERE id = ?1",
+        params![job_id, outcome, error, finished_at],
     )
-    .context("Failed to mark job as failed")?;
+    .context("Failed to record job attempt")?;
     Ok(())
 }
 
+/// Get the attempt history of a job, oldest first.
+pub fn get_attempts(conn: &Connection, job_id: &str) -> Result<Vec<JobAttempt>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT attempt, outcome, error, started_at, finished_at
+             FROM queue_job_attempts WHERE job_id = ?1
+             ORDER BY id ASC",
+        )
+        .context("Failed to prepare get_attempts query")?;
+    let rows = stmt
+        .query_map(params![job_id], |row| {
+            Ok(JobAttempt {
+                attempt: row.get(0)?,
+                outcome: row.get(1)?,
+                error: row.get(2)?,
+                started_at: row.get(3)?,
+                finished_at: row.get(4)?,
+            })
+        })
+        .context("Failed to query job attempts")?;
+
+    let mut attempts = Vec::new();
+    for row in rows {
+        attempts.push(row.context("Failed to read job attempt")?);
+    }
+    Ok(attempts)
+}
+
+fn duration_ms(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+}
+
+fn ms_duration(ms: i64) -> Duration {
+    Duration::from_millis(ms.max(0) as u64)
+}
+
+/// Get the number of attempts made so far and the job's own retry policy
+/// (`None` if the job uses the executor's default).
+pub fn get_retry_state(conn: &Connection, job_id: &str) -> Result<(u32, Option<RetryPolicy>)> {
+    let (attempts, policy_json): (u32, Option<String>) = conn
+        .query_row(
+            "SELECT attempts, retry_policy FROM queue_jobs WHERE id = ?1",
+            params![job_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .context("Failed to read job retry state")?
+        .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+
+    let policy: Option<RetryPolicy> = policy_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .context("Failed to parse job retry policy")?;
+    Ok((attempts, policy))
+}
+
+/// Put a failed processing job back to pending, eligible again at `next_run_at`.
+pub fn schedule_retry(
+    conn: &Connection,
+    job_id: &str,
+    error: &str,
+    next_run_at: &str,
+) -> Result<()> {
+    let now = chrono::Utc::now().to_rfc3339();
+    in_transaction(conn, |conn| {
+        let updated = conn
+            .execute(
+                "UPDATE queue_jobs SET status = 'pending', error_message = ?1, next_run_at = ?2
+                 WHERE id = ?3 AND status = 'processing'",
+                params![error, next_run_at, job_id],
+            )
+            .context("Failed to schedule job retry")?;
+        if updated > 0 {
+            record_attempt(conn, job_id, "failed", Some(error), &now)?;
+        }
+        Ok(())
+    })
+}
+
+/// Reset a finished job so it runs again from scratch. Returns the status
+/// it had before.
+///
+/// The attempt count, error, output and progress are cleared; the attempt
+/// history is kept. A job whose dependencies haven't all completed goes back
+/// to `blocked` instead of `pending`.
+pub fn requeue_job(conn: &Connection, job_id: &str) -> Result<String> {
+    let prev_status: String = conn
+        .query_row(
+            "SELECT status FROM queue_jobs WHERE id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to read job status")?
+        .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+
+    if !REQUEUEABLE_STATUSES.contains(&prev_status.as_str()) {
+        anyhow::bail!(
+            "Job '{}' can't be requeued (status: {})",
+            job_id,
+            prev_status
+        );
+    }
+
+    conn.execute(
+        "UPDATE queue_jobs
+         SET status = CASE
+                 WHEN EXISTS (
+                     SELECT 1 FROM queue_job_dependencies d
+                     JOIN queue_jobs p ON p.id = d.depends_on
+                     WHERE d.job_id = queue_jobs.id AND p.status != 'completed'
+                 ) THEN 'blocked'
+                 ELSE 'pending'
+             END,
+             attempts = 0, error_message = NULL, next_run_at = NULL, started_at = NULL,
+             completed_at = NULL, result_json = NULL, progress_current = NULL,
+             progress_total = NULL, progress_stage = NULL, progress_message = NULL,
+             progress_updated_at = NULL
+         WHERE id = ?1",
+        params![job_id],
+    )
+    .context("Failed to requeue job")?;
+
+    Ok(prev_status)
+}
+
+/// Requeue every job matching `filter` (see [`requeue_job()`]). Only failed,
+/// dead, timed-out and cancelled jobs are affected; with no statuses in the
+/// filter, failed and dead jobs are. Returns `(id, previous_status)` for each
+/// requeued job.
+pub fn requeue_jobs(conn: &Connection, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+    let defaults = [QueueJobStatus::Failed, QueueJobStatus::Dead];
+    let Some(filter) = restrict_statuses(filter, &defaults, &REQUEUEABLE_STATUSES) else {
+        return Ok(Vec::new());
+    };
+
+    in_transaction(conn, |conn| {
+        let mut requeued = Vec::new();
+        for job in query_jobs(conn, &filter)? {
+            let prev_status = requeue_job(conn, &job.id)?;
+            requeued.push((job.id, prev_status));
+        }
+        Ok(requeued)
+    })
+}
+
+/// Cancel every job matching `filter` (see [`cancel_job()`]). Only blocked,
+/// pending and processing jobs are affected; with no statuses in the filter,
+/// all three are. Returns `(id, previous_status)` for each cancelled job.
+pub fn cancel_jobs(conn: &Connection, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+    let defaults = [
+        QueueJobStatus::Blocked,
+        QueueJobStatus::Pending,
+        QueueJobStatus::Processing,
+    ];
+    let Some(filter) = restrict_statuses(filter, &defaults, &CANCELLABLE_STATUSES) else {
+        return Ok(Vec::new());
+    };
+
+    in_transaction(conn, |conn| {
+        let mut cancelled = Vec::new();
+        for job in query_jobs(conn, &filter)? {
+            let prev_status = cancel_job(conn, &job.id)?;
+            cancelled.push((job.id, prev_status));
+        }
+        Ok(cancelled)
+    })
+}
+
 /// Check if a job has been cancelled (used by executor during execution).
 pub fn is_cancelled(conn: &Connection, job_id: &str) -> Result<bool> {
     let status: String = conn
@@ -118,7 +1085,7 @@ pub fn is_cancelled(conn: &Connection, job_id: &str) -> Result<bool> {
     Ok(status == "cancelled")
 }
 
-/// Cancel a pending or processing job. Returns the previous status.
+/// Cancel a blocked, pending or processing job. Returns the previous status.
 pub fn cancel_job(conn: &Connection, job_id: &str) -> Result<String> {
     let prev_status: String = conn
         .query_row(
@@ -128,7 +1095,7 @@ pub fn cancel_job(conn: &Connection, job_id: &str) -> Result<String> {
         )
         .map_err(|_| anyhow::anyhow!("Job '{}' not found", job_id))?;
 
-    if prev_status != "pending" && prev_status != "processing" {
+    if !matches!(prev_status.as_str(), "blocked" | "pending" | "processing") {
         anyhow::bail!(
             "Job '{}' is not cancellable (status: {})",
             job_id,
@@ -139,20 +1106,125 @@ pub fn cancel_job(conn: &Connection, job_id: &str) -> Result<String> {
     let now = chrono::Utc::now().to_rfc3339();
     conn.execute(
         "UPDATE queue_jobs SET status = 'cancelled', completed_at = ?1
-         WHERE id = ?2 AND status IN ('pending', 'processing')",
+         WHERE id = ?2 AND status IN ('blocked', 'pending', 'processing')",
         params![now, job_id],
     )
     .context("Failed to cancel job")?;
+    clear_checkpoint(conn, job_id)?;
 
     Ok(prev_status)
 }
 
-/// Re-queue any jobs that were mid-processing when the app crashed.
-/// Returns the number of jobs requeued.
+/// Append a line to a job's log and return the stored entry.
+pub fn insert_log(
+    conn: &Connection,
+    job_id: &str,
+    level: LogLevel,
+    message: &str,
+    fields: &Value,
+) -> Result<JobLogEntry> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let fields_json = match fields {
+        Value::Null => None,
+        fields => Some(serde_json::to_string(fields)?),
+    };
+    conn.execute(
+        "INSERT INTO queue_job_logs (job_id, level, message, fields_json, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![job_id, level.as_str(), message, fields_json, now],
+    )
+    .context("Failed to insert job log")?;
+    Ok(JobLogEntry {
+        id: conn.last_insert_rowid(),
+        job_id: job_id.to_string(),
+        level,
+        message: message.to_string(),
+        fields: fields.clone(),
+        created_at: now,
+    })
+}
+
+/// Get a job's log, oldest first. With `since`, only entries whose ID is
+/// greater than `since` are returned.
+pub fn get_logs(conn: &Connection, job_id: &str, since: Option<i64>) -> Result<Vec<JobLogEntry>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, level, message, fields_json, created_at FROM queue_job_logs
+             WHERE job_id = ?1 AND id > ?2
+             ORDER BY id ASC",
+        )
+        .context("Failed to prepare get_logs query")?;
+    let rows = stmt
+        .query_map(params![job_id, since.unwrap_or(0)], |row| {
+            let level: String = row.get(1)?;
+            let fields: Option<String> = row.get(3)?;
+            Ok(JobLogEntry {
+                id: row.get(0)?,
+                job_id: job_id.to_string(),
+                level: LogLevel::parse(&level).unwrap_or(LogLevel::Info),
+                message: row.get(2)?,
+                fields: fields
+                    .map(|json| json_column(3, &json))
+                    .transpose()?
+                    .unwrap_or(Value::Null),
+                created_at: row.get(4)?,
+            })
+        })
+        .context("Failed to query job logs")?;
+    rows.collect::<rusqlite::Result<_>>()
+        .context("Failed to read job log row")
+}
+
+/// Store `checkpoint` for a job, replacing the previous one.
+pub fn save_checkpoint(conn: &Connection, job_id: &str, checkpoint: &Value) -> Result<()> {
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO queue_job_checkpoints (job_id, data_json, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(job_id) DO UPDATE SET data_json = excluded.data_json,
+                                           updated_at = excluded.updated_at",
+        params![job_id, serde_json::to_string(checkpoint)?, now],
+    )
+    .context("Failed to save job checkpoint")?;
+    Ok(())
+}
+
+/// Get the last checkpoint saved for a job.
+pub fn load_checkpoint(conn: &Connection, job_id: &str) -> Result<Option<Value>> {
+    let data_json: Option<String> = conn
+        .query_row(
+            "SELECT data_json FROM queue_job_checkpoints WHERE job_id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to load job checkpoint")?;
+    data_json
+        .map(|json| serde_json::from_str(&json).context("Failed to parse checkpoint JSON"))
+        .transpose()
+}
+
+/// Delete a job's checkpoint, once the job has finished for good.
+pub fn clear_checkpoint(conn: &Connection, job_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM queue_job_checkpoints WHERE job_id = ?1",
+        params![job_id],
+    )
+    .context("Failed to clear job checkpoint")?;
+    Ok(())
+}
+
+/// Re-queue jobs that were interrupted by a shutdown, and processing jobs
+/// without a lease (claimed by [`claim_next()`] or before leases existed),
+/// which can only be left over from a crash. Leased jobs are left to
+/// [`reclaim_expired_leases()`], since another process may still be running
+/// them. Returns the number of jobs requeued.
 pub fn requeue_interrupted(conn: &Connection) -> Result<u32> {
     let count = conn
         .execute(
-            "UPDATE queue_jobs SET status = 'pending' WHERE status = 'processing'",
+            "UPDATE queue_jobs SET status = 'pending', completed_at = NULL, error_message = NULL
+             WHERE status = 'interrupted'
+             OR (status = 'processing' AND lease_expires_at IS NULL)",
             [],
         )
         .context("Failed to requeue interrupted jobs")?;
@@ -169,6 +1241,32 @@ pub fn update_priority(conn: &Connection, job_id: &str, priority: i32) -> Result
     Ok(())
 }
 
+/// Set the priority of every pending job in `job_ids` in one statement.
+/// Other jobs are skipped. Returns the IDs of the jobs that were updated.
+pub fn update_priorities(
+    conn: &Connection,
+    job_ids: &[String],
+    priority: i32,
+) -> Result<Vec<String>> {
+    let job_ids = serde_json::to_string(job_ids)?;
+    let mut stmt = conn
+        .prepare(
+            "UPDATE queue_jobs SET priority = ?1
+             WHERE status = 'pending' AND id IN (SELECT value FROM json_each(?2))
+             RETURNING id",
+        )
+        .context("Failed to prepare update_priorities query")?;
+    let rows = stmt
+        .query_map(params![priority, job_ids], |row| row.get(0))
+        .context("Failed to update job priorities")?;
+
+    let mut updated = Vec::new();
+    for row in rows {
+        updated.push(row.context("Failed to read updated job id")?);
+    }
+    Ok(updated)
+}
+
 /// List all jobs ordered by status then priority then creation time.
 /// Returns tuples of (id, status, data_json).
 pub fn list_all_jobs(conn: &Connection) -> Result<Vec<(String, String, String)>> {
@@ -179,9 +1277,11 @@ pub fn list_all_jobs(conn: &Connection) -> Result<Vec<(String, String, String)>>
                 CASE status
                     WHEN 'processing' THEN 0
                     WHEN 'pending' THEN 1
-                    WHEN 'completed' THEN 2
-                    WHEN 'failed' THEN 3
-                    WHEN 'cancelled' THEN 4
+                    WHEN 'blocked' THEN 2
+                    WHEN 'completed' THEN 3
+                    WHEN 'failed' THEN 4
+                    WHEN 'cancelled' THEN 5
+                    ELSE 6
                 END,
                 priority ASC,
                 created_at ASC",
@@ -199,7 +1299,9 @@ pub fn list_all_jobs(conn: &Connection) -> Result<Vec<(String, String, String)>>
     Ok(jobs)
 }
 
-/// Delete completed/failed/cancelled jobs older than the specified number of days.
+/// Delete finished (completed, failed, dead, cancelled, timed out or
+/// incompatible) jobs older
+/// than the specified number of days.
 /// Returns the number of jobs deleted.
 pub fn prune_old_jobs(conn: &Connection, days: u32) -> Result<u32> {
     let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
@@ -208,7 +1310,7 @@ pub fn prune_old_jobs(conn: &Connection, days: u32) -> Result<u32> {
     let count = conn
         .execute(
             "DELETE FROM queue_jobs
-             WHERE status IN ('completed', 'failed', 'cancelled')
+             WHERE status IN ('completed', 'failed', 'dead', 'cancelled', 'timed_out', 'incompatible')
              AND completed_at < ?1",
             params![cutoff_str],
         )
@@ -244,35 +1346,499 @@ pub fn get_job(conn: &Connection, job_id: &str) -> Result<Option<JobRow>> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn setup() -> Connection {
-        open_database(None).unwrap()
-    }
+const JOB_COLUMNS: &str = "id, priority, status, job_type, data_json, created_at, started_at,
+     completed_at, error_message, result_json, attempts, retry_policy, next_run_at,
+     dependency_policy, progress_current, progress_total, progress_stage, progress_message,
+     progress_updated_at, timeout_ms, unique_key, queue, payload_version";
 
-    #[test]
-    fn test_open_in_memory() {
-        let conn = open_database(None);
-        assert!(conn.is_ok());
-    }
+fn query_stored_jobs(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<StoredJob>> {
+    let mut stmt = conn.prepare(sql).context("Failed to prepare job query")?;
 
-    #[test]
-    fn test_insert_and_get_next_pending() {
-        let conn = setup();
-        let data = serde_json::json!({"task": "send email"});
-        insert_job(&conn, "job-1", 2, &data).unwrap();
+    let rows = stmt
+        .query_map(params, |row| {
+            let retry_policy: Option<String> = row.get(11)?;
+            Ok(StoredJob {
+                id: row.get(0)?,
+                priority: row.get(1)?,
+                status: row.get(2)?,
+                job_type: row.get(3)?,
+                data: json_column(4, &row.get::<_, String>(4)?)?,
+                created_at: row.get(5)?,
+                started_at: row.get(6)?,
+                completed_at: row.get(7)?,
+                error_message: row.get(8)?,
+                result_json: row.get(9)?,
+                attempts: row.get(10)?,
+                retry_policy: retry_policy
+                    .map(|json| json_column(11, &json))
+                    .transpose()?,
+                next_run_at: row.get(12)?,
+                dependency_policy: row.get(13)?,
+                depends_on: Vec::new(),
+                progress: progress_columns(row, 14)?,
+                timeout: row.get::<_, Option<i64>>(19)?.map(ms_duration),
+                unique_key: row.get(20)?,
+                queue: row.get(21)?,
+                payload_version: row.get(22)?,
+            })
+        })
+        .context("Failed to execute job query")?;
 
-        let next = get_next_pending(&conn).unwrap();
-        assert!(next.is_some());
-        let (id, val) = next.unwrap();
-        assert_eq!(id, "job-1");
-        assert_eq!(val["task"], "send email");
+    let mut jobs = Vec::new();
+    for row in rows {
+        let mut job = row.context("Failed to read job row")?;
+        job.depends_on = get_dependencies(conn, &job.id)?;
+        jobs.push(job);
     }
+    Ok(jobs)
+}
 
-    #[test]
-    fn test_priority_ordering() {
+/// Read the five progress columns starting at `idx`. `None` if no progress
+/// has been reported.
+fn progress_columns(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<Option<JobProgress>> {
+    let current: Option<u32> = row.get(idx)?;
+    let Some(current) = current else {
+        return Ok(None);
+    };
+    Ok(Some(JobProgress {
+        current,
+        total: row.get::<_, Option<u32>>(idx + 1)?.unwrap_or(0),
+        stage: row.get(idx + 2)?,
+        message: row.get(idx + 3)?,
+        updated_at: row.get(idx + 4)?,
+    }))
+}
+
+/// Store the latest progress of a job.
+pub fn update_progress(conn: &Connection, job_id: &str, progress: &JobProgress) -> Result<()> {
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE queue_jobs
+         SET progress_current = ?1, progress_total = ?2, progress_stage = ?3,
+             progress_message = ?4, progress_updated_at = ?5
+         WHERE id = ?6",
+        params![
+            progress.current,
+            progress.total,
+            progress.stage,
+            progress.message,
+            now,
+            job_id
+        ],
+    )
+    .context("Failed to update job progress")?;
+    Ok(())
+}
+
+/// Get the last stored progress of a job (`None` if it has reported none).
+pub fn get_progress(conn: &Connection, job_id: &str) -> Result<Option<JobProgress>> {
+    conn.query_row(
+        "SELECT progress_current, progress_total, progress_stage, progress_message,
+                progress_updated_at
+         FROM queue_jobs WHERE id = ?1",
+        params![job_id],
+        |row| progress_columns(row, 0),
+    )
+    .optional()
+    .context("Failed to read job progress")
+    .map(Option::flatten)
+}
+
+/// Get a full job row by ID.
+pub fn get_stored_job(conn: &Connection, job_id: &str) -> Result<Option<StoredJob>> {
+    let jobs = query_stored_jobs(
+        conn,
+        &format!("SELECT {} FROM queue_jobs WHERE id = ?1", JOB_COLUMNS),
+        params![job_id],
+    )?;
+    Ok(jobs.into_iter().next())
+}
+
+/// Get the jobs matching `filter`, newest first.
+///
+/// Date bounds are compared with `julianday()`, so they may be given in any
+/// format SQLite understands, including RFC 3339.
+pub fn query_jobs(conn: &Connection, filter: &JobFilter) -> Result<Vec<StoredJob>> {
+    let (where_clause, mut values) = filter_clause(filter);
+    values.push(filter.limit.map_or(-1, i64::from).into());
+    values.push(i64::from(filter.offset).into());
+
+    query_stored_jobs(
+        conn,
+        &format!(
+            "SELECT {} FROM queue_jobs WHERE {}
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT ? OFFSET ?",
+            JOB_COLUMNS, where_clause
+        ),
+        rusqlite::params_from_iter(values),
+    )
+}
+
+/// Build the `WHERE` clause for `filter`. Pagination is left to the caller.
+fn filter_clause(filter: &JobFilter) -> (String, Vec<rusqlite::types::Value>) {
+    let mut clauses = vec!["1 = 1".to_string()];
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+    if !filter.statuses.is_empty() {
+        let placeholders = vec!["?"; filter.statuses.len()].join(", ");
+        clauses.push(format!("status IN ({})", placeholders));
+        values.extend(
+            filter
+                .statuses
+                .iter()
+                .map(|status| status.as_str().to_string().into()),
+        );
+    }
+    if let Some(job_type) = &filter.job_type {
+        clauses.push("job_type = ?".to_string());
+        values.push(job_type.clone().into());
+    }
+    if let Some(queue) = &filter.queue {
+        clauses.push("queue = ?".to_string());
+        values.push(queue.clone().into());
+    }
+    if let Some(after) = &filter.created_after {
+        clauses.push("julianday(created_at) >= julianday(?)".to_string());
+        values.push(after.clone().into());
+    }
+    if let Some(before) = &filter.created_before {
+        clauses.push("julianday(created_at) < julianday(?)".to_string());
+        values.push(before.clone().into());
+    }
+
+    (clauses.join(" AND "), values)
+}
+
+/// Move blocked dependents of a completed job to pending once all of their
+/// dependencies have completed. Returns the IDs of the unblocked jobs.
+pub fn unblock_dependents(conn: &Connection, job_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare(
+            "UPDATE queue_jobs SET status = 'pending'
+             WHERE status = 'blocked'
+             AND id IN (SELECT job_id FROM queue_job_dependencies WHERE depends_on = ?1)
+             AND NOT EXISTS (
+                 SELECT 1 FROM queue_job_dependencies d
+                 JOIN queue_jobs parent ON parent.id = d.depends_on
+                 WHERE d.job_id = queue_jobs.id AND parent.status != 'completed'
+             )
+             RETURNING id",
+        )
+        .context("Failed to prepare unblock_dependents query")?;
+
+    let rows = stmt
+        .query_map(params![job_id], |row| row.get(0))
+        .context("Failed to unblock dependent jobs")?;
+
+    let mut unblocked = Vec::new();
+    for row in rows {
+        unblocked.push(row.context("Failed to read unblocked job id")?);
+    }
+    Ok(unblocked)
+}
+
+/// Propagate a failed or cancelled job to everything that depends on it,
+/// directly or transitively. Each blocked dependent is cancelled or failed
+/// according to its dependency policy.
+///
+/// Returns `(job_id, new_status)` for every job that was changed.
+pub fn fail_dependents(conn: &Connection, job_id: &str) -> Result<Vec<(String, String)>> {
+    in_transaction(conn, |conn| {
+        let mut changed = Vec::new();
+        let mut queue = vec![job_id.to_string()];
+
+        while let Some(parent_id) = queue.pop() {
+            let parent_status: String = conn
+                .query_row(
+                    "SELECT status FROM queue_jobs WHERE id = ?1",
+                    params![parent_id],
+                    |row| row.get(0),
+                )
+                .context("Failed to read job status")?;
+
+            let children: Vec<String> = {
+                let mut stmt = conn
+                    .prepare(
+                        "SELECT j.id FROM queue_jobs j
+                         JOIN queue_job_dependencies d ON d.job_id = j.id
+                         WHERE d.depends_on = ?1 AND j.status = 'blocked'",
+                    )
+                    .context("Failed to prepare dependents query")?;
+                let rows = stmt
+                    .query_map(params![parent_id], |row| row.get(0))
+                    .context("Failed to query dependent jobs")?;
+                rows.collect::<rusqlite::Result<_>>()
+                    .context("Failed to read dependent job id")?
+            };
+
+            for child_id in children {
+                let status = apply_dependency_policy(conn, &child_id, &parent_id, &parent_status)?;
+                changed.push((child_id.clone(), status.to_string()));
+                queue.push(child_id);
+            }
+        }
+
+        Ok(changed)
+    })
+}
+
+/// Cancel or fail `job_id` because `parent_id` ended with `parent_status`.
+fn apply_dependency_policy(
+    conn: &Connection,
+    job_id: &str,
+    parent_id: &str,
+    parent_status: &str,
+) -> Result<&'static str> {
+    let policy: String = conn
+        .query_row(
+            "SELECT dependency_policy FROM queue_jobs WHERE id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )
+        .context("Failed to read dependency policy")?;
+    let status = match DependencyFailurePolicy::parse(&policy) {
+        Some(DependencyFailurePolicy::Fail) => "failed",
+        _ => "cancelled",
+    };
+
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE queue_jobs SET status = ?1, completed_at = ?2, error_message = ?3 WHERE id = ?4",
+        params![
+            status,
+            now,
+            format!("Dependency '{}' {}", parent_id, parent_status),
+            job_id
+        ],
+    )
+    .context("Failed to update dependent job")?;
+    Ok(status)
+}
+
+/// Get the IDs of the jobs `job_id` depends on.
+pub fn get_dependencies(conn: &Connection, job_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT depends_on FROM queue_job_dependencies WHERE job_id = ?1")
+        .context("Failed to prepare get_dependencies query")?;
+    let rows = stmt
+        .query_map(params![job_id], |row| row.get(0))
+        .context("Failed to query job dependencies")?;
+
+    let mut dependencies = Vec::new();
+    for row in rows {
+        dependencies.push(row.context("Failed to read dependency id")?);
+    }
+    Ok(dependencies)
+}
+
+/// Insert a new recurring schedule.
+pub fn insert_schedule(conn: &Connection, schedule: &ScheduleInfo) -> Result<()> {
+    let retry_policy = schedule
+        .retry_policy
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    conn.execute(
+        "INSERT INTO queue_schedules
+            (id, spec_json, job_type, priority, data_json, retry_policy, paused, next_run_at,
+             payload_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            schedule.id,
+            serde_json::to_string(&schedule.spec)?,
+            schedule.job_type,
+            schedule.priority.as_i32(),
+            schedule.data_json,
+            retry_policy,
+            schedule.paused,
+            schedule.next_run_at,
+            schedule.payload_version
+        ],
+    )
+    .context("Failed to insert queue schedule")?;
+    Ok(())
+}
+
+const SCHEDULE_COLUMNS: &str = "id, spec_json, job_type, priority, data_json, retry_policy,
+     paused, next_run_at, last_run_at, created_at, payload_version";
+
+fn query_schedules(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<ScheduleInfo>> {
+    let mut stmt = conn
+        .prepare(sql)
+        .context("Failed to prepare schedule query")?;
+
+    let rows = stmt
+        .query_map(params, |row| {
+            let retry_policy: Option<String> = row.get(5)?;
+            Ok(ScheduleInfo {
+                id: row.get(0)?,
+                spec: json_column(1, &row.get::<_, String>(1)?)?,
+                job_type: row.get(2)?,
+                priority: QueuePriority::from_i32(row.get(3)?),
+                data_json: row.get(4)?,
+                retry_policy: retry_policy.map(|json| json_column(5, &json)).transpose()?,
+                paused: row.get(6)?,
+                next_run_at: row.get(7)?,
+                last_run_at: row.get(8)?,
+                created_at: row.get(9)?,
+                payload_version: row.get(10)?,
+            })
+        })
+        .context("Failed to execute schedule query")?;
+
+    let mut schedules = Vec::new();
+    for row in rows {
+        schedules.push(row.context("Failed to read schedule row")?);
+    }
+    Ok(schedules)
+}
+
+/// Parse a JSON-encoded column, reporting failures as a conversion error.
+fn json_column<T: serde::de::DeserializeOwned>(idx: usize, json: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// List all schedules ordered by their next run time.
+pub fn list_schedules(conn: &Connection) -> Result<Vec<ScheduleInfo>> {
+    query_schedules(
+        conn,
+        &format!(
+            "SELECT {} FROM queue_schedules ORDER BY next_run_at ASC",
+            SCHEDULE_COLUMNS
+        ),
+        [],
+    )
+}
+
+/// Get all active schedules whose next run time is at or before `now`.
+pub fn get_due_schedules(conn: &Connection, now: &str) -> Result<Vec<ScheduleInfo>> {
+    query_schedules(
+        conn,
+        &format!(
+            "SELECT {} FROM queue_schedules
+             WHERE paused = 0 AND next_run_at <= ?1
+             ORDER BY next_run_at ASC",
+            SCHEDULE_COLUMNS
+        ),
+        params![now],
+    )
+}
+
+/// Enqueue a job for a due schedule and advance the schedule to
+/// `next_run_at` in one transaction. A schedule with no further run
+/// time is deleted.
+pub fn fire_schedule(
+    conn: &Connection,
+    schedule: &ScheduleInfo,
+    job_id: &str,
+    next_run_at: Option<&str>,
+) -> Result<()> {
+    let (data, options) = scheduled_job(schedule)?;
+
+    in_transaction(conn, |conn| {
+        insert_job_with_options(conn, job_id, schedule.priority.as_i32(), &data, &options)?;
+
+        match next_run_at {
+            Some(next_run_at) => {
+                let now = chrono::Utc::now().to_rfc3339();
+                conn.execute(
+                    "UPDATE queue_schedules SET next_run_at = ?1, last_run_at = ?2 WHERE id = ?3",
+                    params![next_run_at, now, schedule.id],
+                )
+                .context("Failed to advance queue schedule")?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM queue_schedules WHERE id = ?1",
+                    params![schedule.id],
+                )
+                .context("Failed to delete finished queue schedule")?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Pause or resume a schedule. Returns `false` if the schedule doesn't exist.
+pub fn set_schedule_paused(conn: &Connection, schedule_id: &str, paused: bool) -> Result<bool> {
+    let count = conn
+        .execute(
+            "UPDATE queue_schedules SET paused = ?1 WHERE id = ?2",
+            params![paused, schedule_id],
+        )
+        .context("Failed to update queue schedule")?;
+    Ok(count > 0)
+}
+
+/// Delete a schedule. Jobs it already created are kept.
+/// Returns `false` if the schedule doesn't exist.
+pub fn delete_schedule(conn: &Connection, schedule_id: &str) -> Result<bool> {
+    let count = conn
+        .execute(
+            "DELETE FROM queue_schedules WHERE id = ?1",
+            params![schedule_id],
+        )
+        .context("Failed to delete queue schedule")?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::ScheduleSpec;
+
+    fn setup() -> Connection {
+        open_database(None).unwrap()
+    }
+
+    fn due_schedule(id: &str, spec: ScheduleSpec, data_json: &str) -> ScheduleInfo {
+        ScheduleInfo {
+            id: id.to_string(),
+            spec,
+            job_type: Some("test".to_string()),
+            priority: QueuePriority::Normal,
+            data_json: data_json.to_string(),
+            retry_policy: None,
+            paused: false,
+            next_run_at: (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339(),
+            last_run_at: None,
+            created_at: None,
+            payload_version: 1,
+        }
+    }
+
+    #[test]
+    fn test_open_in_memory() {
+        let conn = open_database(None);
+        assert!(conn.is_ok());
+    }
+
+    #[test]
+    fn test_insert_and_get_next_pending() {
+        let conn = setup();
+        let data = serde_json::json!({"task": "send email"});
+        insert_job(&conn, "job-1", 2, &data).unwrap();
+
+        let next = get_next_pending(&conn).unwrap();
+        assert!(next.is_some());
+        let (id, val) = next.unwrap();
+        assert_eq!(id, "job-1");
+        assert_eq!(val["task"], "send email");
+    }
+
+    #[test]
+    fn test_priority_ordering() {
         let conn = setup();
         insert_job(&conn, "low-1", 3, &serde_json::json!({"p": "low"})).unwrap();
         insert_job(&conn, "high-1", 1, &serde_json::json!({"p": "high"})).unwrap();
@@ -364,6 +1930,189 @@ mod tests {
         assert_eq!(next.unwrap().0, "job-1");
     }
 
+    #[test]
+    fn test_interrupted_job_is_requeued() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        assert!(!mark_interrupted(&conn, "job-1").unwrap());
+        mark_processing(&conn, "job-1").unwrap();
+        assert!(mark_interrupted(&conn, "job-1").unwrap());
+        assert_eq!(get_job(&conn, "job-1").unwrap().unwrap().2, "interrupted");
+        assert_eq!(
+            get_attempts(&conn, "job-1").unwrap()[0].outcome,
+            "interrupted"
+        );
+
+        assert_eq!(requeue_interrupted(&conn).unwrap(), 1);
+        let stored = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(stored.status, "pending");
+        assert!(stored.error_message.is_none());
+    }
+
+    #[test]
+    fn test_leases_are_renewed_and_reclaimed() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "job-1", 2, &json).unwrap();
+        insert_job(&conn, "job-2", 2, &json).unwrap();
+
+        let long = Duration::from_secs(60);
+        let claimed = claim_next_leased(&conn, DEFAULT_QUEUE, &[], true, "worker-a", long).unwrap();
+        assert_eq!(claimed.unwrap().id, "job-1");
+        assert!(renew_lease(&conn, "job-1", "worker-a", long).unwrap());
+        assert!(!renew_lease(&conn, "job-1", "worker-b", long).unwrap());
+
+        // A lease that has run out is reclaimed, a live one is not
+        claim_next_leased(&conn, DEFAULT_QUEUE, &[], true, "worker-b", Duration::ZERO).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(reclaim_expired_leases(&conn).unwrap(), vec!["job-2"]);
+        assert_eq!(get_job(&conn, "job-2").unwrap().unwrap().2, "pending");
+        assert_eq!(
+            get_attempts(&conn, "job-2").unwrap()[0].outcome,
+            "lease_expired"
+        );
+        assert!(!renew_lease(&conn, "job-2", "worker-b", long).unwrap());
+
+        // Startup recovery leaves leased jobs to their worker
+        assert_eq!(requeue_interrupted(&conn).unwrap(), 0);
+        assert_eq!(get_job(&conn, "job-1").unwrap().unwrap().2, "processing");
+
+        // A job cancelled behind its worker's back can't be renewed
+        cancel_job(&conn, "job-1").unwrap();
+        assert!(!renew_lease(&conn, "job-1", "worker-a", long).unwrap());
+    }
+
+    #[test]
+    fn test_checkpoints_survive_retries_until_finished() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "job-1", 2, &json).unwrap();
+        assert!(load_checkpoint(&conn, "job-1").unwrap().is_none());
+
+        mark_processing(&conn, "job-1").unwrap();
+        save_checkpoint(&conn, "job-1", &serde_json::json!({ "file": 10 })).unwrap();
+        save_checkpoint(&conn, "job-1", &serde_json::json!({ "file": 20 })).unwrap();
+        let retry_at = chrono::Utc::now().to_rfc3339();
+        schedule_retry(&conn, "job-1", "boom", &retry_at).unwrap();
+        assert_eq!(
+            load_checkpoint(&conn, "job-1").unwrap(),
+            Some(serde_json::json!({ "file": 20 }))
+        );
+
+        mark_processing(&conn, "job-1").unwrap();
+        mark_completed(&conn, "job-1").unwrap();
+        assert!(load_checkpoint(&conn, "job-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_job_logs() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        let first = insert_log(&conn, "job-1", LogLevel::Info, "Starting", &Value::Null).unwrap();
+        let fields = serde_json::json!({ "file": "a.png", "bytes": 1024 });
+        insert_log(&conn, "job-1", LogLevel::Warn, "Slow disk", &fields).unwrap();
+
+        let logs = get_logs(&conn, "job-1", None).unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0], first);
+        assert_eq!(logs[1].level, LogLevel::Warn);
+        assert_eq!(logs[1].fields, fields);
+
+        let newer = get_logs(&conn, "job-1", Some(first.id)).unwrap();
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].message, "Slow disk");
+    }
+
+    #[test]
+    fn test_queue_metrics() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        let typed = |job_type: &str| JobOptions {
+            job_type: Some(job_type.to_string()),
+            ..JobOptions::default()
+        };
+        for (i, millis) in [100, 200, 300, 400].iter().enumerate() {
+            let id = format!("done-{}", i);
+            insert_job_with_options(&conn, &id, 2, &json, &typed("thumb")).unwrap();
+            mark_processing(&conn, &id).unwrap();
+            mark_completed(&conn, &id).unwrap();
+            // Give each job a known run time
+            let started = chrono::Utc::now() - chrono::Duration::milliseconds(*millis);
+            conn.execute(
+                "UPDATE queue_jobs SET started_at = ?1 WHERE id = ?2",
+                params![started.to_rfc3339(), id],
+            )
+            .unwrap();
+        }
+        insert_job_with_options(&conn, "failed", 2, &json, &typed("thumb")).unwrap();
+        mark_processing(&conn, "failed").unwrap();
+        mark_failed(&conn, "failed", "boom").unwrap();
+        insert_job_with_options(&conn, "waiting", 2, &json, &typed("thumb")).unwrap();
+        insert_job_with_options(&conn, "other", 2, &json, &typed("other")).unwrap();
+
+        let metrics = queue_metrics(&conn, Duration::from_secs(600)).unwrap();
+        assert_eq!(metrics.depth, 2);
+        assert_eq!(metrics.window_secs, 600);
+        let names: Vec<&str> = metrics
+            .job_types
+            .iter()
+            .map(|m| m.job_type.as_str())
+            .collect();
+        assert_eq!(names, vec!["other", "thumb"]);
+
+        let thumb = &metrics.job_types[1];
+        assert_eq!(thumb.counts["completed"], 4);
+        assert_eq!(thumb.counts["failed"], 1);
+        assert_eq!(thumb.depth, 1);
+        assert_eq!((thumb.completed, thumb.failed), (4, 1));
+        assert!((thumb.failure_rate - 0.2).abs() < 1e-9);
+        assert!((thumb.throughput_per_minute - 0.4).abs() < 1e-9);
+        let close = |value: Option<f64>, expected: f64| (value.unwrap() - expected).abs() < 50.0;
+        assert!(close(thumb.avg_duration_ms, 250.0));
+        assert!(close(thumb.p50_duration_ms, 200.0));
+        assert!(close(thumb.p95_duration_ms, 400.0));
+        assert!(metrics.job_types[0].avg_duration_ms.is_none());
+    }
+
+    #[test]
+    fn test_priority_aging_claims_waiting_jobs() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "low", QueuePriority::Low.as_i32(), &json).unwrap();
+        for id in ["high-1", "high-2", "high-3"] {
+            insert_job(&conn, id, QueuePriority::High.as_i32(), &json).unwrap();
+        }
+        let aging = PriorityAging::new(Duration::from_secs(60));
+        let lease = ("worker", Duration::from_secs(30));
+        let claim_at = |aging: Option<&PriorityAging>, later: i64| {
+            let aged_at = chrono::Utc::now() + chrono::Duration::seconds(later);
+            let aging = aging.map(|aging| (aging, aged_at));
+            claim_next_aged(&conn, DEFAULT_QUEUE, &[], true, lease, aging)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        // Not waited long enough: strict priority order
+        assert_eq!(claim_at(Some(&aging), 0), "high-1");
+        // Two intervals later the low job has aged to High and is older
+        assert_eq!(claim_at(Some(&aging), 150), "low");
+        assert_eq!(claim_at(Some(&aging), 150), "high-2");
+
+        // The aging time doesn't make delayed jobs due early
+        let options = JobOptions {
+            next_run_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "delayed", 1, &json, &options).unwrap();
+        assert_eq!(claim_at(Some(&aging), 7200), "high-3");
+        assert!(
+            claim_next_aged(&conn, DEFAULT_QUEUE, &[], true, lease, None)
+                .unwrap()
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_update_priority() {
         let conn = setup();
@@ -374,6 +2123,61 @@ mod tests {
         assert_eq!(job.1, 1);
     }
 
+    #[test]
+    fn test_update_priorities_skips_jobs_that_are_not_pending() {
+        let conn = setup();
+        for id in ["a", "b", "running"] {
+            insert_job(&conn, id, 3, &serde_json::json!({})).unwrap();
+        }
+        mark_processing(&conn, "running").unwrap();
+
+        let ids: Vec<String> = ["a", "b", "running", "missing"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let mut updated = update_priorities(&conn, &ids, 1).unwrap();
+        updated.sort();
+        assert_eq!(updated, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(get_job(&conn, "b").unwrap().unwrap().1, 1);
+        assert_eq!(get_job(&conn, "running").unwrap().unwrap().1, 3);
+    }
+
+    #[test]
+    fn test_cancel_jobs_by_filter() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        let typed = |job_type: &str| JobOptions {
+            job_type: Some(job_type.to_string()),
+            ..JobOptions::default()
+        };
+        for id in ["thumb-1", "thumb-2", "thumb-done"] {
+            insert_job_with_options(&conn, id, 2, &json, &typed("thumb")).unwrap();
+        }
+        insert_job_with_options(&conn, "other", 2, &json, &typed("other")).unwrap();
+        mark_processing(&conn, "thumb-2").unwrap();
+        mark_processing(&conn, "thumb-done").unwrap();
+        mark_completed(&conn, "thumb-done").unwrap();
+
+        let mut cancelled = cancel_jobs(&conn, &JobFilter::new().with_job_type("thumb")).unwrap();
+        cancelled.sort();
+        assert_eq!(
+            cancelled,
+            vec![
+                ("thumb-1".to_string(), "pending".to_string()),
+                ("thumb-2".to_string(), "processing".to_string()),
+            ]
+        );
+        assert_eq!(
+            get_job(&conn, "thumb-done").unwrap().unwrap().2,
+            "completed"
+        );
+        assert_eq!(get_job(&conn, "other").unwrap().unwrap().2, "pending");
+
+        // Statuses that can't be cancelled are ignored
+        let filter = JobFilter::new().with_status(QueueJobStatus::Completed);
+        assert!(cancel_jobs(&conn, &filter).unwrap().is_empty());
+    }
+
     #[test]
     fn test_list_all_jobs() {
         let conn = setup();
@@ -413,6 +2217,641 @@ mod tests {
         assert_eq!(count, 1);
     }
 
+    #[test]
+    fn test_mark_processing_counts_attempts() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+
+        let (attempts, policy) = get_retry_state(&conn, "job-1").unwrap();
+        assert_eq!(attempts, 1);
+        assert!(policy.is_none());
+    }
+
+    #[test]
+    fn test_retry_policy_roundtrip() {
+        let conn = setup();
+        let options = JobOptions {
+            retry_policy: Some(RetryPolicy::new(5)),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "job-1", 2, &serde_json::json!({}), &options).unwrap();
+
+        let (_, policy) = get_retry_state(&conn, "job-1").unwrap();
+        assert_eq!(policy, Some(RetryPolicy::new(5)));
+    }
+
+    #[test]
+    fn test_schedule_retry_delays_pickup() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+
+        let later = (chrono::Utc::now() + chrono::Duration::minutes(5)).to_rfc3339();
+        schedule_retry(&conn, "job-1", "flaky", &later).unwrap();
+
+        let job = get_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.2, "pending");
+        assert_eq!(job.4.as_deref(), Some("flaky"));
+        // Not eligible until the delay has passed
+        assert!(get_next_pending(&conn).unwrap().is_none());
+
+        let earlier = (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+        conn.execute(
+            "UPDATE queue_jobs SET next_run_at = ?1 WHERE id = 'job-1'",
+            params![earlier],
+        )
+        .unwrap();
+        assert_eq!(get_next_pending(&conn).unwrap().unwrap().0, "job-1");
+    }
+
+    #[test]
+    fn test_insert_job_with_run_at() {
+        let conn = setup();
+        let options = JobOptions {
+            next_run_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "later", 2, &serde_json::json!({}), &options).unwrap();
+        insert_job(&conn, "now", 3, &serde_json::json!({})).unwrap();
+
+        // The delayed job is skipped even though it has a higher priority
+        let next = get_next_pending(&conn).unwrap().unwrap();
+        assert_eq!(next.0, "now");
+    }
+
+    #[test]
+    fn test_fire_schedule() {
+        let conn = setup();
+        let spec = ScheduleSpec::Interval(std::time::Duration::from_secs(60));
+        insert_schedule(&conn, &due_schedule("s1", spec.clone(), r#"{"n":1}"#)).unwrap();
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let schedules = get_due_schedules(&conn, &now).unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].spec, spec);
+
+        let next = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc3339();
+        fire_schedule(&conn, &schedules[0], "job-1", Some(&next)).unwrap();
+
+        assert!(get_due_schedules(&conn, &now).unwrap().is_empty());
+        let job = get_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.2, "pending");
+        assert!(job.3.contains("\"n\":1"));
+        let claimed = claim_next(&conn, &["test".to_string()], false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(claimed.job_type.as_deref(), Some("test"));
+
+        let schedule = &list_schedules(&conn).unwrap()[0];
+        assert_eq!(schedule.next_run_at, next);
+        assert!(schedule.last_run_at.is_some());
+    }
+
+    #[test]
+    fn test_pause_and_delete_schedule() {
+        let conn = setup();
+        let spec = ScheduleSpec::Cron("0 * * * *".to_string());
+        insert_schedule(&conn, &due_schedule("s1", spec, "{}")).unwrap();
+
+        assert!(set_schedule_paused(&conn, "s1", true).unwrap());
+        let now = chrono::Utc::now().to_rfc3339();
+        assert!(get_due_schedules(&conn, &now).unwrap().is_empty());
+        assert!(list_schedules(&conn).unwrap()[0].paused);
+
+        assert!(delete_schedule(&conn, "s1").unwrap());
+        assert!(!delete_schedule(&conn, "s1").unwrap());
+        assert!(list_schedules(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_claim_next_marks_processing() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({"n": 1})).unwrap();
+        insert_job(&conn, "job-2", 2, &serde_json::json!({"n": 2})).unwrap();
+
+        let first = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_eq!(first.id, "job-1");
+        assert_eq!(first.data["n"], 1);
+        let job = get_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.2, "processing");
+        assert_eq!(get_retry_state(&conn, "job-1").unwrap().0, 1);
+
+        // A second claim never returns the same job
+        let second = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_eq!(second.id, "job-2");
+        assert!(claim_next(&conn, &[], true).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_claim_next_only_claims_listed_types() {
+        let conn = setup();
+        let gpu = JobOptions {
+            job_type: Some("gpu".to_string()),
+            ..JobOptions::default()
+        };
+        let light = JobOptions {
+            job_type: Some("light".to_string()),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "gpu-1", 1, &serde_json::json!({}), &gpu).unwrap();
+        insert_job_with_options(&conn, "light-1", 3, &serde_json::json!({}), &light).unwrap();
+
+        insert_job(&conn, "untyped-1", 1, &serde_json::json!({})).unwrap();
+
+        // "gpu" is at capacity, so only "light" may be claimed
+        let light_only = ["light".to_string()];
+        let claimed = claim_next(&conn, &light_only, false).unwrap().unwrap();
+        assert_eq!(claimed.id, "light-1");
+        assert_eq!(claimed.job_type.as_deref(), Some("light"));
+        assert!(claim_next(&conn, &light_only, false).unwrap().is_none());
+
+        // Unregistered types stay pending
+        let job = get_job(&conn, "gpu-1").unwrap().unwrap();
+        assert_eq!(job.2, "pending");
+
+        assert_eq!(
+            claim_next(&conn, &[], true).unwrap().unwrap().id,
+            "untyped-1"
+        );
+        let gpu_only = ["gpu".to_string()];
+        assert_eq!(
+            claim_next(&conn, &gpu_only, false).unwrap().unwrap().id,
+            "gpu-1"
+        );
+    }
+
+    fn with_deps(depends_on: &[&str], policy: DependencyFailurePolicy) -> JobOptions {
+        JobOptions {
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            dependency_policy: policy,
+            ..JobOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_dependencies_block_until_complete() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "download", 2, &json).unwrap();
+        insert_job(&conn, "fetch-config", 2, &json).unwrap();
+        let options = with_deps(
+            &["download", "fetch-config"],
+            DependencyFailurePolicy::Cancel,
+        );
+        let status = insert_job_with_options(&conn, "convert", 1, &json, &options).unwrap();
+        assert_eq!(status, "blocked");
+        assert_eq!(get_dependencies(&conn, "convert").unwrap().len(), 2);
+
+        // Blocked jobs are never claimed
+        let first = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_ne!(first.id, "convert");
+        mark_completed(&conn, &first.id).unwrap();
+        assert!(unblock_dependents(&conn, &first.id).unwrap().is_empty());
+
+        let second = claim_next(&conn, &[], true).unwrap().unwrap();
+        mark_completed(&conn, &second.id).unwrap();
+        assert_eq!(
+            unblock_dependents(&conn, &second.id).unwrap(),
+            vec!["convert"]
+        );
+
+        assert_eq!(claim_next(&conn, &[], true).unwrap().unwrap().id, "convert");
+    }
+
+    #[test]
+    fn test_dependency_on_completed_job_is_pending() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "parent", 2, &json).unwrap();
+        mark_processing(&conn, "parent").unwrap();
+        mark_completed(&conn, "parent").unwrap();
+
+        let options = with_deps(&["parent"], DependencyFailurePolicy::Cancel);
+        let status = insert_job_with_options(&conn, "child", 2, &json, &options).unwrap();
+        assert_eq!(status, "pending");
+    }
+
+    #[test]
+    fn test_missing_dependency_fails_insert() {
+        let conn = setup();
+        let options = with_deps(&["missing"], DependencyFailurePolicy::Cancel);
+        let result = insert_job_with_options(&conn, "child", 2, &serde_json::json!({}), &options);
+        assert!(result.is_err());
+        assert!(get_job(&conn, "child").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_failure_propagates_through_dependents() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "download", 2, &json).unwrap();
+        let fail = with_deps(&["download"], DependencyFailurePolicy::Fail);
+        insert_job_with_options(&conn, "convert", 2, &json, &fail).unwrap();
+        let cancel = with_deps(&["convert"], DependencyFailurePolicy::Cancel);
+        insert_job_with_options(&conn, "warm-up", 2, &json, &cancel).unwrap();
+
+        mark_processing(&conn, "download").unwrap();
+        mark_failed(&conn, "download", "network down").unwrap();
+        let mut changed = fail_dependents(&conn, "download").unwrap();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![
+                ("convert".to_string(), "failed".to_string()),
+                ("warm-up".to_string(), "cancelled".to_string()),
+            ]
+        );
+
+        let convert = get_job(&conn, "convert").unwrap().unwrap();
+        assert_eq!(convert.4.as_deref(), Some("Dependency 'download' failed"));
+        let warm_up = get_job(&conn, "warm-up").unwrap().unwrap();
+        assert_eq!(warm_up.4.as_deref(), Some("Dependency 'convert' failed"));
+
+        // Adding a job that depends on a failed job applies the policy immediately
+        let status = insert_job_with_options(&conn, "late", 2, &json, &cancel).unwrap();
+        assert_eq!(status, "cancelled");
+    }
+
+    #[test]
+    fn test_cancel_blocked_job() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "parent", 2, &json).unwrap();
+        let options = with_deps(&["parent"], DependencyFailurePolicy::Cancel);
+        insert_job_with_options(&conn, "child", 2, &json, &options).unwrap();
+
+        assert_eq!(cancel_job(&conn, "child").unwrap(), "blocked");
+    }
+
+    #[test]
+    fn test_completed_output_is_stored() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+        mark_completed_with_output(&conn, "job-1", Some(r#"{"frames":24}"#)).unwrap();
+
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "completed");
+        assert_eq!(job.result_json.as_deref(), Some(r#"{"frames":24}"#));
+        assert!(job.completed_at.is_some());
+        assert_eq!(job.attempts, 1);
+    }
+
+    #[test]
+    fn test_query_jobs_filters_and_paginates() {
+        let conn = setup();
+        let typed = |job_type: &str| JobOptions {
+            job_type: Some(job_type.to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::json!({});
+        for i in 0..5 {
+            insert_job_with_options(&conn, &format!("a-{}", i), 2, &json, &typed("a")).unwrap();
+        }
+        insert_job_with_options(&conn, "b-0", 2, &json, &typed("b")).unwrap();
+        mark_processing(&conn, "a-1").unwrap();
+        mark_failed(&conn, "a-1", "boom").unwrap();
+
+        let all_a = query_jobs(&conn, &JobFilter::new().with_job_type("a")).unwrap();
+        assert_eq!(all_a.len(), 5);
+        // Newest first
+        assert_eq!(all_a[0].id, "a-4");
+
+        let page = query_jobs(&conn, &JobFilter::new().with_job_type("a").paginate(2, 2)).unwrap();
+        let ids: Vec<_> = page.iter().map(|job| job.id.as_str()).collect();
+        assert_eq!(ids, vec!["a-2", "a-1"]);
+
+        let failed =
+            query_jobs(&conn, &JobFilter::new().with_status(QueueJobStatus::Failed)).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].error_message.as_deref(), Some("boom"));
+
+        let now = chrono::Utc::now();
+        let future = query_jobs(
+            &conn,
+            &JobFilter::new().created_after(now + chrono::Duration::hours(1)),
+        )
+        .unwrap();
+        assert!(future.is_empty());
+        let past = query_jobs(
+            &conn,
+            &JobFilter::new().created_before(now + chrono::Duration::hours(1)),
+        )
+        .unwrap();
+        assert_eq!(past.len(), 6);
+    }
+
+    #[test]
+    fn test_progress_roundtrip() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        assert!(get_progress(&conn, "job-1").unwrap().is_none());
+        assert!(get_progress(&conn, "missing").unwrap().is_none());
+
+        let progress = JobProgress {
+            current: 3,
+            total: 10,
+            stage: Some("encoding".to_string()),
+            message: Some("Frame 3 of 10".to_string()),
+            updated_at: None,
+        };
+        update_progress(&conn, "job-1", &progress).unwrap();
+
+        let stored = get_progress(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(stored.current, 3);
+        assert_eq!(stored.total, 10);
+        assert_eq!(stored.stage.as_deref(), Some("encoding"));
+        assert_eq!(stored.message.as_deref(), Some("Frame 3 of 10"));
+        assert!(stored.updated_at.is_some());
+
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.progress.unwrap().current, 3);
+    }
+
+    #[test]
+    fn test_fresh_database_is_at_latest_version() {
+        let conn = setup();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(migrate(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_upgrade_v1_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        // A database written before versioning: v1 tables, user_version 0
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO queue_jobs (id, priority, status, data_json, started_at, error_message)
+             VALUES ('old-1', 1, 'failed', '{\"n\":1}', '2024-01-01 00:00:00', 'boom')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO queue_jobs (id, status, data_json) VALUES ('old-2', 'pending', '{}')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        assert_eq!(migrate(&conn).unwrap(), MIGRATIONS.len() as u32);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        let old = get_stored_job(&conn, "old-1").unwrap().unwrap();
+        assert_eq!(old.priority, 1);
+        assert_eq!(old.status, "failed");
+        assert_eq!(old.data["n"], 1);
+        assert_eq!(old.error_message.as_deref(), Some("boom"));
+        assert_eq!(old.attempts, 1);
+        assert_eq!(old.dependency_policy, "cancel");
+        assert!(old.job_type.is_none());
+
+        // Legacy rows are still claimable, and the new schema works end to end
+        let claimed = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_eq!(claimed.id, "old-2");
+        let json = serde_json::json!({});
+        let options = with_deps(&["old-2"], DependencyFailurePolicy::Cancel);
+        assert_eq!(
+            insert_job_with_options(&conn, "new-1", 2, &json, &options).unwrap(),
+            "blocked"
+        );
+        assert!(list_schedules(&conn).unwrap().is_empty());
+
+        // Re-running is a no-op
+        assert_eq!(migrate(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let conn = setup();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        assert!(migrate(&conn).is_err());
+    }
+
+    #[test]
+    fn test_finishing_does_not_overwrite_cancelled() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+        cancel_job(&conn, "job-1").unwrap();
+
+        assert!(!mark_completed_with_output(&conn, "job-1", Some("late")).unwrap());
+        mark_failed(&conn, "job-1", "late").unwrap();
+        assert!(!mark_timed_out(&conn, "job-1", Duration::from_secs(1)).unwrap());
+
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "cancelled");
+        assert!(job.result_json.is_none());
+    }
+
+    #[test]
+    fn test_timeout_roundtrip() {
+        let conn = setup();
+        let options = JobOptions {
+            timeout: Some(Duration::from_millis(1500)),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "job-1", 2, &serde_json::json!({}), &options).unwrap();
+
+        let claimed = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_eq!(claimed.timeout, Some(Duration::from_millis(1500)));
+        assert!(mark_timed_out(&conn, "job-1", Duration::from_millis(1500)).unwrap());
+
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "timed_out");
+        assert!(job.completed_at.is_some());
+        assert!(job.error_message.unwrap().contains("Timed out"));
+    }
+
+    #[test]
+    fn test_attempt_history() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+        schedule_retry(&conn, "job-1", "first error", "2000-01-01T00:00:00+00:00").unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+        mark_dead(&conn, "job-1", "second error").unwrap();
+
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "dead");
+
+        let attempts = get_attempts(&conn, "job-1").unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].attempt, 1);
+        assert_eq!(attempts[0].outcome, "failed");
+        assert_eq!(attempts[0].error.as_deref(), Some("first error"));
+        assert!(attempts[0].started_at.is_some());
+        assert_eq!(attempts[1].attempt, 2);
+        assert_eq!(attempts[1].error.as_deref(), Some("second error"));
+    }
+
+    #[test]
+    fn test_requeue_job() {
+        let conn = setup();
+        insert_job(&conn, "job-1", 2, &serde_json::json!({})).unwrap();
+        mark_processing(&conn, "job-1").unwrap();
+        mark_failed(&conn, "job-1", "missing model").unwrap();
+
+        assert_eq!(requeue_job(&conn, "job-1").unwrap(), "failed");
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "pending");
+        assert_eq!(job.attempts, 0);
+        assert!(job.error_message.is_none());
+        assert!(job.completed_at.is_none());
+        // History survives the requeue
+        assert_eq!(get_attempts(&conn, "job-1").unwrap().len(), 1);
+
+        // Pending and missing jobs can't be requeued
+        assert!(requeue_job(&conn, "job-1").is_err());
+        assert!(requeue_job(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn test_payload_version_and_incompatible_jobs() {
+        let conn = setup();
+        let options = JobOptions {
+            payload_version: Some(2),
+            ..JobOptions::default()
+        };
+        insert_job_with_options(&conn, "job-1", 2, &serde_json::json!({"a": 1}), &options).unwrap();
+        insert_job(&conn, "job-2", 2, &serde_json::json!({})).unwrap();
+        assert_eq!(
+            get_stored_job(&conn, "job-2")
+                .unwrap()
+                .unwrap()
+                .payload_version,
+            1
+        );
+
+        let claimed = claim_next(&conn, &[], true).unwrap().unwrap();
+        assert_eq!(claimed.id, "job-1");
+        assert_eq!(claimed.payload_version, 2);
+
+        update_payload(&conn, "job-1", &serde_json::json!({"b": 1}), 3).unwrap();
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.data, serde_json::json!({"b": 1}));
+        assert_eq!(job.payload_version, 3);
+
+        assert!(mark_incompatible(&conn, "job-1", "no upcaster").unwrap());
+        assert!(!mark_incompatible(&conn, "job-2", "not processing").unwrap());
+        let job = get_stored_job(&conn, "job-1").unwrap().unwrap();
+        assert_eq!(job.status, "incompatible");
+        assert_eq!(job.error_message.as_deref(), Some("no upcaster"));
+        assert_eq!(
+            get_attempts(&conn, "job-1").unwrap()[0].outcome,
+            "incompatible"
+        );
+
+        assert_eq!(requeue_job(&conn, "job-1").unwrap(), "incompatible");
+        assert_eq!(get_job(&conn, "job-1").unwrap().unwrap().2, "pending");
+    }
+
+    #[test]
+    fn test_requeue_dependent_waits_for_parent() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        insert_job(&conn, "parent", 2, &json).unwrap();
+        let options = with_deps(&["parent"], DependencyFailurePolicy::Cancel);
+        insert_job_with_options(&conn, "child", 2, &json, &options).unwrap();
+        mark_processing(&conn, "parent").unwrap();
+        mark_failed(&conn, "parent", "boom").unwrap();
+        fail_dependents(&conn, "parent").unwrap();
+
+        requeue_job(&conn, "child").unwrap();
+        assert_eq!(get_job(&conn, "child").unwrap().unwrap().2, "blocked");
+        requeue_job(&conn, "parent").unwrap();
+        assert_eq!(get_job(&conn, "parent").unwrap().unwrap().2, "pending");
+    }
+
+    #[test]
+    fn test_requeue_jobs_by_filter() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        for id in ["failed-1", "dead-1", "cancelled-1", "pending-1"] {
+            insert_job(&conn, id, 2, &json).unwrap();
+        }
+        mark_processing(&conn, "failed-1").unwrap();
+        mark_failed(&conn, "failed-1", "boom").unwrap();
+        mark_processing(&conn, "dead-1").unwrap();
+        mark_dead(&conn, "dead-1", "boom").unwrap();
+        cancel_job(&conn, "cancelled-1").unwrap();
+
+        // Defaults to failed and dead jobs
+        let mut requeued = requeue_jobs(&conn, &JobFilter::new()).unwrap();
+        requeued.sort();
+        assert_eq!(
+            requeued,
+            vec![
+                ("dead-1".to_string(), "dead".to_string()),
+                ("failed-1".to_string(), "failed".to_string()),
+            ]
+        );
+        assert_eq!(
+            get_job(&conn, "cancelled-1").unwrap().unwrap().2,
+            "cancelled"
+        );
+
+        // Non-requeueable statuses in the filter are ignored
+        let filter = JobFilter::new()
+            .with_status(QueueJobStatus::Pending)
+            .with_status(QueueJobStatus::Cancelled);
+        let requeued = requeue_jobs(&conn, &filter).unwrap();
+        assert_eq!(
+            requeued,
+            vec![("cancelled-1".to_string(), "cancelled".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unique_key_conflicts() {
+        let conn = setup();
+        let json = serde_json::json!({});
+        let keyed = JobOptions {
+            unique_key: Some("thumb:42".to_string()),
+            ..JobOptions::default()
+        };
+
+        let first = insert_unique_job(&conn, "job-1", 2, &json, &keyed, false).unwrap();
+        assert!(matches!(
+            first,
+            UniqueInsert::Inserted { replaced: None, .. }
+        ));
+        let second = insert_unique_job(&conn, "job-2", 2, &json, &keyed, false).unwrap();
+        assert_eq!(
+            second,
+            UniqueInsert::Conflict {
+                existing_id: "job-1".to_string()
+            }
+        );
+        assert!(get_job(&conn, "job-2").unwrap().is_none());
+
+        // The index backs the check up even without it
+        assert!(insert_job_with_options(&conn, "job-3", 2, &json, &keyed).is_err());
+
+        // A pending job is replaced, a processing one is not
+        let replaced = insert_unique_job(&conn, "job-4", 2, &json, &keyed, true).unwrap();
+        assert_eq!(
+            replaced,
+            UniqueInsert::Inserted {
+                status: "pending".to_string(),
+                replaced: Some("job-1".to_string()),
+            }
+        );
+        assert_eq!(get_job(&conn, "job-1").unwrap().unwrap().2, "cancelled");
+        mark_processing(&conn, "job-4").unwrap();
+        let running = insert_unique_job(&conn, "job-5", 2, &json, &keyed, true).unwrap();
+        assert!(matches!(running, UniqueInsert::Conflict { .. }));
+
+        // Finishing the job frees the key
+        mark_completed(&conn, "job-4").unwrap();
+        let again = insert_unique_job(&conn, "job-6", 2, &json, &keyed, false).unwrap();
+        assert!(matches!(again, UniqueInsert::Inserted { .. }));
+        let stored = get_stored_job(&conn, "job-6").unwrap().unwrap();
+        assert_eq!(stored.unique_key.as_deref(), Some("thumb:42"));
+    }
+
     #[test]
     fn test_get_job_not_found() {
         let conn = setup();
@@ -3,6 +3,7 @@ use thiserror::Error;
 /// Errors that can occur in the queue system.
 #[derive(Error, Debug)]
 pub enum QueueError {
+    #[cfg(feature = "sqlite")]
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
 
@@ -24,6 +25,15 @@ pub enum QueueError {
     #[error("Job was cancelled")]
     Cancelled,
 
+    #[error("A job with unique key '{key}' is already queued: {existing_id}")]
+    DuplicateJob { key: String, existing_id: String },
+
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Incompatible payload: {0}")]
+    IncompatiblePayload(String),
+
     #[error("{0}")]
     Other(String),
 }
@@ -1,3 +1,4 @@
+use crate::types::{LogLevel, QueuePriority};
 use serde::{Deserialize, Serialize};
 
 /// Emitted when a job starts executing.
@@ -23,6 +24,32 @@ pub struct JobFailedEvent {
     pub error: String,
 }
 
+/// Emitted when a failed job has been scheduled for another attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRetryingEvent {
+    pub job_id: String,
+    /// The upcoming attempt number (2 = first retry).
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// Delay before the next attempt, in milliseconds.
+    pub delay_ms: u64,
+    /// When the next attempt becomes eligible to run (RFC 3339).
+    pub next_run_at: String,
+    /// The error from the attempt that just failed.
+    pub error: String,
+}
+
+/// Emitted when a recurring schedule comes due and enqueues a job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleFiredEvent {
+    pub schedule_id: String,
+    pub job_id: String,
+    /// When the schedule fires next (RFC 3339), `None` if it has finished.
+    pub next_run_at: Option<String>,
+}
+
 /// Emitted during job execution to report progress.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,6 +58,82 @@ pub struct JobProgressEvent {
     pub current_step: u32,
     pub total_steps: u32,
     pub progress: f64,
+    /// Short label for the current phase, if the handler set one.
+    pub stage: Option<String>,
+    /// Free-text detail, if the handler set one.
+    pub message: Option<String>,
+}
+
+/// Emitted when a job is added with dependencies that haven't completed yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobBlockedEvent {
+    pub job_id: String,
+    pub depends_on: Vec<String>,
+}
+
+/// Emitted when all of a blocked job's dependencies have completed and it
+/// is ready to run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobUnblockedEvent {
+    pub job_id: String,
+}
+
+/// Emitted when a job fails on its last allowed attempt and moves to the
+/// dead-letter state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobDeadEvent {
+    pub job_id: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Emitted when a finished job is requeued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRequeuedEvent {
+    pub job_id: String,
+    /// The job's status before it was requeued.
+    pub previous_status: String,
+}
+
+/// Emitted once by [`cancel_where()`](crate::QueueManager::cancel_where)
+/// for all the jobs it cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsCancelledEvent {
+    pub job_ids: Vec<String>,
+    pub count: u32,
+}
+
+/// Emitted once by [`reorder_many()`](crate::QueueManager::reorder_many)
+/// for all the jobs it moved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsReorderedEvent {
+    pub job_ids: Vec<String>,
+    pub priority: QueuePriority,
+}
+
+/// Emitted when a job's payload can't be upcast or deserialized into its
+/// handler type, and the job is moved to `incompatible`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobIncompatibleEvent {
+    pub job_id: String,
+    /// Payload version the job was stored with, after any upcasting.
+    pub payload_version: u32,
+    pub error: String,
+}
+
+/// Emitted when a job runs longer than its timeout and is aborted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTimedOutEvent {
+    pub job_id: String,
+    pub timeout_ms: u64,
 }
 
 /// Emitted when a job is cancelled.
@@ -39,3 +142,16 @@ pub struct JobProgressEvent {
 pub struct JobCancelledEvent {
     pub job_id: String,
 }
+
+/// Emitted for every line a job writes to its log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLogEvent {
+    pub job_id: String,
+    /// ID of the stored log entry.
+    pub id: i64,
+    pub level: LogLevel,
+    pub message: String,
+    pub fields: serde_json::Value,
+    pub created_at: String,
+}
@@ -1,243 +1,1039 @@
-use crate::{config::QueueConfig, db, error::QueueError, events::*, JobContext, JobHandler};
-use rusqlite::Connection;
+use crate::{
+    config::QueueConfig,
+    error::QueueError,
+    events::*,
+    registry::{JobFuture, JobRegistry, PreparedJob},
+    sink::EventSink,
+    store::{ClaimedJob, JobStore},
+    types::{LogLevel, QueueMetrics, ShutdownMode, DEFAULT_QUEUE},
+    CancellationToken, JobContext, JobHandler, ProgressThrottle,
+};
+use std::collections::{HashMap, HashSet};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    Arc, Mutex, OnceLock, RwLock,
 };
-use tauri::{AppHandle, Emitter};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::Instant;
 
 /// The background job executor.
 ///
-/// Polls the database for pending jobs and processes them using the
-/// registered [`JobHandler`] implementation. Supports pause/resume,
-/// consecutive job limits with cooldown, and cancellation.
+/// Wakes up whenever [`wake()`](Self::wake) is called (on every add, resume,
+/// reorder and requeue) or a running job finishes, and otherwise polls the
+/// [`JobStore`] every [`QueueConfig::poll_interval`] as a safety net for rows
+/// written from outside the queue. Processes pending jobs with the
+/// [`JobHandler`] registered for their job type. Supports pause/resume,
+/// consecutive job limits with cooldown, retries with backoff, and
+/// cancellation. Each named queue has its own pause state, cooldown,
+/// consecutive count and worker slots (see [`QueueConfig::queue_config()`]);
+/// every job runs on its own tokio task.
 pub struct QueueExecutor {
     config: QueueConfig,
-    pub(crate) db: Arc<Mutex<Connection>>,
+    pub(crate) store: Arc<dyn JobStore>,
     paused: Arc<AtomicBool>,
+    registry: RwLock<JobRegistry>,
+    /// Number of running jobs per job type.
+    running: Arc<Mutex<HashMap<String, usize>>>,
+    /// Number of running jobs per named queue.
+    running_queues: Arc<Mutex<HashMap<String, usize>>>,
+    /// Named queues paused with [`pause_queue()`](Self::pause_queue).
+    paused_queues: RwLock<HashSet<String>>,
+    /// Cancellation tokens of running jobs, by job ID.
+    cancel_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    /// Set once the executor is spawned, so events raised outside the
+    /// executor loop (e.g. by `QueueManager::add`) reach the same sink.
+    events: OnceLock<Arc<dyn EventSink>>,
+    /// Wakes the executor loop before the next poll is due.
+    wakeup: Notify,
+    /// Fired by [`shutdown()`](Self::shutdown): no new jobs are started.
+    shutdown: CancellationToken,
+    /// Fired by a [`ShutdownMode::Abort`] shutdown: running jobs are stopped.
+    abort: CancellationToken,
+    /// Fired once the loop has exited and its workers are done.
+    stopped: CancellationToken,
+    started: AtomicBool,
+    /// Recorded on every job this executor claims; see [`QueueConfig::worker_id`].
+    worker_id: String,
+}
+
+/// Scheduling state of one named queue inside the executor loop.
+#[derive(Debug, Default)]
+struct LaneState {
+    consecutive_count: u32,
+    cooldown_until: Option<Instant>,
+}
+
+/// A running job's place in the per-type counts and its cancellation token.
+/// Released on drop, so both stay correct even if the handler panics.
+struct RunningSlot {
+    running: Arc<Mutex<HashMap<String, usize>>>,
+    job_type: String,
+    running_queues: Arc<Mutex<HashMap<String, usize>>>,
+    queue: String,
+    cancel_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    job_id: String,
+}
+
+impl Drop for RunningSlot {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.running.lock() {
+            if let Some(count) = running.get_mut(&self.job_type) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        if let Ok(mut running) = self.running_queues.lock() {
+            if let Some(count) = running.get_mut(&self.queue) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        if let Ok(mut tokens) = self.cancel_tokens.lock() {
+            tokens.remove(&self.job_id);
+        }
+    }
 }
 
 impl QueueExecutor {
-    pub fn new(config: QueueConfig, db: Arc<Mutex<Connection>>) -> Self {
+    pub fn new(config: QueueConfig, store: Arc<dyn JobStore>) -> Self {
+        let worker_id = config
+            .worker_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
         Self {
             config,
-            db,
+            store,
             paused: Arc::new(AtomicBool::new(false)),
+            registry: RwLock::new(JobRegistry::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            running_queues: Arc::new(Mutex::new(HashMap::new())),
+            paused_queues: RwLock::new(HashSet::new()),
+            cancel_tokens: Arc::new(Mutex::new(HashMap::new())),
+            events: OnceLock::new(),
+            wakeup: Notify::new(),
+            shutdown: CancellationToken::new(),
+            abort: CancellationToken::new(),
+            stopped: CancellationToken::new(),
+            started: AtomicBool::new(false),
+            worker_id,
         }
     }
 
-    /// Spawn the executor loop as a background tokio task.
-    ///
-    /// The executor will poll for pending jobs at the configured interval
-    /// and process them using the provided `JobHandler` implementation.
-    pub fn spawn<H>(self: Arc<Self>, app_handle: AppHandle)
+    /// Register a handler type. Jobs of this type are picked up from the
+    /// next poll on, even if the executor is already running.
+    pub fn register<H>(&self)
     where
         H: JobHandler + 'static,
     {
-        tauri::async_runtime::spawn(async move {
-            self.run_loop::<H>(app_handle).await;
-        });
+        match self.registry.write() {
+            Ok(mut registry) => registry.register::<H>(),
+            Err(e) => log!(error, "Registry lock poisoned: {}", e),
+        }
     }
 
-    async fn run_loop<H>(&self, app_handle: AppHandle)
+    /// Register an upcaster for `H` payloads stored with `from_version`.
+    /// See [`JobRegistry::register_upcaster()`].
+    pub fn register_upcaster<H, F>(&self, from_version: u32, upcast: F)
     where
-        H: JobHandler,
+        H: JobHandler + 'static,
+        F: Fn(serde_json::Value) -> Result<serde_json::Value, QueueError> + Send + Sync + 'static,
     {
-        let mut consecutive_count: u32 = 0;
+        match self.registry.write() {
+            Ok(mut registry) => registry.register_upcaster::<H, F>(from_version, upcast),
+            Err(e) => log!(error, "Registry lock poisoned: {}", e),
+        }
+    }
+
+    /// Bring a stored payload up to its handler's current version. Returns
+    /// the payload and its new version.
+    pub(crate) fn upcast(
+        &self,
+        job_type: Option<&str>,
+        payload_version: u32,
+        data: serde_json::Value,
+    ) -> Result<(serde_json::Value, u32), QueueError> {
+        self.registry
+            .read()
+            .map_err(|e| QueueError::Other(e.to_string()))?
+            .upcast(job_type, payload_version, data)
+    }
+
+    /// All job types with a registered handler.
+    pub fn job_types(&self) -> Vec<String> {
+        self.registry
+            .read()
+            .map(|registry| registry.job_types())
+            .unwrap_or_default()
+    }
+
+    /// Spawn the executor loop as a background tokio task.
+    ///
+    /// The executor will poll for pending jobs at the configured interval,
+    /// process each one with the handler registered for its job type, and
+    /// emit events through `events`.
+    ///
+    /// With the `tauri` feature the loop runs on Tauri's async runtime;
+    /// otherwise it must be called from within a tokio runtime.
+    pub fn spawn(self: Arc<Self>, events: Arc<dyn EventSink>) {
+        let _ = self.events.set(Arc::clone(&events));
+        self.started.store(true, Ordering::SeqCst);
+        // Pick up jobs left from the last run without waiting for a poll
+        self.wake();
+
+        #[cfg(feature = "tauri")]
+        tauri::async_runtime::spawn(async move {
+            self.run_loop(events).await;
+        });
+        #[cfg(not(feature = "tauri"))]
+        tokio::spawn(async move {
+            self.run_loop(events).await;
+        });
+    }
+
+    async fn run_loop(self: Arc<Self>, events: Arc<dyn EventSink>) {
+        let mut workers: JoinSet<(String, bool)> = JoinSet::new();
+        let mut lanes: HashMap<String, LaneState> = HashMap::new();
+        let mut next_stats = self
+            .config
+            .stats_interval
+            .map(|interval| Instant::now() + interval);
 
         loop {
-            tokio::time::sleep(self.config.poll_interval).await;
+            // Wait for a wakeup, the next poll or cooldown end, or a running
+            // job to free its slot
+            let now = Instant::now();
+            let wait = lanes
+                .values()
+                .filter_map(|lane| lane.cooldown_until)
+                .filter(|until| *until > now)
+                .map(|until| until - now)
+                .chain(next_stats.map(|at| at.saturating_duration_since(now)))
+                .fold(self.config.poll_interval, Duration::min);
+            tokio::select! {
+                _ = self.wakeup.notified() => {}
+                _ = self.shutdown.cancelled() => {}
+                _ = tokio::time::sleep(wait) => {}
+                Some(result) = workers.join_next(), if !workers.is_empty() => {
+                    self.record_worker_result(result, &mut lanes);
+                }
+            }
+            while let Some(result) = workers.try_join_next() {
+                self.record_worker_result(result, &mut lanes);
+            }
+            if self.shutdown.is_cancelled() {
+                break;
+            }
+
+            // Turn due schedules into pending jobs, even while paused
+            self.enqueue_due_schedules();
+
+            // Requeue jobs whose worker stopped renewing its lease
+            self.reclaim_expired_leases();
+
+            if let (Some(at), Some(interval)) = (next_stats, self.config.stats_interval) {
+                if Instant::now() >= at {
+                    self.emit_stats();
+                    next_stats = Some(Instant::now() + interval);
+                }
+            }
 
             // Check if paused
             if self.paused.load(Ordering::Relaxed) {
                 continue;
             }
 
-            // Check consecutive limit
-            if self.config.max_consecutive > 0 && consecutive_count >= self.config.max_consecutive {
-                eprintln!(
-                    "[tauri-queue] Consecutive limit ({}) reached, cooling down for {:?}",
-                    self.config.max_consecutive, self.config.cooldown
-                );
-                tokio::time::sleep(self.config.cooldown).await;
-                consecutive_count = 0;
-                continue;
+            for queue in self.runnable_queues() {
+                let lane = lanes.entry(queue.clone()).or_default();
+                self.fill_lane(&queue, lane, &events, &mut workers);
             }
+        }
 
-            // Get next pending job
-            let (job_id, job_data) = {
-                let conn = match self.db.lock() {
-                    Ok(c) => c,
-                    Err(e) => {
-                        eprintln!("[tauri-queue] DB mutex poisoned: {}", e);
-                        continue;
-                    }
-                };
-                match db::get_next_pending(&conn) {
-                    Ok(Some(job)) => job,
-                    Ok(None) => {
-                        consecutive_count = 0;
-                        continue;
-                    }
-                    Err(e) => {
-                        eprintln!("[tauri-queue] Failed to query next pending job: {:#}", e);
-                        continue;
+        self.stop_workers(workers).await;
+        self.stopped.cancel();
+    }
+
+    /// Start jobs from the named queue `queue` until its worker slots are
+    /// full, honouring its cooldown and consecutive limit.
+    fn fill_lane(
+        self: &Arc<Self>,
+        queue: &str,
+        lane: &mut LaneState,
+        events: &Arc<dyn EventSink>,
+        workers: &mut JoinSet<(String, bool)>,
+    ) {
+        let config = self.config.queue_config(queue);
+        let mut running = self.running_in_queue(queue);
+
+        // Check consecutive limit: let running jobs finish, then cool down
+        if config.max_consecutive > 0 && lane.consecutive_count >= config.max_consecutive {
+            if running > 0 {
+                return;
+            }
+            log!(
+                info,
+                "Consecutive limit ({}) reached in queue '{}', cooling down for {:?}",
+                config.max_consecutive,
+                queue,
+                config.cooldown
+            );
+            lane.consecutive_count = 0;
+            lane.cooldown_until = Some(Instant::now() + config.cooldown);
+            return;
+        }
+
+        // Check cooldown after the last completed job
+        if let Some(until) = lane.cooldown_until {
+            if Instant::now() < until {
+                return;
+            }
+            lane.cooldown_until = None;
+        }
+
+        // Fill free worker slots
+        while running < config.concurrency.max(1) {
+            if config.max_consecutive > 0
+                && lane.consecutive_count as usize + running >= config.max_consecutive as usize
+            {
+                break;
+            }
+
+            let (job_types, include_untyped) = self.claimable_job_types();
+            if job_types.is_empty() && !include_untyped {
+                break;
+            }
+            let claimed = self.store.claim_next_aged(
+                queue,
+                &job_types,
+                include_untyped,
+                (&self.worker_id, self.config.lease_duration),
+                self.config
+                    .priority_aging
+                    .as_ref()
+                    .map(|aging| (aging, self.config.clock.now())),
+            );
+            let job = match claimed {
+                Ok(Some(job)) => job,
+                Ok(None) => {
+                    if running == 0 {
+                        lane.consecutive_count = 0;
                     }
+                    break;
                 }
-            };
-
-            // Deserialize job data into the handler type
-            let job_handler: H = match serde_json::from_value(job_data) {
-                Ok(h) => h,
                 Err(e) => {
-                    eprintln!("[tauri-queue] Failed to deserialize job {}: {}", job_id, e);
-                    if let Ok(conn) = self.db.lock() {
-                        let _ = db::mark_failed(
-                            &conn,
-                            &job_id,
-                            &format!("Deserialization failed: {}", e),
-                        );
-                    }
-                    let _ = app_handle.emit(
-                        "queue:job_failed",
-                        JobFailedEvent {
-                            job_id: job_id.clone(),
-                            error: format!("Deserialization failed: {}", e),
-                        },
-                    );
-                    continue;
+                    log!(error, "Failed to claim next pending job: {:#}", e);
+                    break;
                 }
             };
+            if self.start_job(job, queue, events, workers) {
+                running += 1;
+            }
+        }
+    }
 
-            // Process the job
-            let result = self
-                .process_job::<H>(&app_handle, &job_id, job_handler)
-                .await;
+    /// Run a claimed job on its own worker task. Returns `false` if the job
+    /// couldn't be started and was marked as failed instead.
+    fn start_job(
+        self: &Arc<Self>,
+        job: ClaimedJob,
+        queue: &str,
+        events: &Arc<dyn EventSink>,
+        workers: &mut JoinSet<(String, bool)>,
+    ) -> bool {
+        let job_id = job.id;
+        let job_type = job.job_type;
+        let timeout = job.timeout;
+        let cancel_token = CancellationToken::new();
 
-            match result {
-                Ok(_) => {
-                    consecutive_count += 1;
-                    if self.config.cooldown.as_secs() > 0 {
-                        tokio::time::sleep(self.config.cooldown).await;
-                    }
+        let ctx = JobContext {
+            job_id: job_id.clone(),
+            events: Arc::clone(events),
+            store: Arc::clone(&self.store),
+            progress_throttle: Mutex::new(ProgressThrottle::new(
+                self.config.progress_persist_interval,
+            )),
+            cancel_token: cancel_token.clone(),
+        };
+
+        // Upcast older payloads, then deserialize into the registered handler type
+        let (runner, upcast) = match self.registry.read() {
+            Ok(registry) => (
+                registry.runner(job_type.as_deref()),
+                registry.upcast(job_type.as_deref(), job.payload_version, job.data),
+            ),
+            Err(_) => (None, Ok((job.data, job.payload_version))),
+        };
+        let (data, payload_version) = match upcast {
+            Ok(upcast) => upcast,
+            Err(e) => {
+                self.fail_incompatible(&job_id, job.payload_version, &e.to_string());
+                return false;
+            }
+        };
+        if payload_version != job.payload_version {
+            self.store_upcast_payload(&job_id, &data, payload_version);
+        }
+        let PreparedJob { name, future } = match runner.map(|run| run(data, ctx)) {
+            Some(Ok(prepared)) => prepared,
+            Some(Err(e)) => {
+                self.fail_incompatible(
+                    &job_id,
+                    payload_version,
+                    &format!("Deserialization failed: {}", e),
+                );
+                return false;
+            }
+            None => {
+                self.fail_undecodable(
+                    &job_id,
+                    &format!(
+                        "No handler registered for job type '{}'",
+                        job_type.as_deref().unwrap_or_default()
+                    ),
+                );
+                return false;
+            }
+        };
+
+        // Process the job on its own task, in a span that handler code inherits
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "job",
+            job_id = %job_id,
+            job_type = %name,
+            attempt = job.attempt,
+            queue = %queue,
+        );
+        let slot = self.acquire_slot(
+            job_type.unwrap_or_default(),
+            queue,
+            &job_id,
+            cancel_token.clone(),
+        );
+        let executor = Arc::clone(self);
+        let queue = queue.to_string();
+        let worker = async move {
+            let _slot = slot;
+            let result = tokio::select! {
+                result = executor.process_job(&job_id, &name, future, timeout, cancel_token.clone()) => result,
+                _ = executor.keep_lease(&job_id) => {
+                    // Cancelled elsewhere, or another worker owns the job
+                    // now; leave its row alone
+                    cancel_token.cancel();
+                    return (queue, false);
                 }
+            };
+            match result {
+                Ok(()) => (queue, true),
                 Err(e) => {
-                    // Check if this was a cancellation
-                    let was_cancelled = {
-                        match self.db.lock() {
-                            Ok(conn) => db::is_cancelled(&conn, &job_id).unwrap_or(false),
-                            Err(_) => false,
-                        }
-                    };
-
-                    if was_cancelled {
-                        eprintln!("[tauri-queue] Job {} was cancelled", job_id);
-                        let _ = app_handle.emit(
+                    executor.handle_failure(&job_id, &name, &e.to_string());
+                    (queue, false)
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let worker = tracing::Instrument::instrument(worker, span);
+        workers.spawn(worker);
+        true
+    }
+
+    /// Wait for running jobs to finish after a shutdown, or stop them and
+    /// mark them `interrupted` once the shutdown turns into an abort.
+    async fn stop_workers(&self, mut workers: JoinSet<(String, bool)>) {
+        loop {
+            tokio::select! {
+                result = workers.join_next() => match result {
+                    Some(Err(e)) => log!(error, "Job worker panicked: {}", e),
+                    Some(Ok(_)) => {}
+                    None => return,
+                },
+                _ = self.abort.cancelled() => break,
+            }
+        }
+
+        let job_ids: Vec<String> = match self.cancel_tokens.lock() {
+            Ok(tokens) => tokens
+                .iter()
+                .map(|(job_id, token)| {
+                    token.cancel();
+                    job_id.clone()
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        workers.abort_all();
+        while workers.join_next().await.is_some() {}
+
+        for job_id in &job_ids {
+            if let Err(e) = self.store.mark_interrupted(job_id) {
+                log!(
+                    error,
+                    "Failed to mark job {} as interrupted: {:#}",
+                    job_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Renew the lease on a running job every third of the lease duration.
+    /// Returns only if the lease was lost, i.e. the heartbeat fell behind
+    /// long enough for the job to be reclaimed, or the job was cancelled
+    /// directly in the store (e.g. by another process).
+    async fn keep_lease(&self, job_id: &str) {
+        let interval = (self.config.lease_duration / 3).max(Duration::from_millis(10));
+        loop {
+            tokio::time::sleep(interval).await;
+            let renewed =
+                self.store
+                    .renew_lease(job_id, &self.worker_id, self.config.lease_duration);
+            match renewed {
+                Ok(true) => {}
+                Ok(false) => {
+                    let cancelled = self.store.is_cancelled(job_id).unwrap_or(false);
+                    if cancelled {
+                        log!(info, "Job {} was cancelled", job_id);
+                        self.emit(
                             "queue:job_cancelled",
                             JobCancelledEvent {
-                                job_id: job_id.clone(),
+                                job_id: job_id.to_string(),
                             },
                         );
                     } else {
-                        eprintln!("[tauri-queue] Job {} failed: {:#}", job_id, e);
-                        if let Ok(conn) = self.db.lock() {
-                            let _ = db::mark_failed(&conn, &job_id, &e.to_string());
-                        }
-                        let _ = app_handle.emit(
-                            "queue:job_failed",
-                            JobFailedEvent {
-                                job_id: job_id.clone(),
-                                error: e.to_string(),
-                            },
-                        );
+                        log!(warn, "Lost the lease on job {}", job_id);
                     }
+                    return;
+                }
+                Err(e) => log!(error, "Failed to renew lease of job {}: {:#}", job_id, e),
+            }
+        }
+    }
+
+    /// Emit `queue:stats` with the current metrics.
+    fn emit_stats(&self) {
+        match self.metrics() {
+            Ok(metrics) => self.emit("queue:stats", metrics),
+            Err(e) => log!(error, "Failed to compute queue stats: {}", e),
+        }
+    }
+
+    /// The configuration this executor runs with.
+    pub fn config(&self) -> &QueueConfig {
+        &self.config
+    }
+
+    /// Compute queue statistics over [`QueueConfig::stats_window`].
+    pub fn metrics(&self) -> Result<QueueMetrics, QueueError> {
+        self.store
+            .metrics(self.config.stats_window)
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Put jobs with an expired lease back in the queue.
+    fn reclaim_expired_leases(&self) {
+        let job_ids = match self.store.reclaim_expired_leases() {
+            Ok(job_ids) => job_ids,
+            Err(e) => {
+                log!(error, "Failed to reclaim expired leases: {:#}", e);
+                return;
+            }
+        };
+        for job_id in job_ids {
+            log!(warn, "Lease of job {} expired, requeued", job_id);
+            self.emit(
+                "queue:job_requeued",
+                JobRequeuedEvent {
+                    job_id,
+                    previous_status: "processing".to_string(),
+                },
+            );
+        }
+    }
+
+    /// Mark a claimed job as failed because its payload can't be turned into
+    /// a handler. Retrying wouldn't help, so this skips the retry policy.
+    fn fail_undecodable(&self, job_id: &str, error: &str) {
+        log!(error, "Failed to deserialize job {}: {}", job_id, error);
+        if let Err(e) = self.store.mark_failed(job_id, error) {
+            log!(error, "Failed to mark job {} as failed: {:#}", job_id, e);
+            return;
+        }
+        self.emit(
+            "queue:job_failed",
+            JobFailedEvent {
+                job_id: job_id.to_string(),
+                error: error.to_string(),
+            },
+        );
+    }
+
+    /// Move a claimed job whose payload couldn't be upcast or deserialized
+    /// to `incompatible`. Retrying wouldn't help, so this skips the retry
+    /// policy; the job can be requeued once an upcaster handles it.
+    fn fail_incompatible(&self, job_id: &str, payload_version: u32, error: &str) {
+        log!(
+            error,
+            "Job {} has an incompatible payload (version {}): {}",
+            job_id,
+            payload_version,
+            error
+        );
+        match self.store.mark_incompatible(job_id, error) {
+            Ok(true) => {
+                self.log_job(job_id, LogLevel::Error, error);
+                self.emit(
+                    "queue:job_incompatible",
+                    JobIncompatibleEvent {
+                        job_id: job_id.to_string(),
+                        payload_version,
+                        error: error.to_string(),
+                    },
+                );
+                self.fail_dependents(job_id);
+            }
+            // Cancelled in the meantime
+            Ok(false) => {}
+            Err(e) => log!(
+                error,
+                "Failed to mark job {} as incompatible: {:#}",
+                job_id,
+                e
+            ),
+        }
+    }
+
+    /// Save an upcast payload, so later attempts and lookups don't have to
+    /// migrate it again.
+    fn store_upcast_payload(&self, job_id: &str, data: &serde_json::Value, payload_version: u32) {
+        if let Err(e) = self.store.update_payload(job_id, data, payload_version) {
+            log!(
+                error,
+                "Failed to store upcast payload of job {}: {:#}",
+                job_id,
+                e
+            );
+        }
+    }
+
+    /// Update the consecutive count and cooldown of a job's queue after its
+    /// worker finishes.
+    fn record_worker_result(
+        &self,
+        result: Result<(String, bool), JoinError>,
+        lanes: &mut HashMap<String, LaneState>,
+    ) {
+        match result {
+            Ok((queue, true)) => {
+                let cooldown = self.config.queue_config(&queue).cooldown;
+                let lane = lanes.entry(queue).or_default();
+                lane.consecutive_count += 1;
+                if !cooldown.is_zero() {
+                    lane.cooldown_until = Some(Instant::now() + cooldown);
                 }
             }
+            Ok((_, false)) => {}
+            Err(e) => log!(error, "Job worker panicked: {}", e),
+        }
+    }
+
+    /// Named queues with jobs ready to run that aren't paused.
+    fn runnable_queues(&self) -> Vec<String> {
+        let mut queues = match self.store.runnable_queues() {
+            Ok(queues) => queues,
+            Err(e) => {
+                log!(error, "Failed to list runnable queues: {:#}", e);
+                return Vec::new();
+            }
+        };
+        if let Ok(paused) = self.paused_queues.read() {
+            queues.retain(|queue| !paused.contains(queue));
         }
+        queues
     }
 
-    async fn process_job<H>(
+    /// Number of jobs from the named queue `queue` running in this executor.
+    fn running_in_queue(&self, queue: &str) -> usize {
+        self.running_queues
+            .lock()
+            .map(|running| running.get(queue).copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    /// Registered job types that are below their concurrency cap, and
+    /// whether legacy rows without a job type can be claimed.
+    fn claimable_job_types(&self) -> (Vec<String>, bool) {
+        let (job_types, include_untyped) = match self.registry.read() {
+            Ok(registry) => (registry.job_types(), registry.has_default()),
+            Err(_) => return (Vec::new(), false),
+        };
+        if self.config.job_type_limits.is_empty() {
+            return (job_types, include_untyped);
+        }
+        let running = match self.running.lock() {
+            Ok(r) => r,
+            Err(_) => return (job_types, include_untyped),
+        };
+        let job_types = job_types
+            .into_iter()
+            .filter(|job_type| match self.config.job_type_limits.get(job_type) {
+                Some(limit) => running.get(job_type).copied().unwrap_or(0) < *limit,
+                None => true,
+            })
+            .collect();
+        (job_types, include_untyped)
+    }
+
+    /// Count a job against its type's running total and make it cancellable
+    /// until the slot is dropped.
+    fn acquire_slot(
         &self,
-        app_handle: &AppHandle,
+        job_type: String,
+        queue: &str,
         job_id: &str,
-        job_handler: H,
-    ) -> Result<(), QueueError>
-    where
-        H: JobHandler,
-    {
-        // Mark as processing
-        {
-            let conn = self
-                .db
-                .lock()
-                .map_err(|e| QueueError::Other(e.to_string()))?;
-            db::mark_processing(&conn, job_id).map_err(|e| QueueError::Other(e.to_string()))?;
+        cancel_token: CancellationToken,
+    ) -> RunningSlot {
+        if let Ok(mut running) = self.running.lock() {
+            *running.entry(job_type.clone()).or_insert(0) += 1;
+        }
+        if let Ok(mut running) = self.running_queues.lock() {
+            *running.entry(queue.to_string()).or_insert(0) += 1;
+        }
+        if let Ok(mut tokens) = self.cancel_tokens.lock() {
+            tokens.insert(job_id.to_string(), cancel_token);
+        }
+        RunningSlot {
+            running: Arc::clone(&self.running),
+            job_type,
+            running_queues: Arc::clone(&self.running_queues),
+            queue: queue.to_string(),
+            cancel_tokens: Arc::clone(&self.cancel_tokens),
+            job_id: job_id.to_string(),
+        }
+    }
+
+    /// Trigger the cancellation token of a running job. Returns `false` if
+    /// the job isn't running in this executor.
+    pub(crate) fn cancel_running(&self, job_id: &str) -> bool {
+        let token = self
+            .cancel_tokens
+            .lock()
+            .ok()
+            .and_then(|tokens| tokens.get(job_id).cloned());
+        match token {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
         }
+    }
 
-        let _ = app_handle.emit(
+    async fn process_job(
+        &self,
+        job_id: &str,
+        name: &str,
+        job_future: JobFuture,
+        timeout: Option<Duration>,
+        cancel_token: CancellationToken,
+    ) -> Result<(), QueueError> {
+        log!(debug, "Job {} ({}) started", job_id, name);
+        self.emit(
             "queue:job_started",
             JobStartedEvent {
                 job_id: job_id.to_string(),
             },
         );
 
-        // Create job context with DB reference for cancellation checks
-        let ctx = JobContext {
-            job_id: job_id.to_string(),
-            app_handle: app_handle.clone(),
-            db: Arc::clone(&self.db),
+        // Execute job, aborting it if it runs past its timeout
+        let job_result = match timeout {
+            Some(limit) => match tokio::time::timeout(limit, job_future).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    cancel_token.cancel();
+                    self.handle_timeout(job_id, name, limit);
+                    return Ok(());
+                }
+            },
+            None => job_future.await?,
         };
 
-        // Execute job
-        let result = job_handler.execute(&ctx).await;
+        if job_result.success {
+            let completed = self
+                .store
+                .mark_completed(job_id, job_result.output.as_deref())
+                .map_err(|e| QueueError::Other(e.to_string()))?;
+            if !completed {
+                // Cancelled while running; the handler finished anyway
+                self.emit(
+                    "queue:job_cancelled",
+                    JobCancelledEvent {
+                        job_id: job_id.to_string(),
+                    },
+                );
+                return Ok(());
+            }
+            let unblocked = self.store.unblock_dependents(job_id).unwrap_or_else(|e| {
+                log!(error, "Failed to unblock dependents of {}: {:#}", job_id, e);
+                Vec::new()
+            });
 
-        match result {
-            Ok(job_result) => {
-                if job_result.success {
-                    let conn = self
-                        .db
-                        .lock()
-                        .map_err(|e| QueueError::Other(e.to_string()))?;
-                    db::mark_completed(&conn, job_id)
-                        .map_err(|e| QueueError::Other(e.to_string()))?;
-
-                    let _ = app_handle.emit(
-                        "queue:job_completed",
-                        JobCompletedEvent {
-                            job_id: job_id.to_string(),
-                            output: job_result.output,
-                        },
-                    );
-                } else {
-                    let error = job_result
-                        .error
-                        .unwrap_or_else(|| "Unknown error".to_string());
-                    let conn = self
-                        .db
-                        .lock()
-                        .map_err(|e| QueueError::Other(e.to_string()))?;
-                    db::mark_failed(&conn, job_id, &error)
-                        .map_err(|e| QueueError::Other(e.to_string()))?;
-
-                    let _ = app_handle.emit(
-                        "queue:job_failed",
-                        JobFailedEvent {
-                            job_id: job_id.to_string(),
-                            error,
-                        },
-                    );
+            log!(debug, "Job {} ({}) completed", job_id, name);
+            self.emit(
+                "queue:job_completed",
+                JobCompletedEvent {
+                    job_id: job_id.to_string(),
+                    output: job_result.output,
+                },
+            );
+            for job_id in unblocked {
+                self.emit("queue:job_unblocked", JobUnblockedEvent { job_id });
+            }
+        } else {
+            let error = job_result
+                .error
+                .unwrap_or_else(|| "Unknown error".to_string());
+            self.handle_failure(job_id, name, &error);
+        }
+        Ok(())
+    }
+
+    /// Record a job that ran past its timeout and cancel or fail its dependents.
+    fn handle_timeout(&self, job_id: &str, name: &str, timeout: Duration) {
+        match self.store.mark_timed_out(job_id, timeout) {
+            Ok(true) => {
+                let message = format!("Timed out after {:?}", timeout);
+                self.log_job(job_id, LogLevel::Error, &message);
+                log!(
+                    warn,
+                    "Job {} ({}) timed out after {:?}",
+                    job_id,
+                    name,
+                    timeout
+                );
+                self.emit(
+                    "queue:job_timed_out",
+                    JobTimedOutEvent {
+                        job_id: job_id.to_string(),
+                        timeout_ms: timeout.as_millis() as u64,
+                    },
+                );
+                self.fail_dependents(job_id);
+            }
+            // Cancelled just before the timeout fired
+            Ok(false) => {}
+            Err(e) => log!(error, "Failed to mark job {} as timed out: {:#}", job_id, e),
+        }
+    }
+
+    /// Enqueue a job for every active schedule that has come due, and
+    /// advance each schedule to its next run time.
+    fn enqueue_due_schedules(&self) {
+        let now = chrono::Utc::now();
+        let due = match self.store.due_schedules(&now.to_rfc3339()) {
+            Ok(due) => due,
+            Err(e) => {
+                log!(error, "Failed to query due schedules: {:#}", e);
+                return;
+            }
+        };
+
+        for schedule in due {
+            let next_run_at = match schedule.spec.next_after(now) {
+                Ok(next) => next.map(|t| t.to_rfc3339()),
+                Err(e) => {
+                    log!(error, "Schedule {} has an invalid spec: {}", schedule.id, e);
+                    continue;
                 }
-                Ok(())
+            };
+            let job_id = uuid::Uuid::new_v4().to_string();
+            match self
+                .store
+                .fire_schedule(&schedule, &job_id, next_run_at.as_deref())
+            {
+                Ok(()) => self.emit(
+                    "queue:schedule_fired",
+                    ScheduleFiredEvent {
+                        schedule_id: schedule.id,
+                        job_id,
+                        next_run_at,
+                    },
+                ),
+                Err(e) => log!(error, "Failed to fire schedule {}: {:#}", schedule.id, e),
             }
+        }
+    }
+
+    /// Record a failed attempt. Schedules a retry if the job's retry policy
+    /// allows another attempt. Otherwise the job is dead-lettered if it had
+    /// retries, or marked as failed if it only ever had one attempt.
+    fn handle_failure(&self, job_id: &str, name: &str, error: &str) {
+        if self.store.is_cancelled(job_id).unwrap_or(false) {
+            log!(info, "Job {} ({}) was cancelled", job_id, name);
+            self.emit(
+                "queue:job_cancelled",
+                JobCancelledEvent {
+                    job_id: job_id.to_string(),
+                },
+            );
+            return;
+        }
+
+        let (attempts, policy) = match self.store.retry_state(job_id) {
+            Ok((attempts, policy)) => (
+                attempts,
+                policy.unwrap_or_else(|| self.config.retry_policy.clone()),
+            ),
             Err(e) => {
-                let conn = self
-                    .db
-                    .lock()
-                    .map_err(|e| QueueError::Other(e.to_string()))?;
-                db::mark_failed(&conn, job_id, &e.to_string())
-                    .map_err(|e2| QueueError::Other(e2.to_string()))?;
-                Err(e)
+                // Leave the job processing: once its lease expires it is
+                // reclaimed and runs again.
+                log!(error, "Failed to read retry state for {}: {:#}", job_id, e);
+                return;
+            }
+        };
+        self.log_job(
+            job_id,
+            LogLevel::Error,
+            &format!("Attempt {} failed: {}", attempts, error),
+        );
+
+        if policy.should_retry(attempts) {
+            let delay = policy.delay_for_attempt(attempts);
+            let next_run_at = (chrono::Utc::now()
+                + chrono::Duration::from_std(delay)
+                    .unwrap_or_else(|_| chrono::Duration::days(365)))
+            .to_rfc3339();
+            if let Err(e) = self.store.schedule_retry(job_id, error, &next_run_at) {
+                log!(error, "Failed to schedule retry for {}: {:#}", job_id, e);
+                return;
+            }
+
+            log!(
+                warn,
+                "Job {} ({}) failed (attempt {}/{}), retrying in {:?}: {}",
+                job_id,
+                name,
+                attempts,
+                policy.max_attempts,
+                delay,
+                error
+            );
+            self.emit(
+                "queue:job_retrying",
+                JobRetryingEvent {
+                    job_id: job_id.to_string(),
+                    attempt: attempts + 1,
+                    max_attempts: policy.max_attempts,
+                    delay_ms: delay.as_millis() as u64,
+                    next_run_at,
+                    error: error.to_string(),
+                },
+            );
+        } else if policy.max_attempts > 1 {
+            // Retries exhausted: dead-letter the job
+            if let Err(e) = self.store.mark_dead(job_id, error) {
+                log!(error, "Failed to mark job {} as dead: {:#}", job_id, e);
+                return;
+            }
+
+            log!(
+                error,
+                "Job {} ({}) failed after {} attempts: {}",
+                job_id,
+                name,
+                attempts,
+                error
+            );
+            self.emit(
+                "queue:job_dead",
+                JobDeadEvent {
+                    job_id: job_id.to_string(),
+                    attempts,
+                    error: error.to_string(),
+                },
+            );
+            self.fail_dependents(job_id);
+        } else {
+            if let Err(e) = self.store.mark_failed(job_id, error) {
+                log!(error, "Failed to mark job {} as failed: {:#}", job_id, e);
+                return;
             }
+
+            log!(error, "Job {} ({}) failed: {}", job_id, name, error);
+            self.emit(
+                "queue:job_failed",
+                JobFailedEvent {
+                    job_id: job_id.to_string(),
+                    error: error.to_string(),
+                },
+            );
+            self.fail_dependents(job_id);
+        }
+    }
+
+    /// Append a line to a job's log on the queue's behalf and emit it.
+    fn log_job(&self, job_id: &str, level: LogLevel, message: &str) {
+        match self
+            .store
+            .insert_log(job_id, level, message, &serde_json::Value::Null)
+        {
+            Ok(entry) => self.emit(
+                "queue:job_log",
+                JobLogEvent {
+                    job_id: entry.job_id,
+                    id: entry.id,
+                    level: entry.level,
+                    message: entry.message,
+                    fields: entry.fields,
+                    created_at: entry.created_at,
+                },
+            ),
+            Err(e) => log!(error, "Failed to write log of job {}: {:#}", job_id, e),
+        }
+    }
+
+    /// Cancel or fail everything that depends on a job that ended without
+    /// completing, and emit an event for each affected job.
+    pub(crate) fn fail_dependents(&self, job_id: &str) {
+        let changed = match self.store.fail_dependents(job_id) {
+            Ok(changed) => changed,
+            Err(e) => {
+                log!(error, "Failed to update dependents of {}: {:#}", job_id, e);
+                return;
+            }
+        };
+
+        for (dependent_id, status) in changed {
+            if status == "failed" {
+                self.emit(
+                    "queue:job_failed",
+                    JobFailedEvent {
+                        error: format!("Dependency '{}' did not complete", job_id),
+                        job_id: dependent_id,
+                    },
+                );
+            } else {
+                self.emit(
+                    "queue:job_cancelled",
+                    JobCancelledEvent {
+                        job_id: dependent_id,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Emit an event through the sink, if the executor has been spawned.
+    pub(crate) fn emit<S>(&self, event: &str, payload: S)
+    where
+        S: serde::Serialize,
+    {
+        let Some(events) = self.events.get() else {
+            return;
+        };
+        match serde_json::to_value(payload) {
+            Ok(payload) => {
+                let _ = events.emit(event, payload);
+            }
+            Err(e) => log!(error, "Failed to serialize {} event: {}", event, e),
         }
     }
 
@@ -250,10 +1046,73 @@ impl QueueExecutor {
     /// Resume the executor after a pause.
     pub fn resume(&self) {
         self.paused.store(false, Ordering::Relaxed);
+        self.wake();
+    }
+
+    /// Stop the executor loop; no new jobs are started from now on.
+    ///
+    /// With [`ShutdownMode::Drain`] running jobs finish normally. With
+    /// [`ShutdownMode::Abort`] their cancellation tokens fire, their tasks are
+    /// dropped and they are marked `interrupted`. An abort also cuts short a
+    /// drain that is still in progress. Resolves once the loop and all its
+    /// workers have exited, or straight away if the executor was never spawned.
+    pub async fn shutdown(&self, mode: ShutdownMode) {
+        if mode == ShutdownMode::Abort {
+            self.abort.cancel();
+        }
+        self.shutdown.cancel();
+        if self.started.load(Ordering::SeqCst) {
+            self.stopped.cancelled().await;
+        }
+    }
+
+    /// The ID this executor records on the jobs it claims.
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    /// Make the executor look for claimable jobs now instead of at the next
+    /// poll. A wakeup sent while the loop is busy is kept until it next waits.
+    pub fn wake(&self) {
+        self.wakeup.notify_one();
     }
 
     /// Check if the executor is currently paused.
     pub fn is_paused(&self) -> bool {
         self.paused.load(Ordering::Relaxed)
     }
+
+    /// Stop starting jobs from the named queue `queue`. Its running jobs finish.
+    pub fn pause_queue(&self, queue: &str) {
+        if let Ok(mut paused) = self.paused_queues.write() {
+            paused.insert(queue.to_string());
+        }
+    }
+
+    /// Resume the named queue `queue` after a pause.
+    pub fn resume_queue(&self, queue: &str) {
+        if let Ok(mut paused) = self.paused_queues.write() {
+            paused.remove(queue);
+        }
+        self.wake();
+    }
+
+    /// Configured and paused named queues, plus [`DEFAULT_QUEUE`].
+    pub fn queue_names(&self) -> Vec<String> {
+        let mut names: HashSet<String> = self.config.queues.keys().cloned().collect();
+        names.insert(DEFAULT_QUEUE.to_string());
+        if let Ok(paused) = self.paused_queues.read() {
+            names.extend(paused.iter().cloned());
+        }
+        names.into_iter().collect()
+    }
+
+    /// Check if the named queue `queue` is paused. Pausing the whole
+    /// executor doesn't count.
+    pub fn is_queue_paused(&self, queue: &str) -> bool {
+        self.paused_queues
+            .read()
+            .map(|paused| paused.contains(queue))
+            .unwrap_or(false)
+    }
 }
@@ -4,49 +4,130 @@
 //!
 //! ## Features
 //!
-//! - Priority-based scheduling (High, Normal, Low)
-//! - SQLite persistence with crash recovery
+//! - Priority-based scheduling (High, Normal, Low), with optional aging so
+//!   low-priority jobs aren't starved
+//! - SQLite persistence with crash recovery and versioned schema migrations,
+//!   or a lock-free in-memory store
+//! - Versioned job payloads, with upcasters that migrate old rows instead of
+//!   failing them
 //! - Hardware throttling (cooldown, max consecutive runs)
-//! - Real-time cancellation during job execution
-//! - Progress tracking via Tauri events
-//! - Pause/resume capability
+//! - Automatic retries with exponential backoff and jitter, a dead-letter
+//!   state and requeueing with attempt history
+//! - Delayed jobs and recurring (cron / interval) schedules
+//! - Job dependencies, so multi-step pipelines run in order
+//! - Unique keys to deduplicate jobs that are already queued
+//! - Stored job results with typed lookup and filtered queries
+//! - Real-time cancellation during job execution, and per-job timeouts
+//! - Progress tracking via Tauri events, persisted for late-mounting UIs
+//! - Checkpoints, so interrupted jobs resume instead of starting over
+//! - Structured per-job logs, stored and streamed as events
+//! - Statistics per job type: durations, failure rate, throughput and depth
+//! - Pluggable storage through [`JobStore`], with SQLite and in-memory
+//!   implementations
+//! - Framework-agnostic core: events go through an [`EventSink`], with Tauri
+//!   support behind the `tauri` feature (on by default)
+//! - A ready-made Tauri plugin with frontend commands, in the
+//!   `tauri-plugin-queue` crate
+//! - `tracing` integration behind the `tracing` feature: a span per job that
+//!   handler code inherits
+//! - Named queues with their own cooldown, concurrency and pause state
+//! - Pause/resume capability and graceful shutdown
 //!
 //! ## Quick Start
 //!
 //! 1. Define a job type implementing [`JobHandler`]
 //! 2. Create a [`QueueManager`] with a [`QueueConfig`]
 //! 3. Add jobs with [`QueueManager::add()`]
-//! 4. Spawn the executor with [`QueueManager::spawn()`]
+//! 4. Spawn the executor with [`QueueManager::spawn()`], or register several
+//!    job types with [`QueueManager::register()`] and call [`QueueManager::start()`]
 //!
 //! See the `examples/` directory for complete usage examples.
 
+#[macro_use]
+mod macros;
+
+pub mod clock;
 pub mod config;
+#[cfg(feature = "sqlite")]
 pub mod db;
 pub mod error;
 pub mod events;
 pub mod executor;
 pub mod queue;
+pub mod registry;
+pub mod schedule;
+pub mod sink;
+pub mod store;
 pub mod types;
 
-pub use config::{QueueConfig, QueueConfigBuilder};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use config::{NamedQueueConfig, QueueConfig, QueueConfigBuilder};
 pub use error::QueueError;
 pub use queue::QueueManager;
-pub use types::{JobResult, QueueJob, QueueJobStatus, QueuePriority};
+pub use registry::JobRegistry;
+pub use schedule::{QueueSchedule, ScheduleInfo, ScheduleSpec};
+pub use sink::{ChannelEventSink, EventSink, QueueEvent};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+pub use store::{JobStore, MemoryStore};
+pub use tokio_util::sync::CancellationToken;
+pub use types::{
+    DependencyFailurePolicy, JobAttempt, JobFilter, JobLogEntry, JobProgress, JobResult,
+    JobTypeMetrics, LogLevel, PriorityAging, QueueJob, QueueJobStatus, QueueMetrics, QueuePriority,
+    QueueStats, RetryPolicy, ShutdownMode, UniqueConflictPolicy, DEFAULT_QUEUE,
+};
 
-use rusqlite::Connection;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Context provided to job handlers during execution.
 ///
-/// Gives access to the Tauri app handle for emitting events, and
+/// Gives access to the queue's [`EventSink`] for emitting events, and
 /// methods for checking cancellation and emitting progress.
 pub struct JobContext {
     /// The ID of the currently executing job.
     pub job_id: String,
-    /// The Tauri app handle for emitting events.
-    pub app_handle: tauri::AppHandle,
-    /// Shared database connection for cancellation checks.
-    pub(crate) db: Arc<Mutex<Connection>>,
+    /// The sink the queue emits its events through.
+    pub events: Arc<dyn EventSink>,
+    /// The queue's store, for progress, logs and checkpoints.
+    pub(crate) store: Arc<dyn JobStore>,
+    /// Throttles progress writes to the store.
+    pub(crate) progress_throttle: Mutex<ProgressThrottle>,
+    /// Triggered by [`QueueManager::cancel()`] or when the job times out.
+    pub(crate) cancel_token: CancellationToken,
+}
+
+/// Tracks the last progress write of one job.
+pub(crate) struct ProgressThrottle {
+    interval: Duration,
+    last_write: Option<Instant>,
+    last_stage: Option<String>,
+}
+
+impl ProgressThrottle {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_write: None,
+            last_stage: None,
+        }
+    }
+
+    /// Whether `progress` should be written now. Stage changes and the
+    /// final step are always written.
+    fn should_write(&mut self, progress: &JobProgress) -> bool {
+        let due = match self.last_write {
+            None => true,
+            Some(at) => at.elapsed() >= self.interval,
+        };
+        if due || progress.current >= progress.total || progress.stage != self.last_stage {
+            self.last_write = Some(Instant::now());
+            self.last_stage = progress.stage.clone();
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl JobContext {
@@ -56,41 +137,153 @@ impl JobContext {
     /// * `current` - Current step number
     /// * `total` - Total number of steps
     pub fn emit_progress(&self, current: u32, total: u32) -> Result<(), QueueError> {
-        use tauri::Emitter;
-        self.app_handle
-            .emit(
-                "queue:job_progress",
-                events::JobProgressEvent {
-                    job_id: self.job_id.clone(),
-                    current_step: current,
-                    total_steps: total,
-                    progress: if total > 0 {
-                        current as f64 / total as f64
-                    } else {
-                        0.0
-                    },
-                },
-            )
-            .map_err(|e| QueueError::Event(e.to_string()))?;
+        self.emit_progress_with(current, total, None, None)
+    }
+
+    /// Emit a progress event with a stage label and/or message.
+    ///
+    /// Progress is also stored with the job (at most once per
+    /// [`QueueConfig::progress_persist_interval`]), so a frontend that mounts
+    /// late can read it with [`QueueManager::progress()`].
+    pub fn emit_progress_with(
+        &self,
+        current: u32,
+        total: u32,
+        stage: Option<&str>,
+        message: Option<&str>,
+    ) -> Result<(), QueueError> {
+        let progress = JobProgress {
+            current,
+            total,
+            stage: stage.map(str::to_string),
+            message: message.map(str::to_string),
+            updated_at: None,
+        };
+
+        let should_write = self
+            .progress_throttle
+            .lock()
+            .map(|mut throttle| throttle.should_write(&progress))
+            .unwrap_or(false);
+        if should_write {
+            self.store.update_progress(&self.job_id, &progress)?;
+        }
+
+        self.emit(
+            "queue:job_progress",
+            &events::JobProgressEvent {
+                job_id: self.job_id.clone(),
+                current_step: current,
+                total_steps: total,
+                progress: progress.fraction(),
+                stage: progress.stage.clone(),
+                message: progress.message.clone(),
+            },
+        )
+    }
+
+    /// Emit a custom event through the queue's [`EventSink`].
+    pub fn emit<S: serde::Serialize>(&self, event: &str, payload: &S) -> Result<(), QueueError> {
+        self.events.emit(event, serde_json::to_value(payload)?)
+    }
+
+    /// Write a line to this job's log and emit it as `queue:job_log`.
+    ///
+    /// `fields` is stored as JSON next to the message; pass
+    /// `serde_json::Value::Null` for none. Read the log back with
+    /// [`QueueManager::logs()`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// ctx.log(LogLevel::Warn, "Retrying download", &json!({ "url": url, "status": 503 }))?;
+    /// ```
+    pub fn log<S: serde::Serialize>(
+        &self,
+        level: LogLevel,
+        message: &str,
+        fields: &S,
+    ) -> Result<(), QueueError> {
+        let fields = serde_json::to_value(fields)?;
+        let entry = self
+            .store
+            .insert_log(&self.job_id, level, message, &fields)?;
+        self.emit(
+            "queue:job_log",
+            &events::JobLogEvent {
+                job_id: entry.job_id,
+                id: entry.id,
+                level: entry.level,
+                message: entry.message,
+                fields: entry.fields,
+                created_at: entry.created_at,
+            },
+        )
+    }
+
+    /// Save a checkpoint for this job, replacing the previous one.
+    ///
+    /// Checkpoints survive retries, crashes and aborting shutdowns, so the
+    /// next attempt can pick up where this one left off with
+    /// [`load_checkpoint()`](Self::load_checkpoint). They are deleted once the
+    /// job completes, fails for good or is cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let start = ctx.load_checkpoint::<usize>()?.unwrap_or(0);
+    /// for (i, file) in self.files.iter().enumerate().skip(start) {
+    ///     process(file).await?;
+    ///     ctx.save_checkpoint(&(i + 1))?;
+    /// }
+    /// ```
+    pub fn save_checkpoint<S: serde::Serialize>(&self, checkpoint: &S) -> Result<(), QueueError> {
+        let checkpoint = serde_json::to_value(checkpoint)?;
+        self.store.save_checkpoint(&self.job_id, &checkpoint)?;
         Ok(())
     }
 
+    /// Load the last checkpoint saved for this job, if any.
+    pub fn load_checkpoint<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>, QueueError> {
+        let checkpoint = self.store.load_checkpoint(&self.job_id)?;
+        Ok(checkpoint.map(serde_json::from_value).transpose()?)
+    }
+
     /// Check if this job has been cancelled.
     ///
     /// Call this periodically during long-running jobs to support
     /// cooperative cancellation. If it returns `true`, your handler
-    /// should return `Err(QueueError::Cancelled)`.
+    /// should return `Err(QueueError::Cancelled)`. This only reads the
+    /// job's cancellation token, so it is cheap to call often.
     pub fn is_cancelled(&self) -> bool {
-        match self.db.lock() {
-            Ok(conn) => db::is_cancelled(&conn, &self.job_id).unwrap_or(false),
-            Err(_) => false,
-        }
+        self.cancel_token.is_cancelled()
+    }
+
+    /// Wait until this job is cancelled (or times out).
+    ///
+    /// Resolves as soon as [`QueueManager::cancel()`] is called, so handlers
+    /// can race it against their work:
+    ///
+    /// ```ignore
+    /// tokio::select! {
+    ///     result = do_work() => result,
+    ///     _ = ctx.cancelled() => Err(QueueError::Cancelled),
+    /// }
+    /// ```
+    pub async fn cancelled(&self) {
+        self.cancel_token.cancelled().await
+    }
+
+    /// A clone of this job's cancellation token, for passing to code that
+    /// outlives the `&JobContext` borrow (e.g. spawned tasks).
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
     }
 }
 
 /// Trait that job types must implement to be processed by the queue.
 ///
-/// Your job type must be serializable (stored as JSON in SQLite),
+/// Your job type must be serializable (stored as JSON in the job store),
 /// cloneable, and thread-safe.
 ///
 /// # Example
@@ -123,8 +316,35 @@ pub trait JobHandler: Send + Sync + serde::Serialize + serde::de::DeserializeOwn
         ctx: &JobContext,
     ) -> impl std::future::Future<Output = Result<JobResult, QueueError>> + Send;
 
-    /// Optional: a human-readable name for this job type, used in logging.
-    fn job_type(&self) -> &str {
+    /// Key recorded with each job and used to route it back to this handler
+    /// type when several types share one queue (see
+    /// [`QueueManager::register()`]). Per-type concurrency limits and
+    /// statistics are keyed by it too.
+    ///
+    /// Defaults to the Rust type name. Override it with a stable string if the
+    /// type may be renamed or moved, so jobs already in the database still
+    /// find their handler.
+    fn type_key() -> &'static str {
         std::any::type_name::<Self>()
     }
+
+    /// Optional: a human-readable name for this job, shown in the executor's
+    /// log lines and as the `job_type` field of the job's `tracing` span.
+    ///
+    /// Defaults to [`type_key()`](Self::type_key). Routing, per-type
+    /// concurrency limits and statistics always use `type_key()`, so
+    /// overriding this doesn't affect which handler runs the job.
+    fn job_type(&self) -> &str {
+        Self::type_key()
+    }
+
+    /// Schema version of this type's payload, stored with every job.
+    ///
+    /// Bump it when the serialized shape changes in a way older payloads
+    /// can't be deserialized into, and register an upcaster from the
+    /// previous version with [`QueueManager::upcaster()`], so jobs already in
+    /// the database still run. Defaults to 1.
+    fn payload_version() -> u32 {
+        1
+    }
 }
@@ -0,0 +1,23 @@
+//! Internal logging.
+//!
+//! With the `tracing` feature, `log!(level, ...)` emits a `tracing` event at
+//! that level, inside whatever span is current (for job work, the job's
+//! span). Without it, messages go to stderr prefixed with `[tauri-queue]`,
+//! and `debug` messages are dropped.
+
+#[cfg(feature = "tracing")]
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! log {
+    (debug, $($arg:tt)+) => {{
+        let _ = format_args!($($arg)+);
+    }};
+    ($level:ident, $($arg:tt)+) => {
+        eprintln!("[tauri-queue] {}", format_args!($($arg)+))
+    };
+}
@@ -1,13 +1,24 @@
 use crate::{
     config::QueueConfig,
-    db,
     error::QueueError,
+    events::{
+        JobBlockedEvent, JobCancelledEvent, JobFailedEvent, JobRequeuedEvent, JobsCancelledEvent,
+        JobsReorderedEvent,
+    },
     executor::QueueExecutor,
-    types::{QueueJob, QueuePriority},
+    schedule::{QueueSchedule, ScheduleInfo},
+    sink::EventSink,
+    store::{JobOptions, JobStore, NewJob, StoredJob, UniqueInsert},
+    types::{
+        JobAttempt, JobFilter, JobLogEntry, JobProgress, QueueJob, QueueJobStatus, QueueMetrics,
+        QueuePriority, QueueStats, ShutdownMode,
+    },
     JobHandler,
 };
-use rusqlite::Connection;
-use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 /// High-level queue manager providing the public API.
 ///
@@ -29,83 +40,298 @@ use std::sync::{Arc, Mutex};
 /// manager.spawn::<MyJob>(app.handle().clone());
 /// ```
 pub struct QueueManager {
-    db: Arc<Mutex<Connection>>,
+    store: Arc<dyn JobStore>,
     executor: Arc<QueueExecutor>,
 }
 
 impl QueueManager {
     /// Create a new queue manager with the given configuration.
     ///
-    /// Opens (or creates) the SQLite database and requeues any jobs that
-    /// were interrupted by a previous crash.
+    /// Opens (or creates) the SQLite database and requeues jobs interrupted
+    /// by a shutdown. Jobs left `processing` by a crash are requeued by the
+    /// executor once their lease expires, so a database shared with another
+    /// running process is safe to open.
+    #[cfg(feature = "sqlite")]
     pub fn new(config: QueueConfig) -> Result<Self, QueueError> {
-        let db_path = config.db_path.as_deref();
-        let conn = db::open_database(db_path).map_err(|e| QueueError::Other(e.to_string()))?;
+        let store = crate::store::SqliteStore::open(config.db_path.as_deref())
+            .map_err(|e| QueueError::Other(e.to_string()))?;
+        Self::with_store(config, Arc::new(store))
+    }
 
-        // Requeue interrupted jobs from a previous crash
-        let requeued =
-            db::requeue_interrupted(&conn).map_err(|e| QueueError::Other(e.to_string()))?;
+    /// Create a queue manager that keeps its jobs in `store`, e.g. a
+    /// [`MemoryStore`](crate::MemoryStore). `config.db_path` is ignored.
+    ///
+    /// Requeues jobs the store holds as interrupted by a shutdown.
+    pub fn with_store(config: QueueConfig, store: Arc<dyn JobStore>) -> Result<Self, QueueError> {
+        // Requeue jobs interrupted by a shutdown or left over without a lease
+        let requeued = store
+            .requeue_interrupted()
+            .map_err(|e| QueueError::Other(e.to_string()))?;
         if requeued > 0 {
-            eprintln!("[tauri-queue] Requeued {} interrupted jobs", requeued);
+            log!(info, "Requeued {} interrupted jobs", requeued);
         }
 
-        let db = Arc::new(Mutex::new(conn));
-        let executor = Arc::new(QueueExecutor::new(config, Arc::clone(&db)));
+        let executor = Arc::new(QueueExecutor::new(config, Arc::clone(&store)));
 
-        Ok(Self { db, executor })
+        Ok(Self { store, executor })
     }
 
     /// Add a job to the queue. Returns the job ID.
+    ///
+    /// A job with unfinished dependencies is added as `blocked` and becomes
+    /// `pending` once they have all completed. Returns an error if a
+    /// dependency doesn't exist.
+    ///
+    /// If the job has a [unique key](QueueJob::with_unique_key) that another
+    /// blocked, pending or processing job already holds, its
+    /// [`UniqueConflictPolicy`](crate::UniqueConflictPolicy) decides the outcome:
+    /// [`QueueError::DuplicateJob`], the existing job's ID, or the existing
+    /// job cancelled and replaced by this one.
     pub fn add<H>(&self, job: QueueJob<H>) -> Result<String, QueueError>
     where
         H: JobHandler,
     {
-        let conn = self
-            .db
-            .lock()
+        let new_job = new_job(&job)?;
+        let inserted = self
+            .store
+            .insert_many(std::slice::from_ref(&new_job))
+            .map_err(into_queue_error)?;
+        let inserted = inserted_job(&job, inserted.into_iter().next());
+        let job_id = inserted.job_id.clone();
+        self.announce(inserted);
+        self.executor.wake();
+        Ok(job_id)
+    }
+
+    /// Add many jobs in a single transaction. Returns their IDs, in order.
+    ///
+    /// Each job is added as by [`add()`](Self::add), and may depend on jobs
+    /// earlier in the batch. If any job fails, e.g. on a unique key
+    /// conflict under
+    /// [`UniqueConflictPolicy::Reject`](crate::UniqueConflictPolicy::Reject),
+    /// none are added.
+    pub fn add_many<H>(&self, jobs: Vec<QueueJob<H>>) -> Result<Vec<String>, QueueError>
+    where
+        H: JobHandler,
+    {
+        let new_jobs = jobs.iter().map(new_job).collect::<Result<Vec<_>, _>>()?;
+        let inserted: Vec<InsertedJob> = self
+            .store
+            .insert_many(&new_jobs)
+            .map_err(into_queue_error)?
+            .into_iter()
+            .zip(&jobs)
+            .map(|(inserted, job)| inserted_job(job, Some(inserted)))
+            .collect();
+        let job_ids: Vec<String> = inserted.iter().map(|job| job.job_id.clone()).collect();
+        for inserted in inserted {
+            self.announce(inserted);
+        }
+        if !job_ids.is_empty() {
+            self.executor.wake();
+        }
+        Ok(job_ids)
+    }
+
+    /// Emit the events for a job that was just inserted: the job it
+    /// replaced, and a status other than pending.
+    fn announce(&self, inserted: InsertedJob) {
+        if let Some(replaced_id) = inserted.replaced {
+            self.executor.emit(
+                "queue:job_cancelled",
+                JobCancelledEvent {
+                    job_id: replaced_id.clone(),
+                },
+            );
+            self.executor.fail_dependents(&replaced_id);
+        }
+
+        let job_id = inserted.job_id;
+        match inserted.status.as_deref() {
+            Some("blocked") => self.executor.emit(
+                "queue:job_blocked",
+                JobBlockedEvent {
+                    job_id,
+                    depends_on: inserted.depends_on,
+                },
+            ),
+            Some("failed") => self.executor.emit(
+                "queue:job_failed",
+                JobFailedEvent {
+                    job_id,
+                    error: "A dependency did not complete".to_string(),
+                },
+            ),
+            Some("cancelled") => self
+                .executor
+                .emit("queue:job_cancelled", JobCancelledEvent { job_id }),
+            _ => {}
+        }
+    }
+
+    /// Cancel a blocked, pending or processing job by ID.
+    ///
+    /// A running job's cancellation token fires immediately (see
+    /// [`JobContext::cancelled()`](crate::JobContext::cancelled)); the handler
+    /// decides how quickly to stop. Jobs that depend on it are cancelled or failed according to their
+    /// [`DependencyFailurePolicy`](crate::DependencyFailurePolicy).
+    pub fn cancel(&self, job_id: &str) -> Result<(), QueueError> {
+        self.store
+            .cancel(job_id)
             .map_err(|e| QueueError::Other(e.to_string()))?;
-        let data = serde_json::to_value(&job.data)?;
-        db::insert_job(&conn, &job.id, job.priority.as_i32(), &data)
+        self.executor.cancel_running(job_id);
+        self.executor.fail_dependents(job_id);
+        Ok(())
+    }
+
+    /// Cancel every blocked, pending or processing job matching `filter`
+    /// (see [`cancel()`](Self::cancel)) in one transaction. Returns the
+    /// number cancelled.
+    ///
+    /// Emits a single `queue:jobs_cancelled` event listing them all rather
+    /// than one `queue:job_cancelled` per job.
+    pub fn cancel_where(&self, filter: JobFilter) -> Result<u32, QueueError> {
+        let cancelled = self
+            .store
+            .cancel_where(&filter)
             .map_err(|e| QueueError::Other(e.to_string()))?;
-        Ok(job.id)
+        let job_ids: Vec<String> = cancelled.into_iter().map(|(job_id, _)| job_id).collect();
+        for job_id in &job_ids {
+            self.executor.cancel_running(job_id);
+            self.executor.fail_dependents(job_id);
+        }
+        let count = job_ids.len() as u32;
+        if count > 0 {
+            self.executor.emit(
+                "queue:jobs_cancelled",
+                JobsCancelledEvent { job_ids, count },
+            );
+        }
+        Ok(count)
     }
 
-    /// Cancel a pending or processing job by ID.
-    pub fn cancel(&self, job_id: &str) -> Result<(), QueueError> {
-        let conn = self
-            .db
-            .lock()
+    /// Reset a failed, dead, timed-out, cancelled or incompatible job so it
+    /// runs again, e.g. after the user fixed what made it fail.
+    ///
+    /// The job starts over with a fresh attempt count; earlier attempts stay
+    /// in its [`attempts()`](Self::attempts) history. Jobs that were cancelled
+    /// because this one failed are not requeued with it.
+    pub fn requeue(&self, job_id: &str) -> Result<(), QueueError> {
+        if self
+            .store
+            .get(job_id)
+            .map_err(|e| QueueError::Other(e.to_string()))?
+            .is_none()
+        {
+            return Err(QueueError::NotFound(job_id.to_string()));
+        }
+        let previous_status = self
+            .store
+            .requeue(job_id)
             .map_err(|e| QueueError::Other(e.to_string()))?;
-        db::cancel_job(&conn, job_id).map_err(|e| QueueError::Other(e.to_string()))?;
+        self.executor.emit(
+            "queue:job_requeued",
+            JobRequeuedEvent {
+                job_id: job_id.to_string(),
+                previous_status,
+            },
+        );
+        self.executor.wake();
         Ok(())
     }
 
-    /// Reorder a pending job to a new priority.
-    pub fn reorder(&self, job_id: &str, new_priority: QueuePriority) -> Result<(), QueueError> {
-        let conn = self
-            .db
-            .lock()
+    /// Requeue every failed or dead job matching `filter` (see
+    /// [`requeue()`](Self::requeue)). Set statuses on the filter to also
+    /// requeue timed-out, cancelled or incompatible jobs. Returns the number
+    /// requeued.
+    pub fn requeue_all_failed(&self, filter: JobFilter) -> Result<u32, QueueError> {
+        let requeued = self
+            .store
+            .requeue_where(&filter)
             .map_err(|e| QueueError::Other(e.to_string()))?;
+        let count = requeued.len() as u32;
+        for (job_id, previous_status) in requeued {
+            self.executor.emit(
+                "queue:job_requeued",
+                JobRequeuedEvent {
+                    job_id,
+                    previous_status,
+                },
+            );
+        }
+        if count > 0 {
+            self.executor.wake();
+        }
+        Ok(count)
+    }
+
+    /// Get a job's log, oldest first. Pass the ID of the last entry you
+    /// have as `since` to fetch only newer ones.
+    pub fn logs(&self, job_id: &str, since: Option<i64>) -> Result<Vec<JobLogEntry>, QueueError> {
+        self.store
+            .logs(job_id, since)
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Get the attempt history of a job, oldest first.
+    pub fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>, QueueError> {
+        self.store
+            .attempts(job_id)
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
 
+    /// Reorder a pending job to a new priority.
+    pub fn reorder(&self, job_id: &str, new_priority: QueuePriority) -> Result<(), QueueError> {
         // Check job is pending
-        if let Some(job) =
-            db::get_job(&conn, job_id).map_err(|e| QueueError::Other(e.to_string()))?
+        if let Some(job) = self
+            .store
+            .get(job_id)
+            .map_err(|e| QueueError::Other(e.to_string()))?
         {
-            if job.2 != "pending" {
+            if job.status != "pending" {
                 return Err(QueueError::Other(format!(
                     "Can only reorder pending jobs (job {} is {})",
-                    job_id, job.2
+                    job_id, job.status
                 )));
             }
         } else {
             return Err(QueueError::NotFound(job_id.to_string()));
         }
 
-        db::update_priority(&conn, job_id, new_priority.as_i32())
+        self.store
+            .update_priority(job_id, new_priority.as_i32())
             .map_err(|e| QueueError::Other(e.to_string()))?;
+        self.executor.wake();
         Ok(())
     }
 
+    /// Move many pending jobs to a new priority. Jobs that
+    /// don't exist or aren't pending are skipped. Returns the number moved.
+    ///
+    /// Emits a single `queue:jobs_reordered` event listing the moved jobs.
+    pub fn reorder_many(
+        &self,
+        job_ids: &[String],
+        new_priority: QueuePriority,
+    ) -> Result<u32, QueueError> {
+        let job_ids = self
+            .store
+            .update_priorities(job_ids, new_priority.as_i32())
+            .map_err(|e| QueueError::Other(e.to_string()))?;
+        let count = job_ids.len() as u32;
+        if count > 0 {
+            self.executor.emit(
+                "queue:jobs_reordered",
+                JobsReorderedEvent {
+                    job_ids,
+                    priority: new_priority,
+                },
+            );
+            self.executor.wake();
+        }
+        Ok(count)
+    }
+
     /// Pause the queue. The current job will finish, but no new jobs start.
     pub fn pause(&self) {
         self.executor.pause();
@@ -116,18 +342,113 @@ impl QueueManager {
         self.executor.resume();
     }
 
+    /// Stop the executor. Returns a future that resolves once it has exited.
+    ///
+    /// [`ShutdownMode::Drain`] starts no new jobs and waits for the running
+    /// ones to finish; [`ShutdownMode::Abort`] cancels them and marks them
+    /// `interrupted`, and they run again on the next start. Calling it again
+    /// with `Abort` cuts a drain short. Jobs added after a shutdown are stored
+    /// and picked up on the next start.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// app.run(move |app, event| {
+    ///     if let tauri::RunEvent::ExitRequested { api, .. } = event {
+    ///         api.prevent_exit();
+    ///         let queue = queue.clone();
+    ///         let app = app.clone();
+    ///         tauri::async_runtime::spawn(async move {
+    ///             queue.shutdown(ShutdownMode::Drain).await;
+    ///             app.exit(0);
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub async fn shutdown(&self, mode: ShutdownMode) {
+        self.executor.shutdown(mode).await;
+    }
+
     /// Check if the queue is currently paused.
     pub fn is_paused(&self) -> bool {
         self.executor.is_paused()
     }
 
+    /// Pause the named queue `queue`. Its running jobs finish; other queues
+    /// keep going.
+    pub fn pause_queue(&self, queue: &str) {
+        self.executor.pause_queue(queue);
+    }
+
+    /// Resume the named queue `queue` after a pause.
+    pub fn resume_queue(&self, queue: &str) {
+        self.executor.resume_queue(queue);
+    }
+
+    /// Check if the named queue `queue` is paused.
+    pub fn is_queue_paused(&self, queue: &str) -> bool {
+        self.executor.is_queue_paused(queue)
+    }
+
+    /// Get job counts and pause state of the named queue `queue`.
+    pub fn queue_stats(&self, queue: &str) -> Result<QueueStats, QueueError> {
+        let stats = self.queues()?;
+        Ok(stats
+            .into_iter()
+            .find(|stats| stats.queue == queue)
+            .unwrap_or_else(|| QueueStats {
+                queue: queue.to_string(),
+                paused: self.is_queue_paused(queue),
+                ..QueueStats::default()
+            }))
+    }
+
+    /// Get job counts and pause state of every named queue that has jobs or
+    /// is configured, in name order.
+    pub fn queues(&self) -> Result<Vec<QueueStats>, QueueError> {
+        let counts = self
+            .store
+            .status_counts()
+            .map_err(|e| QueueError::Other(e.to_string()))?;
+
+        let mut stats: BTreeMap<String, QueueStats> = self
+            .executor
+            .queue_names()
+            .into_iter()
+            .map(|queue| (queue.clone(), QueueStats::default()))
+            .collect();
+        for (queue, status, count) in counts {
+            stats.entry(queue).or_default().add(&status, count);
+        }
+        Ok(stats
+            .into_iter()
+            .map(|(queue, mut stats)| {
+                stats.paused = self.is_queue_paused(&queue);
+                stats.queue = queue;
+                stats
+            })
+            .collect())
+    }
+
+    /// Get counts per job type and status, run durations (average, p50,
+    /// p95), failure rate, throughput and queue depth. Durations, failure
+    /// rate and throughput cover jobs that finished within
+    /// [`QueueConfig::stats_window`].
+    pub fn stats(&self) -> Result<QueueMetrics, QueueError> {
+        self.executor.metrics()
+    }
+
+    /// The [`JobStore`] this queue keeps its jobs in.
+    pub fn store(&self) -> Arc<dyn JobStore> {
+        Arc::clone(&self.store)
+    }
+
     /// Get all jobs as `(id, status)` pairs, ordered by status then priority.
     pub fn list_jobs(&self) -> Result<Vec<(String, String)>, QueueError> {
-        let conn = self
-            .db
-            .lock()
+        let jobs = self
+            .store
+            .list()
             .map_err(|e| QueueError::Other(e.to_string()))?;
-        let jobs = db::list_all_jobs(&conn).map_err(|e| QueueError::Other(e.to_string()))?;
         Ok(jobs
             .into_iter()
             .map(|(id, status, _)| (id, status))
@@ -136,34 +457,316 @@ impl QueueManager {
 
     /// Get all jobs as `(id, status, data_json)` tuples.
     pub fn list_jobs_with_data(&self) -> Result<Vec<(String, String, String)>, QueueError> {
-        let conn = self
-            .db
-            .lock()
-            .map_err(|e| QueueError::Other(e.to_string()))?;
-        db::list_all_jobs(&conn).map_err(|e| QueueError::Other(e.to_string()))
+        self.store
+            .list()
+            .map_err(|e| QueueError::Other(e.to_string()))
     }
 
-    /// Prune completed/failed/cancelled jobs older than `days`.
+    /// Get a job by ID, with its status, timestamps, error and output.
+    ///
+    /// Returns `None` if the job doesn't exist, or an error if its payload
+    /// can't be decoded as `H`.
+    pub fn get<H>(&self, job_id: &str) -> Result<Option<QueueJob<H>>, QueueError>
+    where
+        H: JobHandler,
+    {
+        self.store
+            .get(job_id)
+            .map_err(|e| QueueError::Other(e.to_string()))?
+            .map(|job| self.decode(job))
+            .transpose()
+    }
+
+    /// Get one page of jobs of type `H` matching `filter`, newest first.
+    ///
+    /// If the filter has no `job_type`, it matches `H::type_key()`.
+    pub fn query<H>(&self, mut filter: JobFilter) -> Result<Vec<QueueJob<H>>, QueueError>
+    where
+        H: JobHandler,
+    {
+        filter
+            .job_type
+            .get_or_insert_with(|| H::type_key().to_string());
+
+        self.store
+            .query(&filter)
+            .map_err(|e| QueueError::Other(e.to_string()))?
+            .into_iter()
+            .map(|job| self.decode(job))
+            .collect()
+    }
+
+    /// Upcast a stored job's payload to the current version of its handler
+    /// and build the typed job.
+    fn decode<H>(&self, mut job: StoredJob) -> Result<QueueJob<H>, QueueError>
+    where
+        H: JobHandler,
+    {
+        let (data, payload_version) =
+            self.executor
+                .upcast(job.job_type.as_deref(), job.payload_version, job.data)?;
+        job.data = data;
+        job.payload_version = payload_version;
+        job.into_job()
+            .map(|job| self.with_effective_priority(job))
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Apply the configured [`PriorityAging`](crate::PriorityAging) to a
+    /// pending job's `effective_priority`.
+    fn with_effective_priority<H>(&self, mut job: QueueJob<H>) -> QueueJob<H>
+    where
+        H: JobHandler,
+    {
+        let config = self.executor.config();
+        let Some(aging) = &config.priority_aging else {
+            return job;
+        };
+        if job.status != QueueJobStatus::Pending {
+            return job;
+        }
+        if let Some(since) = job.next_run_at.as_deref().or(job.created_at.as_deref()) {
+            job.effective_priority =
+                aging.effective_priority(job.priority, since, config.clock.now());
+        }
+        job
+    }
+
+    /// Get the last progress a job reported, or `None` if it has reported
+    /// none. Lets a frontend that mounts mid-run catch up.
+    pub fn progress(&self, job_id: &str) -> Result<Option<JobProgress>, QueueError> {
+        self.store
+            .progress(job_id)
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Prune completed/failed/dead/cancelled/timed-out/incompatible jobs older
+    /// than `days`.
     /// Returns the number of jobs deleted.
     pub fn prune(&self, days: u32) -> Result<u32, QueueError> {
-        let conn = self
-            .db
-            .lock()
+        self.store
+            .prune(days)
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Add a recurring schedule. Returns the schedule ID.
+    ///
+    /// Schedules are persisted, so they keep firing across restarts. If the
+    /// app was closed when a run came due, the schedule fires once on the
+    /// next start and then continues from there.
+    pub fn add_schedule<H>(&self, schedule: QueueSchedule<H>) -> Result<String, QueueError>
+    where
+        H: JobHandler,
+    {
+        schedule.spec.validate()?;
+        let first_run = match schedule.start_at {
+            Some(at) => at,
+            None => schedule
+                .spec
+                .next_after(chrono::Utc::now())?
+                .ok_or_else(|| QueueError::InvalidSchedule("Schedule never fires".to_string()))?,
+        };
+
+        let info = ScheduleInfo {
+            id: schedule.id,
+            spec: schedule.spec,
+            job_type: Some(H::type_key().to_string()),
+            priority: schedule.priority,
+            data_json: serde_json::to_string(&schedule.data)?,
+            retry_policy: schedule.retry_policy,
+            paused: false,
+            next_run_at: first_run.to_rfc3339(),
+            last_run_at: None,
+            created_at: None,
+            payload_version: H::payload_version(),
+        };
+
+        self.store
+            .insert_schedule(&info)
             .map_err(|e| QueueError::Other(e.to_string()))?;
-        db::prune_old_jobs(&conn, days).map_err(|e| QueueError::Other(e.to_string()))
+        Ok(info.id)
+    }
+
+    /// Get all schedules, ordered by their next run time.
+    pub fn list_schedules(&self) -> Result<Vec<ScheduleInfo>, QueueError> {
+        self.store
+            .list_schedules()
+            .map_err(|e| QueueError::Other(e.to_string()))
+    }
+
+    /// Pause a schedule. It stops creating jobs until resumed.
+    pub fn pause_schedule(&self, schedule_id: &str) -> Result<(), QueueError> {
+        self.set_schedule_paused(schedule_id, true)
     }
 
-    /// Spawn the background executor and return the manager wrapped in an `Arc`.
+    /// Resume a paused schedule.
+    pub fn resume_schedule(&self, schedule_id: &str) -> Result<(), QueueError> {
+        self.set_schedule_paused(schedule_id, false)
+    }
+
+    fn set_schedule_paused(&self, schedule_id: &str, paused: bool) -> Result<(), QueueError> {
+        if !self
+            .store
+            .set_schedule_paused(schedule_id, paused)
+            .map_err(|e| QueueError::Other(e.to_string()))?
+        {
+            return Err(QueueError::NotFound(schedule_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete a schedule. Jobs it already created are not affected.
+    pub fn delete_schedule(&self, schedule_id: &str) -> Result<(), QueueError> {
+        if !self
+            .store
+            .delete_schedule(schedule_id)
+            .map_err(|e| QueueError::Other(e.to_string()))?
+        {
+            return Err(QueueError::NotFound(schedule_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Register a handler type, so jobs of that type can share this queue
+    /// with other types. Returns `&self` for chaining.
+    ///
+    /// Each job records its [`JobHandler::type_key()`] when added; the
+    /// executor routes it to the matching handler. Jobs whose type has no
+    /// registered handler stay pending until one is registered.
+    pub fn register<H>(&self) -> &Self
+    where
+        H: JobHandler + 'static,
+    {
+        self.executor.register::<H>();
+        self
+    }
+
+    /// Register a function that migrates `H` payloads stored with
+    /// `from_version` to `from_version + 1`. Returns `&self` for chaining.
+    ///
+    /// When a job's stored [payload version](JobHandler::payload_version) is
+    /// older than its handler's, the executor runs the upcasters for each
+    /// step in turn before deserializing, and saves the result. A job that
+    /// still can't be decoded, because a step has no upcaster, an upcaster
+    /// fails, or deserialization fails, is moved to `incompatible` and
+    /// `queue:job_incompatible` is emitted. [`get()`](Self::get) and
+    /// [`query()`](Self::query) upcast payloads the same way.
+    ///
+    /// # Example
     ///
-    /// The returned `Arc<QueueManager>` can be stored in Tauri's managed state
-    /// and shared across commands.
-    pub fn spawn<H>(self, app_handle: tauri::AppHandle) -> Arc<Self>
+    /// ```rust,ignore
+    /// // Version 2 of EmailJob added a `cc` list
+    /// queue.upcaster::<EmailJob, _>(1, |mut data| {
+    ///     data["cc"] = serde_json::json!([]);
+    ///     Ok(data)
+    /// });
+    /// ```
+    pub fn upcaster<H, F>(&self, from_version: u32, upcast: F) -> &Self
     where
         H: JobHandler + 'static,
+        F: Fn(Value) -> Result<Value, QueueError> + Send + Sync + 'static,
     {
+        self.executor
+            .register_upcaster::<H, F>(from_version, upcast);
+        self
+    }
+
+    /// All job types with a registered handler.
+    pub fn job_types(&self) -> Vec<String> {
+        self.executor.job_types()
+    }
+
+    /// Spawn the background executor for all registered handler types and
+    /// return the manager wrapped in an `Arc`.
+    ///
+    /// Events are emitted through `events`: pass `app.handle().clone()` in a
+    /// Tauri app (with the `tauri` feature), or a [`ChannelEventSink`](crate::ChannelEventSink)
+    /// anywhere else. The returned `Arc<QueueManager>` can be stored in
+    /// Tauri's managed state and shared across commands.
+    pub fn start(self, events: impl EventSink) -> Arc<Self> {
         let manager = Arc::new(self);
         let executor = Arc::clone(&manager.executor);
-        executor.spawn::<H>(app_handle);
+        executor.spawn(Arc::new(events));
         manager
     }
+
+    /// Register `H` and spawn the background executor. Shorthand for
+    /// `register::<H>()` followed by [`start()`](Self::start).
+    pub fn spawn<H>(self, events: impl EventSink) -> Arc<Self>
+    where
+        H: JobHandler + 'static,
+    {
+        self.register::<H>();
+        self.start(events)
+    }
+}
+
+/// A job written by [`JobStore::insert_many()`].
+struct InsertedJob {
+    /// The new job's ID, or the existing job's under
+    /// [`UniqueConflictPolicy::ReturnExisting`](crate::UniqueConflictPolicy::ReturnExisting).
+    job_id: String,
+    /// Status the job was inserted with. `None` = nothing was inserted.
+    status: Option<String>,
+    depends_on: Vec<String>,
+    /// Pending job with the same unique key that this one replaced.
+    replaced: Option<String>,
+}
+
+/// Serialize `job` for [`JobStore::insert_many()`].
+fn new_job<H>(job: &QueueJob<H>) -> Result<NewJob, QueueError>
+where
+    H: JobHandler,
+{
+    Ok(NewJob {
+        id: job.id.clone(),
+        priority: job.priority.as_i32(),
+        data: serde_json::to_value(&job.data)?,
+        options: JobOptions {
+            retry_policy: job.retry_policy.clone(),
+            next_run_at: job.next_run_at.clone(),
+            job_type: Some(H::type_key().to_string()),
+            depends_on: job.depends_on.clone(),
+            dependency_policy: job.dependency_policy,
+            timeout: job.timeout,
+            unique_key: job.unique_key.clone(),
+            queue: Some(job.queue.clone()),
+            payload_version: Some(H::payload_version()),
+        },
+        on_conflict: job.on_conflict,
+    })
+}
+
+/// What became of `job` in the store: inserted, or resolved to the
+/// existing job holding its unique key.
+fn inserted_job<H>(job: &QueueJob<H>, inserted: Option<UniqueInsert>) -> InsertedJob
+where
+    H: JobHandler,
+{
+    match inserted {
+        Some(UniqueInsert::Conflict { existing_id }) => InsertedJob {
+            job_id: existing_id,
+            status: None,
+            depends_on: Vec::new(),
+            replaced: None,
+        },
+        Some(UniqueInsert::Inserted { status, replaced }) => InsertedJob {
+            job_id: job.id.clone(),
+            status: Some(status),
+            depends_on: job.depends_on.clone(),
+            replaced,
+        },
+        None => InsertedJob {
+            job_id: job.id.clone(),
+            status: None,
+            depends_on: Vec::new(),
+            replaced: None,
+        },
+    }
+}
+
+/// Recover a [`QueueError`] passed through the store.
+fn into_queue_error(err: anyhow::Error) -> QueueError {
+    err.downcast::<QueueError>()
+        .unwrap_or_else(|err| QueueError::Other(err.to_string()))
 }
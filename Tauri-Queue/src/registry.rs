@@ -0,0 +1,155 @@
+use crate::{error::QueueError, types::JobResult, JobContext, JobHandler};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// A type-erased, ready-to-run job.
+pub(crate) type JobFuture = Pin<Box<dyn Future<Output = Result<JobResult, QueueError>> + Send>>;
+
+/// A deserialized job, ready to run.
+pub(crate) struct PreparedJob {
+    /// The handler's [`JobHandler::job_type()`], for logging.
+    pub name: String,
+    pub future: JobFuture,
+}
+
+/// Deserializes a stored payload into its handler type and starts it.
+pub(crate) type JobRunner =
+    Arc<dyn Fn(Value, JobContext) -> Result<PreparedJob, serde_json::Error> + Send + Sync>;
+
+/// Migrates a stored payload from one schema version to the next.
+pub(crate) type Upcaster = Arc<dyn Fn(Value) -> Result<Value, QueueError> + Send + Sync>;
+
+/// Maps job types to the handlers that process them.
+///
+/// Each row in `queue_jobs` records the [`JobHandler::type_key()`] it was
+/// created from. The executor only claims rows whose type is registered,
+/// so jobs for a handler that isn't registered (yet) stay pending instead
+/// of failing.
+///
+/// It also holds the upcasters that bring payloads stored with an older
+/// [`JobHandler::payload_version()`] up to the current one.
+#[derive(Default, Clone)]
+pub struct JobRegistry {
+    runners: HashMap<String, JobRunner>,
+    /// Current payload version of each registered job type.
+    versions: HashMap<String, u32>,
+    /// Upcasters per job type, keyed by the version they migrate from.
+    upcasters: HashMap<String, BTreeMap<u32, Upcaster>>,
+    /// Handler for rows written before job types were recorded.
+    default_type: Option<String>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register handler type `H` under [`JobHandler::type_key()`].
+    ///
+    /// The first registered type also handles legacy rows that have no
+    /// job type recorded.
+    pub fn register<H>(&mut self)
+    where
+        H: JobHandler + 'static,
+    {
+        let job_type = H::type_key().to_string();
+        let runner: JobRunner = Arc::new(
+            |data: Value, ctx: JobContext| -> Result<PreparedJob, serde_json::Error> {
+                let handler: H = serde_json::from_value(data)?;
+                let name = handler.job_type().to_string();
+                let future: JobFuture = Box::pin(async move { handler.execute(&ctx).await });
+                Ok(PreparedJob { name, future })
+            },
+        );
+        self.runners.insert(job_type.clone(), runner);
+        self.versions.insert(job_type.clone(), H::payload_version());
+        self.default_type.get_or_insert(job_type);
+    }
+
+    /// Register `upcast` to migrate `H` payloads stored with version
+    /// `from_version` to `from_version + 1`. Replaces any upcaster already
+    /// registered for that step. Works whether or not `H` is registered as a
+    /// handler, so typed lookups can upcast too.
+    pub fn register_upcaster<H, F>(&mut self, from_version: u32, upcast: F)
+    where
+        H: JobHandler + 'static,
+        F: Fn(Value) -> Result<Value, QueueError> + Send + Sync + 'static,
+    {
+        let job_type = H::type_key().to_string();
+        self.versions.insert(job_type.clone(), H::payload_version());
+        self.upcasters
+            .entry(job_type)
+            .or_default()
+            .insert(from_version, Arc::new(upcast));
+    }
+
+    /// Bring a payload of `job_type` stored with `version` up to the current
+    /// version of its handler, one upcaster at a time. Returns the payload
+    /// and the version it ended up at.
+    ///
+    /// Fails with [`QueueError::IncompatiblePayload`] if a step has no
+    /// upcaster, an upcaster fails, or the payload is newer than the handler.
+    /// Payloads of unregistered types are returned unchanged.
+    pub(crate) fn upcast(
+        &self,
+        job_type: Option<&str>,
+        mut version: u32,
+        mut data: Value,
+    ) -> Result<(Value, u32), QueueError> {
+        let Some(job_type) = job_type.or(self.default_type.as_deref()) else {
+            return Ok((data, version));
+        };
+        let Some(&current) = self.versions.get(job_type) else {
+            return Ok((data, version));
+        };
+        if version > current {
+            return Err(QueueError::IncompatiblePayload(format!(
+                "payload version {} of '{}' is newer than the handler's version {}",
+                version, job_type, current
+            )));
+        }
+        let upcasters = self.upcasters.get(job_type);
+        while version < current {
+            let upcast = upcasters
+                .and_then(|upcasters| upcasters.get(&version))
+                .ok_or_else(|| {
+                    QueueError::IncompatiblePayload(format!(
+                        "no upcaster for '{}' from payload version {}",
+                        job_type, version
+                    ))
+                })?;
+            data = upcast(data).map_err(|e| {
+                QueueError::IncompatiblePayload(format!(
+                    "upcasting '{}' from payload version {} failed: {}",
+                    job_type, version, e
+                ))
+            })?;
+            version += 1;
+        }
+        Ok((data, version))
+    }
+
+    /// Whether a handler is registered for `job_type`.
+    pub fn contains(&self, job_type: &str) -> bool {
+        self.runners.contains_key(job_type)
+    }
+
+    /// All registered job types.
+    pub fn job_types(&self) -> Vec<String> {
+        self.runners.keys().cloned().collect()
+    }
+
+    /// Whether rows without a recorded job type can be handled.
+    pub(crate) fn has_default(&self) -> bool {
+        self.default_type.is_some()
+    }
+
+    /// Find the runner for a row's job type (`None` = legacy untyped row).
+    pub(crate) fn runner(&self, job_type: Option<&str>) -> Option<JobRunner> {
+        let job_type = job_type.or(self.default_type.as_deref())?;
+        self.runners.get(job_type).cloned()
+    }
+}
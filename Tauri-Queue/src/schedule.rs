@@ -0,0 +1,147 @@
+use crate::{
+    error::QueueError,
+    types::{QueuePriority, RetryPolicy},
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// When a recurring schedule fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleSpec {
+    /// A cron expression, evaluated in UTC.
+    ///
+    /// Accepts standard 5-field expressions (`min hour day month weekday`)
+    /// as well as 6/7-field expressions with leading seconds and trailing year.
+    Cron(String),
+    /// A fixed interval, measured from the previous run.
+    Interval(Duration),
+}
+
+impl ScheduleSpec {
+    /// Check that the spec can produce run times.
+    pub fn validate(&self) -> Result<(), QueueError> {
+        match self {
+            ScheduleSpec::Cron(expr) => parse_cron(expr).map(|_| ()),
+            ScheduleSpec::Interval(interval) if interval.is_zero() => Err(
+                QueueError::InvalidSchedule("Interval must be greater than zero".to_string()),
+            ),
+            ScheduleSpec::Interval(_) => Ok(()),
+        }
+    }
+
+    /// The first run time strictly after `after`, or `None` if the
+    /// schedule never fires again.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, QueueError> {
+        match self {
+            ScheduleSpec::Cron(expr) => Ok(parse_cron(expr)?.after(&after).next()),
+            ScheduleSpec::Interval(interval) => {
+                let interval = chrono::Duration::from_std(*interval)
+                    .map_err(|e| QueueError::InvalidSchedule(e.to_string()))?;
+                Ok(after.checked_add_signed(interval))
+            }
+        }
+    }
+}
+
+fn parse_cron(expr: &str) -> Result<cron::Schedule, QueueError> {
+    // The cron crate expects a leading seconds field
+    let expr = expr.trim();
+    let normalized = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&normalized).map_err(|e| {
+        QueueError::InvalidSchedule(format!("Invalid cron expression '{}': {}", expr, e))
+    })
+}
+
+/// A recurring job template. Each time the schedule comes due, the executor
+/// enqueues a new [`QueueJob`](crate::QueueJob) carrying a copy of `data`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+pub struct QueueSchedule<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync,
+{
+    pub id: String,
+    pub spec: ScheduleSpec,
+    pub priority: QueuePriority,
+    pub data: T,
+    /// Retry policy for the jobs this schedule creates.
+    pub retry_policy: Option<RetryPolicy>,
+    /// First run time. `None` = one period from now.
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+impl<T> QueueSchedule<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync,
+{
+    /// Create a schedule that fires on a cron expression.
+    pub fn cron(expr: impl Into<String>, data: T) -> Self {
+        Self::new(ScheduleSpec::Cron(expr.into()), data)
+    }
+
+    /// Create a schedule that fires at a fixed interval.
+    pub fn every(interval: Duration, data: T) -> Self {
+        Self::new(ScheduleSpec::Interval(interval), data)
+    }
+
+    fn new(spec: ScheduleSpec, data: T) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            spec,
+            priority: QueuePriority::Normal,
+            data,
+            retry_policy: None,
+            start_at: None,
+        }
+    }
+
+    /// Set a custom ID for this schedule (builder pattern).
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Set the priority of the jobs this schedule creates (builder pattern).
+    pub fn with_priority(mut self, priority: QueuePriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the retry policy of the jobs this schedule creates (builder pattern).
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Set the time of the first run (builder pattern).
+    pub fn starting_at(mut self, at: DateTime<Utc>) -> Self {
+        self.start_at = Some(at);
+        self
+    }
+}
+
+/// A stored schedule, as returned by [`QueueManager::list_schedules()`](crate::QueueManager::list_schedules).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleInfo {
+    pub id: String,
+    pub spec: ScheduleSpec,
+    pub job_type: Option<String>,
+    pub priority: QueuePriority,
+    pub data_json: String,
+    pub retry_policy: Option<RetryPolicy>,
+    pub paused: bool,
+    pub next_run_at: String,
+    pub last_run_at: Option<String>,
+    pub created_at: Option<String>,
+    /// Schema version of `data_json`, as in [`QueueJob`](crate::QueueJob)
+    /// payloads.
+    pub payload_version: u32,
+}
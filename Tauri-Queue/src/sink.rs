@@ -0,0 +1,75 @@
+use crate::error::QueueError;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// Destination for queue events (`queue:job_started`, `queue:job_progress`, ...).
+///
+/// The executor and [`JobContext`](crate::JobContext) emit every event through
+/// a sink, so the queue itself doesn't depend on any UI framework. With the
+/// `tauri` feature, `tauri::AppHandle` is a sink that forwards events to the
+/// frontend; [`ChannelEventSink`] works anywhere else (CLIs, servers, tests).
+pub trait EventSink: Send + Sync + 'static {
+    /// Deliver one event. `payload` is the serialized event struct.
+    fn emit(&self, event: &str, payload: Value) -> Result<(), QueueError>;
+}
+
+#[cfg(feature = "tauri")]
+impl<R: tauri::Runtime> EventSink for tauri::AppHandle<R> {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), QueueError> {
+        tauri::Emitter::emit(self, event, payload).map_err(|e| QueueError::Event(e.to_string()))
+    }
+}
+
+/// An event delivered by [`ChannelEventSink`].
+#[derive(Debug, Clone)]
+pub struct QueueEvent {
+    /// Event name, e.g. `queue:job_completed`.
+    pub name: String,
+    /// The serialized event struct.
+    pub payload: Value,
+}
+
+impl QueueEvent {
+    /// Deserialize the payload into its event struct, e.g.
+    /// [`JobCompletedEvent`](crate::events::JobCompletedEvent).
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(self.payload.clone())
+    }
+}
+
+/// An [`EventSink`] that sends events down an unbounded tokio channel.
+///
+/// # Example
+///
+/// ```ignore
+/// let (sink, mut events) = ChannelEventSink::new();
+/// let queue = queue.spawn::<MyJob>(sink);
+///
+/// while let Some(event) = events.recv().await {
+///     println!("{}: {}", event.name, event.payload);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChannelEventSink {
+    sender: mpsc::UnboundedSender<QueueEvent>,
+}
+
+impl ChannelEventSink {
+    /// Create a sink and the receiver its events arrive on.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<QueueEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, receiver)
+    }
+}
+
+impl EventSink for ChannelEventSink {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), QueueError> {
+        self.sender
+            .send(QueueEvent {
+                name: event.to_string(),
+                payload,
+            })
+            .map_err(|_| QueueError::Event("Event receiver was dropped".to_string()))
+    }
+}
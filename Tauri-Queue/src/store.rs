@@ -0,0 +1,2428 @@
+//! Pluggable job storage.
+//!
+//! [`JobStore`] covers everything the queue keeps about its jobs: inserting,
+//! claiming and leasing them, status changes, dependencies, attempts, logs,
+//! checkpoints, progress, statistics and recurring schedules. The executor
+//! and [`QueueManager`](crate::QueueManager) only ever talk to a `JobStore`.
+//! [`SqliteStore`] (behind the `sqlite` feature) persists jobs with the same
+//! schema and queries as the rest of the crate; [`MemoryStore`] keeps them
+//! in process, with no database at all.
+
+use crate::error::QueueError;
+use crate::schedule::ScheduleInfo;
+use crate::types::{
+    DependencyFailurePolicy, JobAttempt, JobFilter, JobLogEntry, JobProgress, JobTypeMetrics,
+    LogLevel, PriorityAging, QueueJob, QueueJobStatus, QueueMetrics, QueuePriority, RetryPolicy,
+    UniqueConflictPolicy, DEFAULT_QUEUE,
+};
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use crossbeam_skiplist::SkipMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "sqlite")]
+use crate::db;
+#[cfg(feature = "sqlite")]
+use rusqlite::Connection;
+#[cfg(feature = "sqlite")]
+use std::sync::{Mutex, MutexGuard};
+
+/// Optional per-job settings stored alongside the job payload.
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+    /// Retry policy for the job. `None` = use the executor's default.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Earliest time the job may run (RFC 3339). `None` = immediately.
+    pub next_run_at: Option<String>,
+    /// Job type, from [`JobHandler::type_key()`](crate::JobHandler::type_key).
+    pub job_type: Option<String>,
+    /// IDs of jobs that must complete before this one may run.
+    pub depends_on: Vec<String>,
+    /// What happens to the job if a dependency fails or is cancelled.
+    pub dependency_policy: DependencyFailurePolicy,
+    /// Maximum run time of one attempt. `None` = no limit.
+    pub timeout: Option<Duration>,
+    /// Deduplication key, enforced while the job is blocked, pending or processing.
+    pub unique_key: Option<String>,
+    /// Named queue the job runs in. `None` = [`DEFAULT_QUEUE`].
+    pub queue: Option<String>,
+    /// Schema version of the payload, from
+    /// [`JobHandler::payload_version()`](crate::JobHandler::payload_version).
+    /// `None` = 1.
+    pub payload_version: Option<u32>,
+}
+
+/// A job to add with [`JobStore::insert_many()`].
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub id: String,
+    pub priority: i32,
+    pub data: Value,
+    pub options: JobOptions,
+    /// What happens if another active job holds `options.unique_key`.
+    pub on_conflict: UniqueConflictPolicy,
+}
+
+/// Outcome of adding one job of a [`JobStore::insert_many()`] batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniqueInsert {
+    /// The job was inserted with `status`. `replaced` is the pending job it
+    /// replaced, which is now cancelled.
+    Inserted {
+        status: String,
+        replaced: Option<String>,
+    },
+    /// Another active job holds the key and was left alone.
+    Conflict { existing_id: String },
+}
+
+/// A job claimed by [`JobStore::claim_next()`], already marked as processing.
+#[derive(Debug, Clone)]
+pub struct ClaimedJob {
+    pub id: String,
+    pub job_type: Option<String>,
+    pub data: Value,
+    pub timeout: Option<Duration>,
+    /// Which attempt this claim starts, counting from 1.
+    pub attempt: u32,
+    /// Schema version the payload was stored with.
+    pub payload_version: u32,
+}
+
+/// A full job row, with the payload still as JSON.
+#[derive(Debug, Clone)]
+pub struct StoredJob {
+    pub id: String,
+    pub priority: i32,
+    pub status: String,
+    pub job_type: Option<String>,
+    pub data: Value,
+    pub created_at: Option<String>,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub error_message: Option<String>,
+    pub result_json: Option<String>,
+    pub attempts: u32,
+    pub retry_policy: Option<RetryPolicy>,
+    pub next_run_at: Option<String>,
+    pub dependency_policy: String,
+    pub depends_on: Vec<String>,
+    pub progress: Option<JobProgress>,
+    pub timeout: Option<Duration>,
+    pub unique_key: Option<String>,
+    pub queue: String,
+    pub payload_version: u32,
+}
+
+impl StoredJob {
+    /// Deserialize the payload into `T` and build a [`QueueJob`].
+    pub fn into_job<T>(self) -> Result<QueueJob<T>>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + Clone + Send + Sync,
+    {
+        let status = QueueJobStatus::parse(&self.status)
+            .ok_or_else(|| anyhow::anyhow!("Unknown job status '{}'", self.status))?;
+        let data = serde_json::from_value(self.data)
+            .with_context(|| format!("Failed to decode payload of job {}", self.id))?;
+        Ok(QueueJob {
+            id: self.id,
+            priority: QueuePriority::from_i32(self.priority),
+            effective_priority: QueuePriority::from_i32(self.priority),
+            status,
+            data,
+            created_at: self.created_at,
+            started_at: self.started_at,
+            completed_at: self.completed_at,
+            error_message: self.error_message,
+            output: self.result_json,
+            retry_policy: self.retry_policy,
+            attempts: self.attempts,
+            next_run_at: self.next_run_at,
+            depends_on: self.depends_on,
+            progress: self.progress,
+            timeout: self.timeout,
+            unique_key: self.unique_key,
+            on_conflict: UniqueConflictPolicy::default(),
+            queue: self.queue,
+            dependency_policy: DependencyFailurePolicy::parse(&self.dependency_policy)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Storage backend for queue jobs.
+///
+/// Implementations must be safe to share between threads: two concurrent
+/// [`claim_next()`](Self::claim_next) or
+/// [`claim_next_aged()`](Self::claim_next_aged) calls never return the
+/// same job. Methods that change a job in a given status leave it alone,
+/// and report so, once it has moved on, e.g. because it was cancelled.
+pub trait JobStore: Send + Sync {
+    /// Insert a new job. Returns the status it was inserted with: `pending`,
+    /// or `blocked` if it has unfinished dependencies. If a dependency has
+    /// already failed or been cancelled, the job's dependency policy is
+    /// applied straight away and `cancelled` or `failed` is returned.
+    fn insert(
+        &self,
+        job_id: &str,
+        priority: i32,
+        data: &Value,
+        options: &JobOptions,
+    ) -> Result<String>;
+
+    /// Insert a batch of jobs, all or none. Jobs may depend on jobs earlier
+    /// in the batch.
+    ///
+    /// A job whose unique key another active job (blocked, pending,
+    /// processing or interrupted) already holds is handled by its
+    /// `on_conflict` policy: the whole batch fails with
+    /// [`QueueError::DuplicateJob`], the holder is reported as a
+    /// [`UniqueInsert::Conflict`], or a holder that hasn't started yet is
+    /// cancelled and replaced.
+    fn insert_many(&self, jobs: &[NewJob]) -> Result<Vec<UniqueInsert>>;
+
+    /// Atomically pick the next runnable pending job (highest priority,
+    /// oldest first) whose type is in `job_types`, or that has no type if
+    /// `include_untyped` is set, and mark it as processing, without a lease.
+    fn claim_next(&self, job_types: &[String], include_untyped: bool)
+        -> Result<Option<ClaimedJob>>;
+
+    /// Like [`claim_next()`](Self::claim_next), but only from the named
+    /// queue `queue`, leasing the job to `lease` as `(worker_id, duration)`.
+    ///
+    /// If `aging` is set, jobs are picked by their effective priority: a
+    /// pending job moves up one level for every `aging.interval` it has
+    /// waited since its `next_run_at` (or creation) until the given time,
+    /// up to High.
+    fn claim_next_aged(
+        &self,
+        queue: &str,
+        job_types: &[String],
+        include_untyped: bool,
+        lease: (&str, Duration),
+        aging: Option<(&PriorityAging, DateTime<Utc>)>,
+    ) -> Result<Option<ClaimedJob>>;
+
+    /// Extend the lease `worker_id` holds on a processing job by `lease`
+    /// from now. Returns `false` if the job no longer belongs to the worker
+    /// or is no longer processing.
+    fn renew_lease(&self, job_id: &str, worker_id: &str, lease: Duration) -> Result<bool>;
+
+    /// Put processing jobs whose lease has run out back into the queue,
+    /// recording the lost attempt. Returns the IDs of the reclaimed jobs.
+    fn reclaim_expired_leases(&self) -> Result<Vec<String>>;
+
+    /// Put jobs interrupted by a shutdown, and processing jobs without a
+    /// lease, back into the queue. Returns the number of jobs requeued.
+    fn requeue_interrupted(&self) -> Result<u32>;
+
+    /// Named queues that have pending jobs ready to run, in name order.
+    fn runnable_queues(&self) -> Result<Vec<String>>;
+
+    /// Get a job by ID.
+    fn get(&self, job_id: &str) -> Result<Option<StoredJob>>;
+
+    /// Check if a job has been cancelled.
+    fn is_cancelled(&self, job_id: &str) -> Result<bool>;
+
+    /// Get the number of attempts made so far and the job's own retry
+    /// policy (`None` if the job uses the executor's default).
+    fn retry_state(&self, job_id: &str) -> Result<(u32, Option<RetryPolicy>)>;
+
+    /// Mark a processing job as completed with an optional output. Returns
+    /// `false` if the job was no longer processing.
+    fn mark_completed(&self, job_id: &str, output: Option<&str>) -> Result<bool>;
+
+    /// Mark a processing job as failed. Jobs that are no longer processing
+    /// are left unchanged.
+    fn mark_failed(&self, job_id: &str, error: &str) -> Result<()>;
+
+    /// Move a processing job that has used up all of its attempts to the
+    /// dead-letter state.
+    fn mark_dead(&self, job_id: &str, error: &str) -> Result<()>;
+
+    /// Mark a processing job as interrupted by a shutdown. Returns `false`
+    /// if the job was no longer processing.
+    fn mark_interrupted(&self, job_id: &str) -> Result<bool>;
+
+    /// Mark a processing job whose payload couldn't be upcast or
+    /// deserialized as incompatible. Returns `false` if the job was no
+    /// longer processing.
+    fn mark_incompatible(&self, job_id: &str, error: &str) -> Result<bool>;
+
+    /// Mark a processing job as timed out after running longer than
+    /// `timeout`. Returns `false` if the job was no longer processing.
+    fn mark_timed_out(&self, job_id: &str, timeout: Duration) -> Result<bool>;
+
+    /// Put a failed processing job back to pending, eligible again at
+    /// `next_run_at`.
+    fn schedule_retry(&self, job_id: &str, error: &str, next_run_at: &str) -> Result<()>;
+
+    /// Replace a job's payload with `data`, migrated to `payload_version`.
+    fn update_payload(&self, job_id: &str, data: &Value, payload_version: u32) -> Result<()>;
+
+    /// Move blocked dependents of a completed job to pending once all of
+    /// their dependencies have completed. Returns the IDs of the unblocked jobs.
+    fn unblock_dependents(&self, job_id: &str) -> Result<Vec<String>>;
+
+    /// Cancel or fail, by their dependency policy, the blocked jobs that
+    /// depend on a job that ended without completing, directly or
+    /// transitively. Returns `(job_id, new_status)` for every changed job.
+    fn fail_dependents(&self, job_id: &str) -> Result<Vec<(String, String)>>;
+
+    /// Cancel a blocked, pending or processing job. Returns the previous status.
+    fn cancel(&self, job_id: &str) -> Result<String>;
+
+    /// Cancel every blocked, pending or processing job matching `filter`;
+    /// with no statuses in the filter, all three. Returns
+    /// `(id, previous_status)` for each cancelled job.
+    fn cancel_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>>;
+
+    /// Reset a failed, dead, timed-out, cancelled or incompatible job so it
+    /// runs again from scratch, `blocked` if its dependencies haven't all
+    /// completed. Returns the status it had before.
+    fn requeue(&self, job_id: &str) -> Result<String>;
+
+    /// Requeue every job matching `filter` that can be requeued; with no
+    /// statuses in the filter, failed and dead jobs. Returns
+    /// `(id, previous_status)` for each requeued job.
+    fn requeue_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>>;
+
+    /// Update the priority of a job.
+    fn update_priority(&self, job_id: &str, priority: i32) -> Result<()>;
+
+    /// Set the priority of every pending job in `job_ids`. Other jobs are
+    /// skipped. Returns the IDs of the jobs that were updated.
+    fn update_priorities(&self, job_ids: &[String], priority: i32) -> Result<Vec<String>>;
+
+    /// List all jobs as `(id, status, data_json)`, ordered by status, then
+    /// priority, then creation time.
+    fn list(&self) -> Result<Vec<(String, String, String)>>;
+
+    /// Get the jobs matching `filter`, newest first.
+    fn query(&self, filter: &JobFilter) -> Result<Vec<StoredJob>>;
+
+    /// Number of jobs per named queue and status, as `(queue, status, count)`.
+    fn status_counts(&self) -> Result<Vec<(String, String, u32)>>;
+
+    /// Compute queue statistics, with durations, failure rate and
+    /// throughput taken from jobs that finished within the last `window`.
+    fn metrics(&self, window: Duration) -> Result<QueueMetrics>;
+
+    /// Delete finished jobs older than `days`, with their attempts, logs
+    /// and checkpoints. Returns the number deleted.
+    fn prune(&self, days: u32) -> Result<u32>;
+
+    /// Get the attempt history of a job, oldest first.
+    fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>>;
+
+    /// Append a line to a job's log and return the stored entry.
+    fn insert_log(
+        &self,
+        job_id: &str,
+        level: LogLevel,
+        message: &str,
+        fields: &Value,
+    ) -> Result<JobLogEntry>;
+
+    /// Get a job's log, oldest first. With `since`, only entries whose ID
+    /// is greater than `since` are returned.
+    fn logs(&self, job_id: &str, since: Option<i64>) -> Result<Vec<JobLogEntry>>;
+
+    /// Store `checkpoint` for a job, replacing the previous one.
+    fn save_checkpoint(&self, job_id: &str, checkpoint: &Value) -> Result<()>;
+
+    /// Get the last checkpoint saved for a job.
+    fn load_checkpoint(&self, job_id: &str) -> Result<Option<Value>>;
+
+    /// Store the latest progress of a job.
+    fn update_progress(&self, job_id: &str, progress: &JobProgress) -> Result<()>;
+
+    /// Get the last stored progress of a job (`None` if it has reported none).
+    fn progress(&self, job_id: &str) -> Result<Option<JobProgress>>;
+
+    /// Insert a new recurring schedule.
+    fn insert_schedule(&self, schedule: &ScheduleInfo) -> Result<()>;
+
+    /// List all schedules ordered by their next run time.
+    fn list_schedules(&self) -> Result<Vec<ScheduleInfo>>;
+
+    /// Get all active schedules whose next run time is at or before `now`.
+    fn due_schedules(&self, now: &str) -> Result<Vec<ScheduleInfo>>;
+
+    /// Enqueue a job for a due schedule and advance the schedule to
+    /// `next_run_at`. A schedule with no further run time is deleted.
+    fn fire_schedule(
+        &self,
+        schedule: &ScheduleInfo,
+        job_id: &str,
+        next_run_at: Option<&str>,
+    ) -> Result<()>;
+
+    /// Pause or resume a schedule. Returns `false` if it doesn't exist.
+    fn set_schedule_paused(&self, schedule_id: &str, paused: bool) -> Result<bool>;
+
+    /// Delete a schedule. Jobs it already created are kept. Returns
+    /// `false` if it doesn't exist.
+    fn delete_schedule(&self, schedule_id: &str) -> Result<bool>;
+}
+
+/// Statuses a job can be requeued from.
+pub(crate) const REQUEUEABLE_STATUSES: [&str; 5] =
+    ["failed", "dead", "timed_out", "cancelled", "incompatible"];
+
+/// Statuses a job can be cancelled from.
+pub(crate) const CANCELLABLE_STATUSES: [&str; 3] = ["blocked", "pending", "processing"];
+
+/// `filter` limited to the `allowed` statuses, with `defaults` if it names
+/// none. `None` if no allowed status is left.
+pub(crate) fn restrict_statuses(
+    filter: &JobFilter,
+    defaults: &[QueueJobStatus],
+    allowed: &[&str],
+) -> Option<JobFilter> {
+    let mut filter = filter.clone();
+    if filter.statuses.is_empty() {
+        filter.statuses = defaults.to_vec();
+    }
+    filter
+        .statuses
+        .retain(|status| allowed.contains(&status.as_str()));
+    (!filter.statuses.is_empty()).then_some(filter)
+}
+
+/// Settle a unique key conflict with `existing_id` under `job`'s policy,
+/// once replacing the holder is off the table.
+pub(crate) fn resolve_conflict(job: &NewJob, existing_id: String) -> Result<UniqueInsert> {
+    match job.on_conflict {
+        UniqueConflictPolicy::ReturnExisting => Ok(UniqueInsert::Conflict { existing_id }),
+        _ => Err(QueueError::DuplicateJob {
+            key: job.options.unique_key.clone().unwrap_or_default(),
+            existing_id,
+        }
+        .into()),
+    }
+}
+
+/// The payload and options of the job a schedule fires.
+pub(crate) fn scheduled_job(schedule: &ScheduleInfo) -> Result<(Value, JobOptions)> {
+    let data =
+        serde_json::from_str(&schedule.data_json).context("Failed to parse schedule data JSON")?;
+    let options = JobOptions {
+        retry_policy: schedule.retry_policy.clone(),
+        job_type: schedule.job_type.clone(),
+        payload_version: Some(schedule.payload_version),
+        ..JobOptions::default()
+    };
+    Ok((data, options))
+}
+
+/// Build [`QueueMetrics`] from the job count per `(job_type, status)` and
+/// the `(job_type, status, duration_ms)` of every job that finished within
+/// `window`. Untyped jobs use an empty job type.
+pub(crate) fn build_metrics(
+    now: DateTime<Utc>,
+    window: Duration,
+    counts: impl IntoIterator<Item = (String, String, u32)>,
+    finished: impl IntoIterator<Item = (String, String, Option<f64>)>,
+) -> QueueMetrics {
+    let mut job_types: BTreeMap<String, JobTypeMetrics> = BTreeMap::new();
+    for (job_type, status, count) in counts {
+        let metrics = job_types.entry(job_type).or_default();
+        if matches!(status.as_str(), "blocked" | "pending") {
+            metrics.depth += count;
+        }
+        metrics.counts.insert(status, count);
+    }
+
+    let mut durations: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for (job_type, status, duration) in finished {
+        let metrics = job_types.entry(job_type.clone()).or_default();
+        if status == "completed" {
+            metrics.completed += 1;
+            if let Some(duration) = duration {
+                durations
+                    .entry(job_type)
+                    .or_default()
+                    .push(duration.max(0.0));
+            }
+        } else {
+            metrics.failed += 1;
+        }
+    }
+
+    let minutes = window.as_secs_f64() / 60.0;
+    let mut depth = 0;
+    let job_types = job_types
+        .into_iter()
+        .map(|(job_type, mut metrics)| {
+            let finished = metrics.completed + metrics.failed;
+            if finished > 0 {
+                metrics.failure_rate = metrics.failed as f64 / finished as f64;
+            }
+            if minutes > 0.0 {
+                metrics.throughput_per_minute = metrics.completed as f64 / minutes;
+            }
+            if let Some(mut durations) = durations.remove(&job_type) {
+                durations.sort_by(f64::total_cmp);
+                metrics.avg_duration_ms =
+                    Some(durations.iter().sum::<f64>() / durations.len() as f64);
+                metrics.p50_duration_ms = percentile(&durations, 0.50);
+                metrics.p95_duration_ms = percentile(&durations, 0.95);
+            }
+            depth += metrics.depth;
+            metrics.job_type = job_type;
+            metrics
+        })
+        .collect();
+
+    QueueMetrics {
+        generated_at: now.to_rfc3339(),
+        window_secs: window.as_secs(),
+        depth,
+        job_types,
+    }
+}
+
+/// Nearest-rank percentile of sorted `values`.
+fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let rank = (p * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
+
+/// [`JobStore`] backed by the queue's SQLite database.
+#[cfg(feature = "sqlite")]
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Open (or create) the database at `path` and run pending migrations.
+    /// `None` = in-memory database.
+    pub fn open(path: Option<&std::path::Path>) -> Result<Self> {
+        Ok(Self::new(Arc::new(Mutex::new(db::open_database(path)?))))
+    }
+
+    /// Use an already opened connection, e.g. one shared with code that
+    /// calls [`db`] directly.
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    /// The shared connection.
+    pub fn connection(&self) -> &Arc<Mutex<Connection>> {
+        &self.conn
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|e| anyhow::anyhow!("DB mutex poisoned: {}", e))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl JobStore for SqliteStore {
+    fn insert(
+        &self,
+        job_id: &str,
+        priority: i32,
+        data: &Value,
+        options: &JobOptions,
+    ) -> Result<String> {
+        db::insert_job_with_options(&*self.conn()?, job_id, priority, data, options)
+    }
+
+    fn insert_many(&self, jobs: &[NewJob]) -> Result<Vec<UniqueInsert>> {
+        db::in_transaction(&*self.conn()?, |conn| {
+            jobs.iter()
+                .map(|job| db::insert_new_job(conn, job))
+                .collect()
+        })
+    }
+
+    fn claim_next(
+        &self,
+        job_types: &[String],
+        include_untyped: bool,
+    ) -> Result<Option<ClaimedJob>> {
+        db::claim_next(&*self.conn()?, job_types, include_untyped)
+    }
+
+    fn claim_next_aged(
+        &self,
+        queue: &str,
+        job_types: &[String],
+        include_untyped: bool,
+        lease: (&str, Duration),
+        aging: Option<(&PriorityAging, DateTime<Utc>)>,
+    ) -> Result<Option<ClaimedJob>> {
+        db::claim_next_aged(
+            &*self.conn()?,
+            queue,
+            job_types,
+            include_untyped,
+            lease,
+            aging,
+        )
+    }
+
+    fn renew_lease(&self, job_id: &str, worker_id: &str, lease: Duration) -> Result<bool> {
+        db::renew_lease(&*self.conn()?, job_id, worker_id, lease)
+    }
+
+    fn reclaim_expired_leases(&self) -> Result<Vec<String>> {
+        db::reclaim_expired_leases(&*self.conn()?)
+    }
+
+    fn requeue_interrupted(&self) -> Result<u32> {
+        db::requeue_interrupted(&*self.conn()?)
+    }
+
+    fn runnable_queues(&self) -> Result<Vec<String>> {
+        db::runnable_queues(&*self.conn()?)
+    }
+
+    fn get(&self, job_id: &str) -> Result<Option<StoredJob>> {
+        db::get_stored_job(&*self.conn()?, job_id)
+    }
+
+    fn is_cancelled(&self, job_id: &str) -> Result<bool> {
+        db::is_cancelled(&*self.conn()?, job_id)
+    }
+
+    fn retry_state(&self, job_id: &str) -> Result<(u32, Option<RetryPolicy>)> {
+        db::get_retry_state(&*self.conn()?, job_id)
+    }
+
+    fn mark_completed(&self, job_id: &str, output: Option<&str>) -> Result<bool> {
+        db::mark_completed_with_output(&*self.conn()?, job_id, output)
+    }
+
+    fn mark_failed(&self, job_id: &str, error: &str) -> Result<()> {
+        db::mark_failed(&*self.conn()?, job_id, error)
+    }
+
+    fn mark_dead(&self, job_id: &str, error: &str) -> Result<()> {
+        db::mark_dead(&*self.conn()?, job_id, error)
+    }
+
+    fn mark_interrupted(&self, job_id: &str) -> Result<bool> {
+        db::mark_interrupted(&*self.conn()?, job_id)
+    }
+
+    fn mark_incompatible(&self, job_id: &str, error: &str) -> Result<bool> {
+        db::mark_incompatible(&*self.conn()?, job_id, error)
+    }
+
+    fn mark_timed_out(&self, job_id: &str, timeout: Duration) -> Result<bool> {
+        db::mark_timed_out(&*self.conn()?, job_id, timeout)
+    }
+
+    fn schedule_retry(&self, job_id: &str, error: &str, next_run_at: &str) -> Result<()> {
+        db::schedule_retry(&*self.conn()?, job_id, error, next_run_at)
+    }
+
+    fn update_payload(&self, job_id: &str, data: &Value, payload_version: u32) -> Result<()> {
+        db::update_payload(&*self.conn()?, job_id, data, payload_version)
+    }
+
+    fn unblock_dependents(&self, job_id: &str) -> Result<Vec<String>> {
+        db::unblock_dependents(&*self.conn()?, job_id)
+    }
+
+    fn fail_dependents(&self, job_id: &str) -> Result<Vec<(String, String)>> {
+        db::fail_dependents(&*self.conn()?, job_id)
+    }
+
+    fn cancel(&self, job_id: &str) -> Result<String> {
+        db::cancel_job(&*self.conn()?, job_id)
+    }
+
+    fn cancel_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+        db::cancel_jobs(&*self.conn()?, filter)
+    }
+
+    fn requeue(&self, job_id: &str) -> Result<String> {
+        db::requeue_job(&*self.conn()?, job_id)
+    }
+
+    fn requeue_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+        db::requeue_jobs(&*self.conn()?, filter)
+    }
+
+    fn update_priority(&self, job_id: &str, priority: i32) -> Result<()> {
+        db::update_priority(&*self.conn()?, job_id, priority)
+    }
+
+    fn update_priorities(&self, job_ids: &[String], priority: i32) -> Result<Vec<String>> {
+        db::update_priorities(&*self.conn()?, job_ids, priority)
+    }
+
+    fn list(&self) -> Result<Vec<(String, String, String)>> {
+        db::list_all_jobs(&*self.conn()?)
+    }
+
+    fn query(&self, filter: &JobFilter) -> Result<Vec<StoredJob>> {
+        db::query_jobs(&*self.conn()?, filter)
+    }
+
+    fn status_counts(&self) -> Result<Vec<(String, String, u32)>> {
+        db::queue_status_counts(&*self.conn()?)
+    }
+
+    fn metrics(&self, window: Duration) -> Result<QueueMetrics> {
+        db::queue_metrics(&*self.conn()?, window)
+    }
+
+    fn prune(&self, days: u32) -> Result<u32> {
+        db::prune_old_jobs(&*self.conn()?, days)
+    }
+
+    fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>> {
+        db::get_attempts(&*self.conn()?, job_id)
+    }
+
+    fn insert_log(
+        &self,
+        job_id: &str,
+        level: LogLevel,
+        message: &str,
+        fields: &Value,
+    ) -> Result<JobLogEntry> {
+        db::insert_log(&*self.conn()?, job_id, level, message, fields)
+    }
+
+    fn logs(&self, job_id: &str, since: Option<i64>) -> Result<Vec<JobLogEntry>> {
+        db::get_logs(&*self.conn()?, job_id, since)
+    }
+
+    fn save_checkpoint(&self, job_id: &str, checkpoint: &Value) -> Result<()> {
+        db::save_checkpoint(&*self.conn()?, job_id, checkpoint)
+    }
+
+    fn load_checkpoint(&self, job_id: &str) -> Result<Option<Value>> {
+        db::load_checkpoint(&*self.conn()?, job_id)
+    }
+
+    fn update_progress(&self, job_id: &str, progress: &JobProgress) -> Result<()> {
+        db::update_progress(&*self.conn()?, job_id, progress)
+    }
+
+    fn progress(&self, job_id: &str) -> Result<Option<JobProgress>> {
+        db::get_progress(&*self.conn()?, job_id)
+    }
+
+    fn insert_schedule(&self, schedule: &ScheduleInfo) -> Result<()> {
+        db::insert_schedule(&*self.conn()?, schedule)
+    }
+
+    fn list_schedules(&self) -> Result<Vec<ScheduleInfo>> {
+        db::list_schedules(&*self.conn()?)
+    }
+
+    fn due_schedules(&self, now: &str) -> Result<Vec<ScheduleInfo>> {
+        db::get_due_schedules(&*self.conn()?, now)
+    }
+
+    fn fire_schedule(
+        &self,
+        schedule: &ScheduleInfo,
+        job_id: &str,
+        next_run_at: Option<&str>,
+    ) -> Result<()> {
+        db::fire_schedule(&*self.conn()?, schedule, job_id, next_run_at)
+    }
+
+    fn set_schedule_paused(&self, schedule_id: &str, paused: bool) -> Result<bool> {
+        db::set_schedule_paused(&*self.conn()?, schedule_id, paused)
+    }
+
+    fn delete_schedule(&self, schedule_id: &str) -> Result<bool> {
+        db::delete_schedule(&*self.conn()?, schedule_id)
+    }
+}
+
+/// A job as held by [`MemoryStore`]. Never mutated in place: every change
+/// swaps in a new snapshot.
+#[derive(Debug, Clone)]
+struct MemoryJob {
+    /// Insertion order, the FIFO tie-breaker within a priority.
+    seq: u64,
+    job: StoredJob,
+    /// Worker holding the lease on the job while it is processing.
+    worker_id: Option<String>,
+    lease_expires_at: Option<DateTime<Utc>>,
+    /// Part of an [`insert_many()`](JobStore::insert_many) batch that isn't
+    /// all in yet: hidden from claims and lookups.
+    staged: bool,
+}
+
+/// Jobs written so far by an [`insert_many()`](JobStore::insert_many)
+/// batch, to undo if a later one fails.
+#[derive(Default)]
+struct Batch {
+    inserted: Vec<String>,
+    /// Unique keys reserved as `(key, job_id)`.
+    reserved: Vec<(String, String)>,
+    /// Snapshots of the jobs the batch cancelled to take over their key.
+    replaced: Vec<MemoryJob>,
+}
+
+/// Lock-free, in-process [`JobStore`], for tests and apps that don't need
+/// jobs to outlive the process.
+///
+/// Jobs live in a concurrent skip list keyed by ID. Each entry holds an
+/// atomically swapped snapshot, and state changes are compare-and-swap
+/// loops, so claiming and finishing jobs never blocks another thread.
+/// Unique key reservations, the dependency graph, attempts, logs,
+/// checkpoints and schedules live in skip lists of their own.
+///
+/// Changes that span several jobs, like [`cancel_where()`](JobStore::cancel_where),
+/// are made job by job rather than in one transaction. An
+/// [`insert_many()`](JobStore::insert_many) batch stays hidden until all of
+/// it is in, and is taken out again if any job fails.
+#[derive(Default)]
+pub struct MemoryStore {
+    jobs: SkipMap<String, ArcSwap<MemoryJob>>,
+    /// Unique key -> ID of the active job holding it.
+    unique_keys: SkipMap<String, String>,
+    /// `(job_id, dependent_id)` for every dependency, to find a job's dependents.
+    dependents: SkipMap<(String, String), ()>,
+    /// Attempt history, keyed by `(job_id, seq)`.
+    attempts: SkipMap<(String, u64), JobAttempt>,
+    /// Log lines, keyed by `(job_id, log_id)`.
+    logs: SkipMap<(String, i64), JobLogEntry>,
+    checkpoints: SkipMap<String, Value>,
+    schedules: SkipMap<String, ArcSwap<ScheduleInfo>>,
+    next_seq: AtomicU64,
+    next_log_id: AtomicI64,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current snapshots of all jobs, leaving out unfinished batches.
+    fn snapshots(&self) -> impl Iterator<Item = Arc<MemoryJob>> + '_ {
+        self.jobs
+            .iter()
+            .map(|entry| entry.value().load_full())
+            .filter(|snapshot| !snapshot.staged)
+    }
+
+    /// Current status of a job, staged or not.
+    fn status_of(&self, job_id: &str) -> Option<String> {
+        self.jobs
+            .get(job_id)
+            .map(|entry| entry.value().load().job.status.clone())
+    }
+
+    /// Apply `change` to the current snapshot of a job and swap the result
+    /// in, retrying if another thread changed the job in between. `change`
+    /// returns the new job (`None` = leave it unchanged) and a value to pass
+    /// back. A job that leaves the active statuses gives up its unique key.
+    fn update<T>(
+        &self,
+        job_id: &str,
+        mut change: impl FnMut(&MemoryJob) -> Result<(Option<MemoryJob>, T)>,
+    ) -> Result<T> {
+        let entry = self
+            .jobs
+            .get(job_id)
+            .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+        loop {
+            let current = entry.value().load_full();
+            let (next, value) = change(&current)?;
+            let Some(next) = next else {
+                return Ok(value);
+            };
+            let left_active = is_active(&current.job.status) && !is_active(&next.job.status);
+            let previous = entry.value().compare_and_swap(&current, Arc::new(next));
+            if Arc::ptr_eq(&previous, &current) {
+                if let (true, Some(key)) = (left_active, &current.job.unique_key) {
+                    self.release_key(key, job_id);
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Apply `change` to a job if `applies` holds for its current snapshot.
+    /// Returns the snapshot it replaced, or `None` if `applies` didn't hold
+    /// or the job doesn't exist.
+    fn transition(
+        &self,
+        job_id: &str,
+        applies: impl Fn(&MemoryJob) -> bool,
+        change: impl Fn(&mut MemoryJob),
+    ) -> Result<Option<MemoryJob>> {
+        if !self.jobs.contains_key(job_id) {
+            return Ok(None);
+        }
+        self.update(job_id, |current| {
+            if !applies(current) {
+                return Ok((None, None));
+            }
+            let mut next = current.clone();
+            change(&mut next);
+            Ok((Some(next), Some(current.clone())))
+        })
+    }
+
+    /// Reserve `key` for `job_id`. Returns the active job that already
+    /// holds it, if any.
+    fn reserve_key(&self, key: &str, job_id: &str) -> Option<String> {
+        loop {
+            let entry = self
+                .unique_keys
+                .get_or_insert(key.to_string(), job_id.to_string());
+            if entry.value() == job_id {
+                return None;
+            }
+            let holder_active = self
+                .status_of(entry.value())
+                .is_some_and(|status| is_active(&status));
+            if holder_active {
+                return Some(entry.value().clone());
+            }
+            // Left behind by a job that is just finishing
+            entry.remove();
+        }
+    }
+
+    /// Give up `job_id`'s reservation of `key`, if it still holds it.
+    fn release_key(&self, key: &str, job_id: &str) {
+        if let Some(entry) = self.unique_keys.get(key) {
+            if entry.value() == job_id {
+                entry.remove();
+            }
+        }
+    }
+
+    /// IDs of the jobs that depend on `job_id`.
+    fn dependents_of(&self, job_id: &str) -> Vec<String> {
+        self.dependents
+            .range((job_id.to_string(), String::new())..)
+            .take_while(|entry| entry.key().0 == job_id)
+            .map(|entry| entry.key().1.clone())
+            .collect()
+    }
+
+    /// Whether every dependency of `job` that still exists has completed.
+    fn dependencies_completed(&self, job: &StoredJob) -> bool {
+        job.depends_on.iter().all(|parent_id| {
+            self.status_of(parent_id)
+                .is_none_or(|status| status == "completed")
+        })
+    }
+
+    /// Insert one job, `staged` if it belongs to an unfinished batch.
+    fn insert_job(
+        &self,
+        job_id: &str,
+        priority: i32,
+        data: &Value,
+        options: &JobOptions,
+        staged: bool,
+    ) -> Result<String> {
+        if self.jobs.contains_key(job_id) {
+            anyhow::bail!("Job '{}' already exists", job_id);
+        }
+        let mut status = "pending";
+        for parent_id in &options.depends_on {
+            let parent_status = self
+                .status_of(parent_id)
+                .ok_or_else(|| anyhow::anyhow!("Dependency '{}' not found", parent_id))?;
+            if parent_status != "completed" {
+                status = "blocked";
+            }
+        }
+        if let Some(key) = &options.unique_key {
+            if let Some(holder) = self.reserve_key(key, job_id) {
+                anyhow::bail!("Unique key '{}' is already held by job '{}'", key, holder);
+            }
+        }
+
+        // Index the dependencies before the job appears, so a dependency
+        // that finishes in the meantime is seen either by it or below
+        for parent_id in &options.depends_on {
+            self.dependents
+                .insert((parent_id.clone(), job_id.to_string()), ());
+        }
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let job = StoredJob {
+            id: job_id.to_string(),
+            priority,
+            status: status.to_string(),
+            job_type: options.job_type.clone(),
+            data: data.clone(),
+            created_at: Some(Utc::now().to_rfc3339()),
+            started_at: None,
+            completed_at: None,
+            error_message: None,
+            result_json: None,
+            attempts: 0,
+            retry_policy: options.retry_policy.clone(),
+            next_run_at: options.next_run_at.clone(),
+            dependency_policy: options.dependency_policy.as_str().to_string(),
+            depends_on: options.depends_on.clone(),
+            progress: None,
+            timeout: options.timeout,
+            unique_key: options.unique_key.clone(),
+            queue: options
+                .queue
+                .clone()
+                .unwrap_or_else(|| DEFAULT_QUEUE.to_string()),
+            payload_version: options.payload_version.unwrap_or(1),
+        };
+        let entry = self.jobs.get_or_insert(
+            job_id.to_string(),
+            ArcSwap::from_pointee(MemoryJob {
+                seq,
+                job,
+                worker_id: None,
+                lease_expires_at: None,
+                staged,
+            }),
+        );
+        if entry.value().load().seq != seq {
+            anyhow::bail!("Job '{}' already exists", job_id);
+        }
+        if status == "pending" {
+            return Ok(status.to_string());
+        }
+
+        // Catch up with dependencies that finished since they were checked
+        let depends_on = &options.depends_on;
+        let failed = depends_on.iter().find_map(|parent_id| {
+            self.status_of(parent_id)
+                .filter(|status| is_failed(status))
+                .map(|status| (parent_id, status))
+        });
+        match failed {
+            Some((parent_id, parent_status)) => {
+                self.apply_dependency_policy(job_id, parent_id, &parent_status)?;
+            }
+            None => {
+                self.unblock(job_id)?;
+            }
+        }
+        Ok(self.status_of(job_id).unwrap_or_default())
+    }
+
+    /// Add one job of an [`insert_many()`](JobStore::insert_many) batch,
+    /// staged, recording what it did in `batch`.
+    fn stage(&self, job: &NewJob, batch: &mut Batch) -> Result<UniqueInsert> {
+        if self.jobs.contains_key(&job.id) {
+            anyhow::bail!("Job '{}' already exists", job.id);
+        }
+        let mut replaced = None;
+        if let Some(key) = &job.options.unique_key {
+            while let Some(holder) = self.reserve_key(key, &job.id) {
+                if job.on_conflict != UniqueConflictPolicy::ReplacePending {
+                    return resolve_conflict(job, holder);
+                }
+                let cancelled = self.cancel_if(&holder, &["blocked", "pending"])?;
+                let Some(previous) = cancelled else {
+                    return resolve_conflict(job, holder);
+                };
+                batch.replaced.push(previous);
+                replaced = Some(holder);
+            }
+            batch.reserved.push((key.clone(), job.id.clone()));
+        }
+
+        let status = self.insert_job(&job.id, job.priority, &job.data, &job.options, true)?;
+        batch.inserted.push(job.id.clone());
+        Ok(UniqueInsert::Inserted { status, replaced })
+    }
+
+    /// Undo a failed batch: remove its jobs and give replaced jobs back
+    /// their status and key.
+    fn roll_back(&self, batch: Batch) {
+        for job_id in batch.inserted.iter().rev() {
+            if let Some(entry) = self.jobs.remove(job_id) {
+                self.forget(&entry.value().load().job);
+            }
+        }
+        for (key, job_id) in &batch.reserved {
+            self.release_key(key, job_id);
+        }
+        for previous in batch.replaced.into_iter().rev() {
+            let job_id = previous.job.id.clone();
+            let restored = self.update(&job_id, |current| {
+                if current.job.status != "cancelled" {
+                    return Ok((None, false));
+                }
+                Ok((Some(previous.clone()), true))
+            });
+            if let (Ok(true), Some(key)) = (restored, &previous.job.unique_key) {
+                self.reserve_key(key, &job_id);
+            }
+        }
+    }
+
+    /// Drop everything kept about a deleted job besides the job itself.
+    fn forget(&self, job: &StoredJob) {
+        for parent_id in &job.depends_on {
+            self.dependents.remove(&(parent_id.clone(), job.id.clone()));
+        }
+        for dependent_id in self.dependents_of(&job.id) {
+            self.dependents.remove(&(job.id.clone(), dependent_id));
+        }
+        for entry in self
+            .attempts
+            .range((job.id.clone(), 0)..)
+            .take_while(|entry| entry.key().0 == job.id)
+        {
+            entry.remove();
+        }
+        for entry in self
+            .logs
+            .range((job.id.clone(), i64::MIN)..)
+            .take_while(|entry| entry.key().0 == job.id)
+        {
+            entry.remove();
+        }
+        self.checkpoints.remove(&job.id);
+        if let Some(key) = &job.unique_key {
+            self.release_key(key, &job.id);
+        }
+    }
+
+    /// Append the attempt a job was on before `job` was replaced.
+    fn record_attempt(
+        &self,
+        job: &StoredJob,
+        outcome: &str,
+        error: Option<&str>,
+        finished_at: &str,
+    ) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.attempts.insert(
+            (job.id.clone(), seq),
+            JobAttempt {
+                attempt: job.attempts,
+                outcome: outcome.to_string(),
+                error: error.map(str::to_string),
+                started_at: job.started_at.clone(),
+                finished_at: finished_at.to_string(),
+            },
+        );
+    }
+
+    /// Pick and lease the next runnable job; see [`JobStore::claim_next_aged()`].
+    fn claim(
+        &self,
+        queue: Option<&str>,
+        job_types: &[String],
+        include_untyped: bool,
+        lease: Option<(&str, Duration)>,
+        aging: Option<(&PriorityAging, DateTime<Utc>)>,
+    ) -> Result<Option<ClaimedJob>> {
+        loop {
+            let now = Utc::now();
+            let now_str = now.to_rfc3339();
+            let next = self
+                .snapshots()
+                .filter(|snapshot| {
+                    let job = &snapshot.job;
+                    is_runnable(job, &now_str)
+                        && (queue.is_none() || queue == Some(job.queue.as_str()))
+                        && match &job.job_type {
+                            Some(job_type) => job_types.contains(job_type),
+                            None => include_untyped,
+                        }
+                })
+                .min_by_key(|snapshot| (aged_priority(&snapshot.job, aging), snapshot.seq));
+            let Some(next) = next else {
+                return Ok(None);
+            };
+
+            // Another thread may claim or cancel the job first; then look again
+            let claimed = self.transition(
+                &next.job.id,
+                |job| job.job.status == "pending",
+                |job| {
+                    job.job.status = "processing".to_string();
+                    job.job.started_at = Some(now_str.clone());
+                    job.job.attempts += 1;
+                    job.worker_id = lease.map(|(worker_id, _)| worker_id.to_string());
+                    job.lease_expires_at = lease.map(|(_, lease)| lease_expiry(now, lease));
+                },
+            );
+            match claimed {
+                Ok(Some(previous)) => {
+                    let job = previous.job;
+                    return Ok(Some(ClaimedJob {
+                        id: job.id,
+                        job_type: job.job_type,
+                        data: job.data,
+                        timeout: job.timeout,
+                        attempt: job.attempts + 1,
+                        payload_version: job.payload_version,
+                    }));
+                }
+                // Claimed by someone else, or pruned in the meantime
+                Ok(None) | Err(_) => continue,
+            }
+        }
+    }
+
+    /// End the current attempt of a processing job with `error`, setting
+    /// the job to `status` and recording the attempt as `outcome`.
+    fn finish_with_error(
+        &self,
+        job_id: &str,
+        status: &str,
+        outcome: &str,
+        error: &str,
+    ) -> Result<bool> {
+        let now = Utc::now().to_rfc3339();
+        let previous = self.transition(
+            job_id,
+            |job| job.job.status == "processing",
+            |job| {
+                job.job.status = status.to_string();
+                job.job.completed_at = Some(now.clone());
+                job.job.error_message = Some(error.to_string());
+            },
+        )?;
+        let Some(previous) = previous else {
+            return Ok(false);
+        };
+        self.record_attempt(&previous.job, outcome, Some(error), &now);
+        // An interrupted job resumes from its checkpoint on the next start
+        if status != "interrupted" {
+            self.checkpoints.remove(job_id);
+        }
+        Ok(true)
+    }
+
+    /// Cancel a job if its status is one of `from`. Returns the snapshot
+    /// it had before, or `None` if it was in another status.
+    fn cancel_if(&self, job_id: &str, from: &[&str]) -> Result<Option<MemoryJob>> {
+        let now = Utc::now().to_rfc3339();
+        let previous = self.transition(
+            job_id,
+            |job| from.contains(&job.job.status.as_str()),
+            |job| {
+                job.job.status = "cancelled".to_string();
+                job.job.completed_at = Some(now.clone());
+            },
+        )?;
+        if previous.is_some() {
+            self.checkpoints.remove(job_id);
+        }
+        Ok(previous)
+    }
+
+    /// Requeue a job if it can be requeued. Returns its previous status, or
+    /// `None` if it was in a status it can't be requeued from.
+    fn try_requeue(&self, job_id: &str) -> Result<Option<String>> {
+        let key = self
+            .jobs
+            .get(job_id)
+            .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?
+            .value()
+            .load()
+            .job
+            .unique_key
+            .clone();
+        if let Some(key) = &key {
+            if let Some(holder) = self.reserve_key(key, job_id) {
+                anyhow::bail!("Unique key '{}' is already held by job '{}'", key, holder);
+            }
+        }
+
+        let previous = self.update(job_id, |current| {
+            if !REQUEUEABLE_STATUSES.contains(&current.job.status.as_str()) {
+                return Ok((None, None));
+            }
+            let mut next = current.clone();
+            let job = &mut next.job;
+            job.status = if self.dependencies_completed(job) {
+                "pending"
+            } else {
+                "blocked"
+            }
+            .to_string();
+            job.attempts = 0;
+            job.error_message = None;
+            job.next_run_at = None;
+            job.started_at = None;
+            job.completed_at = None;
+            job.result_json = None;
+            job.progress = None;
+            next.worker_id = None;
+            next.lease_expires_at = None;
+            Ok((Some(next), Some(current.job.status.clone())))
+        })?;
+        match previous {
+            Some(previous) => {
+                // A dependency may have completed since it was checked
+                self.unblock(job_id)?;
+                Ok(Some(previous))
+            }
+            None => {
+                if let Some(key) = &key {
+                    self.release_key(key, job_id);
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Move a blocked job to pending if all of its dependencies have
+    /// completed. Returns `true` if it was moved.
+    fn unblock(&self, job_id: &str) -> Result<bool> {
+        let previous = self.transition(
+            job_id,
+            |job| job.job.status == "blocked" && self.dependencies_completed(&job.job),
+            |job| job.job.status = "pending".to_string(),
+        )?;
+        Ok(previous.is_some())
+    }
+
+    /// Cancel or fail a blocked job because `parent_id` ended with
+    /// `parent_status`. Returns the new status, or `None` if the job was no
+    /// longer blocked on it.
+    fn apply_dependency_policy(
+        &self,
+        job_id: &str,
+        parent_id: &str,
+        parent_status: &str,
+    ) -> Result<Option<String>> {
+        let now = Utc::now().to_rfc3339();
+        let error = format!("Dependency '{}' {}", parent_id, parent_status);
+        let previous = self.transition(
+            job_id,
+            |job| {
+                job.job.status == "blocked" && job.job.depends_on.iter().any(|id| id == parent_id)
+            },
+            |job| {
+                job.job.status = dependency_failure_status(&job.job).to_string();
+                job.job.completed_at = Some(now.clone());
+                job.job.error_message = Some(error.clone());
+            },
+        )?;
+        Ok(previous.map(|previous| dependency_failure_status(&previous.job).to_string()))
+    }
+}
+
+/// Whether a job in `status` holds its unique key.
+fn is_active(status: &str) -> bool {
+    matches!(status, "blocked" | "pending" | "processing" | "interrupted")
+}
+
+/// Whether a job in `status` ended without completing, failing its dependents.
+fn is_failed(status: &str) -> bool {
+    matches!(
+        status,
+        "failed" | "dead" | "cancelled" | "timed_out" | "incompatible"
+    )
+}
+
+/// Whether a job in `status` is finished and may be pruned.
+fn is_finished(status: &str) -> bool {
+    status == "completed" || is_failed(status)
+}
+
+/// Whether `job` is pending and due at `now` (RFC 3339).
+fn is_runnable(job: &StoredJob, now: &str) -> bool {
+    job.status == "pending" && job.next_run_at.as_deref().filter(|at| *at > now).is_none()
+}
+
+/// Status a blocked job gets when one of its dependencies fails.
+fn dependency_failure_status(job: &StoredJob) -> &'static str {
+    match DependencyFailurePolicy::parse(&job.dependency_policy) {
+        Some(DependencyFailurePolicy::Fail) => "failed",
+        _ => "cancelled",
+    }
+}
+
+/// The priority `job` is claimed at, after aging.
+fn aged_priority(job: &StoredJob, aging: Option<(&PriorityAging, DateTime<Utc>)>) -> i32 {
+    let Some((aging, aged_at)) = aging else {
+        return job.priority;
+    };
+    match job.next_run_at.as_deref().or(job.created_at.as_deref()) {
+        Some(since) => aging
+            .effective_priority(QueuePriority::from_i32(job.priority), since, aged_at)
+            .as_i32(),
+        None => job.priority,
+    }
+}
+
+fn lease_expiry(now: DateTime<Utc>, lease: Duration) -> DateTime<Utc> {
+    now + chrono::Duration::from_std(lease).unwrap_or_else(|_| chrono::Duration::days(365))
+}
+
+/// Parse a timestamp given as RFC 3339, `YYYY-MM-DD HH:MM:SS` or a bare
+/// date, in UTC.
+fn parse_time(at: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(at)
+        .map(|at| at.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|at| at.and_utc())
+        })
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(at, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|at| at.and_utc())
+        })
+}
+
+/// Whether `job` matches `filter`, ignoring pagination. Unparseable date
+/// bounds match nothing.
+fn matches_filter(job: &StoredJob, filter: &JobFilter) -> bool {
+    let created_at = job.created_at.as_deref().and_then(parse_time);
+    let after = |bound: &str| {
+        created_at
+            .zip(parse_time(bound))
+            .is_some_and(|(at, b)| at >= b)
+    };
+    let before = |bound: &str| {
+        created_at
+            .zip(parse_time(bound))
+            .is_some_and(|(at, b)| at < b)
+    };
+    (filter.statuses.is_empty()
+        || filter
+            .statuses
+            .iter()
+            .any(|status| status.as_str() == job.status))
+        && filter
+            .job_type
+            .as_ref()
+            .is_none_or(|job_type| job.job_type.as_ref() == Some(job_type))
+        && filter
+            .queue
+            .as_ref()
+            .is_none_or(|queue| job.queue == *queue)
+        && filter.created_after.as_deref().is_none_or(after)
+        && filter.created_before.as_deref().is_none_or(before)
+}
+
+/// Sort rank of a status in [`JobStore::list()`].
+fn status_rank(status: &str) -> u8 {
+    match status {
+        "processing" => 0,
+        "pending" => 1,
+        "blocked" => 2,
+        "completed" => 3,
+        "failed" => 4,
+        "cancelled" => 5,
+        _ => 6,
+    }
+}
+
+impl JobStore for MemoryStore {
+    fn insert(
+        &self,
+        job_id: &str,
+        priority: i32,
+        data: &Value,
+        options: &JobOptions,
+    ) -> Result<String> {
+        self.insert_job(job_id, priority, data, options, false)
+    }
+
+    fn insert_many(&self, jobs: &[NewJob]) -> Result<Vec<UniqueInsert>> {
+        let mut batch = Batch::default();
+        let inserted = jobs
+            .iter()
+            .map(|job| self.stage(job, &mut batch))
+            .collect::<Result<Vec<_>>>();
+        if inserted.is_err() {
+            self.roll_back(batch);
+            return inserted;
+        }
+        for job_id in &batch.inserted {
+            self.transition(job_id, |job| job.staged, |job| job.staged = false)?;
+        }
+        inserted
+    }
+
+    fn claim_next(
+        &self,
+        job_types: &[String],
+        include_untyped: bool,
+    ) -> Result<Option<ClaimedJob>> {
+        self.claim(None, job_types, include_untyped, None, None)
+    }
+
+    fn claim_next_aged(
+        &self,
+        queue: &str,
+        job_types: &[String],
+        include_untyped: bool,
+        lease: (&str, Duration),
+        aging: Option<(&PriorityAging, DateTime<Utc>)>,
+    ) -> Result<Option<ClaimedJob>> {
+        self.claim(Some(queue), job_types, include_untyped, Some(lease), aging)
+    }
+
+    fn renew_lease(&self, job_id: &str, worker_id: &str, lease: Duration) -> Result<bool> {
+        let expires_at = lease_expiry(Utc::now(), lease);
+        let renewed = self.transition(
+            job_id,
+            |job| job.job.status == "processing" && job.worker_id.as_deref() == Some(worker_id),
+            |job| job.lease_expires_at = Some(expires_at),
+        )?;
+        Ok(renewed.is_some())
+    }
+
+    fn reclaim_expired_leases(&self) -> Result<Vec<String>> {
+        let now = Utc::now();
+        let now_str = now.to_rfc3339();
+        let expired = |job: &MemoryJob| {
+            job.job.status == "processing" && job.lease_expires_at.is_some_and(|at| at < now)
+        };
+        let mut reclaimed = Vec::new();
+        for snapshot in self.snapshots().filter(|snapshot| expired(snapshot)) {
+            let previous = self.transition(&snapshot.job.id, expired, |job| {
+                job.job.status = "pending".to_string();
+                job.worker_id = None;
+                job.lease_expires_at = None;
+            })?;
+            if let Some(previous) = previous {
+                self.record_attempt(
+                    &previous.job,
+                    "lease_expired",
+                    Some("Worker lease expired"),
+                    &now_str,
+                );
+                reclaimed.push(previous.job.id);
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    fn requeue_interrupted(&self) -> Result<u32> {
+        let interrupted = |job: &MemoryJob| {
+            job.job.status == "interrupted"
+                || (job.job.status == "processing" && job.lease_expires_at.is_none())
+        };
+        let mut count = 0;
+        for snapshot in self.snapshots().filter(|snapshot| interrupted(snapshot)) {
+            let previous = self.transition(&snapshot.job.id, interrupted, |job| {
+                job.job.status = "pending".to_string();
+                job.job.completed_at = None;
+                job.job.error_message = None;
+            })?;
+            if previous.is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn runnable_queues(&self) -> Result<Vec<String>> {
+        let now = Utc::now().to_rfc3339();
+        let queues: BTreeSet<String> = self
+            .snapshots()
+            .filter(|snapshot| is_runnable(&snapshot.job, &now))
+            .map(|snapshot| snapshot.job.queue.clone())
+            .collect();
+        Ok(queues.into_iter().collect())
+    }
+
+    fn get(&self, job_id: &str) -> Result<Option<StoredJob>> {
+        Ok(self
+            .jobs
+            .get(job_id)
+            .map(|entry| entry.value().load_full())
+            .filter(|snapshot| !snapshot.staged)
+            .map(|snapshot| snapshot.job.clone()))
+    }
+
+    fn is_cancelled(&self, job_id: &str) -> Result<bool> {
+        let status = self
+            .status_of(job_id)
+            .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+        Ok(status == "cancelled")
+    }
+
+    fn retry_state(&self, job_id: &str) -> Result<(u32, Option<RetryPolicy>)> {
+        let job = self
+            .get(job_id)?
+            .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+        Ok((job.attempts, job.retry_policy))
+    }
+
+    fn mark_completed(&self, job_id: &str, output: Option<&str>) -> Result<bool> {
+        let now = Utc::now().to_rfc3339();
+        let previous = self.transition(
+            job_id,
+            |job| job.job.status == "processing",
+            |job| {
+                job.job.status = "completed".to_string();
+                job.job.completed_at = Some(now.clone());
+                job.job.error_message = None;
+                job.job.result_json = output.map(str::to_string);
+            },
+        )?;
+        let Some(previous) = previous else {
+            return Ok(false);
+        };
+        self.record_attempt(&previous.job, "completed", None, &now);
+        self.checkpoints.remove(job_id);
+        Ok(true)
+    }
+
+    fn mark_failed(&self, job_id: &str, error: &str) -> Result<()> {
+        self.finish_with_error(job_id, "failed", "failed", error)?;
+        Ok(())
+    }
+
+    fn mark_dead(&self, job_id: &str, error: &str) -> Result<()> {
+        self.finish_with_error(job_id, "dead", "failed", error)?;
+        Ok(())
+    }
+
+    fn mark_interrupted(&self, job_id: &str) -> Result<bool> {
+        self.finish_with_error(
+            job_id,
+            "interrupted",
+            "interrupted",
+            "Interrupted by shutdown",
+        )
+    }
+
+    fn mark_incompatible(&self, job_id: &str, error: &str) -> Result<bool> {
+        self.finish_with_error(job_id, "incompatible", "incompatible", error)
+    }
+
+    fn mark_timed_out(&self, job_id: &str, timeout: Duration) -> Result<bool> {
+        let error = format!("Timed out after {:?}", timeout);
+        self.finish_with_error(job_id, "timed_out", "timed_out", &error)
+    }
+
+    fn schedule_retry(&self, job_id: &str, error: &str, next_run_at: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let previous = self.transition(
+            job_id,
+            |job| job.job.status == "processing",
+            |job| {
+                job.job.status = "pending".to_string();
+                job.job.error_message = Some(error.to_string());
+                job.job.next_run_at = Some(next_run_at.to_string());
+            },
+        )?;
+        if let Some(previous) = previous {
+            self.record_attempt(&previous.job, "failed", Some(error), &now);
+        }
+        Ok(())
+    }
+
+    fn update_payload(&self, job_id: &str, data: &Value, payload_version: u32) -> Result<()> {
+        self.transition(
+            job_id,
+            |_| true,
+            |job| {
+                job.job.data = data.clone();
+                job.job.payload_version = payload_version;
+            },
+        )?;
+        Ok(())
+    }
+
+    fn unblock_dependents(&self, job_id: &str) -> Result<Vec<String>> {
+        let mut unblocked = Vec::new();
+        for dependent_id in self.dependents_of(job_id) {
+            if self.unblock(&dependent_id)? {
+                unblocked.push(dependent_id);
+            }
+        }
+        Ok(unblocked)
+    }
+
+    fn fail_dependents(&self, job_id: &str) -> Result<Vec<(String, String)>> {
+        let mut changed = Vec::new();
+        let mut queue = vec![job_id.to_string()];
+
+        while let Some(parent_id) = queue.pop() {
+            let parent_status = self
+                .status_of(&parent_id)
+                .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", parent_id))?;
+            for dependent_id in self.dependents_of(&parent_id) {
+                let status =
+                    self.apply_dependency_policy(&dependent_id, &parent_id, &parent_status)?;
+                if let Some(status) = status {
+                    changed.push((dependent_id.clone(), status));
+                    queue.push(dependent_id);
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    fn cancel(&self, job_id: &str) -> Result<String> {
+        let status = self
+            .status_of(job_id)
+            .ok_or_else(|| anyhow::anyhow!("Job '{}' not found", job_id))?;
+        match self.cancel_if(job_id, &CANCELLABLE_STATUSES)? {
+            Some(previous) => Ok(previous.job.status),
+            None => anyhow::bail!("Job '{}' is not cancellable (status: {})", job_id, status),
+        }
+    }
+
+    fn cancel_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+        let defaults = [
+            QueueJobStatus::Blocked,
+            QueueJobStatus::Pending,
+            QueueJobStatus::Processing,
+        ];
+        let Some(filter) = restrict_statuses(filter, &defaults, &CANCELLABLE_STATUSES) else {
+            return Ok(Vec::new());
+        };
+        let mut cancelled = Vec::new();
+        for job in self.query(&filter)? {
+            // Skip jobs that finished since the query
+            if let Some(previous) = self.cancel_if(&job.id, &CANCELLABLE_STATUSES)? {
+                cancelled.push((job.id, previous.job.status));
+            }
+        }
+        Ok(cancelled)
+    }
+
+    fn requeue(&self, job_id: &str) -> Result<String> {
+        match self.try_requeue(job_id)? {
+            Some(previous_status) => Ok(previous_status),
+            None => anyhow::bail!(
+                "Job '{}' can't be requeued (status: {})",
+                job_id,
+                self.status_of(job_id).unwrap_or_default()
+            ),
+        }
+    }
+
+    fn requeue_where(&self, filter: &JobFilter) -> Result<Vec<(String, String)>> {
+        let defaults = [QueueJobStatus::Failed, QueueJobStatus::Dead];
+        let Some(filter) = restrict_statuses(filter, &defaults, &REQUEUEABLE_STATUSES) else {
+            return Ok(Vec::new());
+        };
+        let mut requeued = Vec::new();
+        for job in self.query(&filter)? {
+            if let Some(previous_status) = self.try_requeue(&job.id)? {
+                requeued.push((job.id, previous_status));
+            }
+        }
+        Ok(requeued)
+    }
+
+    fn update_priority(&self, job_id: &str, priority: i32) -> Result<()> {
+        self.transition(job_id, |_| true, |job| job.job.priority = priority)?;
+        Ok(())
+    }
+
+    fn update_priorities(&self, job_ids: &[String], priority: i32) -> Result<Vec<String>> {
+        let mut updated = Vec::new();
+        for job_id in job_ids {
+            let previous = self.transition(
+                job_id,
+                |job| job.job.status == "pending" && !job.staged,
+                |job| job.job.priority = priority,
+            )?;
+            if previous.is_some() {
+                updated.push(job_id.clone());
+            }
+        }
+        Ok(updated)
+    }
+
+    fn list(&self) -> Result<Vec<(String, String, String)>> {
+        let mut snapshots: Vec<Arc<MemoryJob>> = self.snapshots().collect();
+        snapshots.sort_by_key(|snapshot| {
+            (
+                status_rank(&snapshot.job.status),
+                snapshot.job.priority,
+                snapshot.seq,
+            )
+        });
+        snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let job = &snapshot.job;
+                Ok((
+                    job.id.clone(),
+                    job.status.clone(),
+                    serde_json::to_string(&job.data)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn query(&self, filter: &JobFilter) -> Result<Vec<StoredJob>> {
+        let mut snapshots: Vec<Arc<MemoryJob>> = self
+            .snapshots()
+            .filter(|snapshot| matches_filter(&snapshot.job, filter))
+            .collect();
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.seq));
+        Ok(snapshots
+            .into_iter()
+            .skip(filter.offset as usize)
+            .take(filter.limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|snapshot| snapshot.job.clone())
+            .collect())
+    }
+
+    fn status_counts(&self) -> Result<Vec<(String, String, u32)>> {
+        let mut counts: BTreeMap<(String, String), u32> = BTreeMap::new();
+        for snapshot in self.snapshots() {
+            let job = &snapshot.job;
+            *counts
+                .entry((job.queue.clone(), job.status.clone()))
+                .or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|((queue, status), count)| (queue, status, count))
+            .collect())
+    }
+
+    fn metrics(&self, window: Duration) -> Result<QueueMetrics> {
+        let now = Utc::now();
+        let since = now
+            - chrono::Duration::from_std(window).unwrap_or_else(|_| chrono::Duration::days(365));
+        let mut counts: BTreeMap<(String, String), u32> = BTreeMap::new();
+        let mut finished = Vec::new();
+        for snapshot in self.snapshots() {
+            let job = &snapshot.job;
+            let job_type = job.job_type.clone().unwrap_or_default();
+            *counts
+                .entry((job_type.clone(), job.status.clone()))
+                .or_default() += 1;
+
+            // Jobs that finished within the window, with the duration of
+            // their last attempt in milliseconds
+            let completed_at = job.completed_at.as_deref().and_then(parse_time);
+            if job.status == "completed" || is_failed(&job.status) && job.status != "cancelled" {
+                if let Some(completed_at) = completed_at.filter(|at| *at >= since) {
+                    let duration = job
+                        .started_at
+                        .as_deref()
+                        .and_then(parse_time)
+                        .and_then(|started_at| (completed_at - started_at).num_microseconds())
+                        .map(|micros| micros as f64 / 1000.0);
+                    finished.push((job_type, job.status.clone(), duration));
+                }
+            }
+        }
+        Ok(build_metrics(
+            now,
+            window,
+            counts
+                .into_iter()
+                .map(|((job_type, status), count)| (job_type, status, count)),
+            finished,
+        ))
+    }
+
+    fn prune(&self, days: u32) -> Result<u32> {
+        let cutoff = (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+        let mut count = 0;
+        for entry in self.jobs.iter() {
+            let snapshot = entry.value().load_full();
+            let job = &snapshot.job;
+            if is_finished(&job.status)
+                && job
+                    .completed_at
+                    .as_deref()
+                    .is_some_and(|at| at < cutoff.as_str())
+                && entry.remove()
+            {
+                self.forget(job);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn attempts(&self, job_id: &str) -> Result<Vec<JobAttempt>> {
+        Ok(self
+            .attempts
+            .range((job_id.to_string(), 0)..)
+            .take_while(|entry| entry.key().0 == job_id)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    fn insert_log(
+        &self,
+        job_id: &str,
+        level: LogLevel,
+        message: &str,
+        fields: &Value,
+    ) -> Result<JobLogEntry> {
+        if !self.jobs.contains_key(job_id) {
+            anyhow::bail!("Job '{}' not found", job_id);
+        }
+        let entry = JobLogEntry {
+            id: self.next_log_id.fetch_add(1, Ordering::Relaxed) + 1,
+            job_id: job_id.to_string(),
+            level,
+            message: message.to_string(),
+            fields: fields.clone(),
+            created_at: Utc::now().to_rfc3339(),
+        };
+        self.logs
+            .insert((job_id.to_string(), entry.id), entry.clone());
+        Ok(entry)
+    }
+
+    fn logs(&self, job_id: &str, since: Option<i64>) -> Result<Vec<JobLogEntry>> {
+        let since = since.unwrap_or(0);
+        Ok(self
+            .logs
+            .range((job_id.to_string(), since.saturating_add(1))..)
+            .take_while(|entry| entry.key().0 == job_id)
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    fn save_checkpoint(&self, job_id: &str, checkpoint: &Value) -> Result<()> {
+        if !self.jobs.contains_key(job_id) {
+            anyhow::bail!("Job '{}' not found", job_id);
+        }
+        self.checkpoints
+            .insert(job_id.to_string(), checkpoint.clone());
+        Ok(())
+    }
+
+    fn load_checkpoint(&self, job_id: &str) -> Result<Option<Value>> {
+        Ok(self
+            .checkpoints
+            .get(job_id)
+            .map(|entry| entry.value().clone()))
+    }
+
+    fn update_progress(&self, job_id: &str, progress: &JobProgress) -> Result<()> {
+        let progress = JobProgress {
+            updated_at: Some(Utc::now().to_rfc3339()),
+            ..progress.clone()
+        };
+        self.transition(
+            job_id,
+            |_| true,
+            |job| job.job.progress = Some(progress.clone()),
+        )?;
+        Ok(())
+    }
+
+    fn progress(&self, job_id: &str) -> Result<Option<JobProgress>> {
+        Ok(self.get(job_id)?.and_then(|job| job.progress))
+    }
+
+    fn insert_schedule(&self, schedule: &ScheduleInfo) -> Result<()> {
+        let schedule = Arc::new(ScheduleInfo {
+            created_at: schedule
+                .created_at
+                .clone()
+                .or_else(|| Some(Utc::now().to_rfc3339())),
+            ..schedule.clone()
+        });
+        let entry = self
+            .schedules
+            .get_or_insert(schedule.id.clone(), ArcSwap::new(Arc::clone(&schedule)));
+        if !Arc::ptr_eq(&entry.value().load(), &schedule) {
+            anyhow::bail!("Schedule '{}' already exists", schedule.id);
+        }
+        Ok(())
+    }
+
+    fn list_schedules(&self) -> Result<Vec<ScheduleInfo>> {
+        let mut schedules: Vec<ScheduleInfo> = self
+            .schedules
+            .iter()
+            .map(|entry| ScheduleInfo::clone(&entry.value().load()))
+            .collect();
+        schedules.sort_by(|a, b| a.next_run_at.cmp(&b.next_run_at));
+        Ok(schedules)
+    }
+
+    fn due_schedules(&self, now: &str) -> Result<Vec<ScheduleInfo>> {
+        let mut schedules = self.list_schedules()?;
+        schedules.retain(|schedule| !schedule.paused && schedule.next_run_at.as_str() <= now);
+        Ok(schedules)
+    }
+
+    fn fire_schedule(
+        &self,
+        schedule: &ScheduleInfo,
+        job_id: &str,
+        next_run_at: Option<&str>,
+    ) -> Result<()> {
+        let (data, options) = scheduled_job(schedule)?;
+        self.insert(job_id, schedule.priority.as_i32(), &data, &options)?;
+
+        let Some(entry) = self.schedules.get(&schedule.id) else {
+            return Ok(());
+        };
+        match next_run_at {
+            Some(next_run_at) => {
+                let now = Utc::now().to_rfc3339();
+                entry.value().rcu(|current| ScheduleInfo {
+                    next_run_at: next_run_at.to_string(),
+                    last_run_at: Some(now.clone()),
+                    ..ScheduleInfo::clone(current)
+                });
+            }
+            None => {
+                entry.remove();
+            }
+        }
+        Ok(())
+    }
+
+    fn set_schedule_paused(&self, schedule_id: &str, paused: bool) -> Result<bool> {
+        let Some(entry) = self.schedules.get(schedule_id) else {
+            return Ok(false);
+        };
+        entry.value().rcu(|current| ScheduleInfo {
+            paused,
+            ..ScheduleInfo::clone(current)
+        });
+        Ok(true)
+    }
+
+    fn delete_schedule(&self, schedule_id: &str) -> Result<bool> {
+        Ok(self.schedules.remove(schedule_id).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exercise the lifecycle every store must support.
+    fn check_store(store: &dyn JobStore) {
+        let json = serde_json::json!({ "n": 1 });
+        let typed = JobOptions {
+            job_type: Some("thumb".to_string()),
+            ..JobOptions::default()
+        };
+        assert_eq!(store.insert("low", 3, &json, &typed).unwrap(), "pending");
+        assert_eq!(store.insert("high", 1, &json, &typed).unwrap(), "pending");
+        store
+            .insert("untyped", 1, &json, &JobOptions::default())
+            .unwrap();
+        store
+            .insert("other", 1, &json, &JobOptions::default())
+            .unwrap();
+        assert!(store.insert("low", 3, &json, &typed).is_err());
+
+        // Highest priority first, and only the requested types
+        let types = vec!["thumb".to_string()];
+        let claimed = store.claim_next(&types, false).unwrap().unwrap();
+        assert_eq!(claimed.id, "high");
+        assert_eq!(claimed.data, json);
+        assert_eq!(claimed.attempt, 1);
+        store.update_priority("low", 1).unwrap();
+        let claimed = store.claim_next(&types, false).unwrap().unwrap();
+        assert_eq!(claimed.id, "low");
+        assert!(store.claim_next(&types, false).unwrap().is_none());
+        assert_eq!(
+            store.claim_next(&types, true).unwrap().unwrap().id,
+            "untyped"
+        );
+
+        assert!(store.mark_completed("high", Some("\"done\"")).unwrap());
+        assert!(!store.mark_completed("high", None).unwrap());
+        store.mark_failed("low", "boom").unwrap();
+        assert_eq!(store.cancel("other").unwrap(), "pending");
+        assert!(store.cancel("other").is_err());
+        assert!(store.cancel("missing").is_err());
+
+        let high = store.get("high").unwrap().unwrap();
+        assert_eq!(high.status, "completed");
+        assert_eq!(high.attempts, 1);
+        assert_eq!(high.result_json.as_deref(), Some("\"done\""));
+        assert_eq!(high.queue, DEFAULT_QUEUE);
+        let low = store.get("low").unwrap().unwrap();
+        assert_eq!(low.error_message.as_deref(), Some("boom"));
+        assert!(store.get("missing").unwrap().is_none());
+
+        let statuses: Vec<(String, String)> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(id, status, _)| (id, status))
+            .collect();
+        let expected = [
+            ("untyped", "processing"),
+            ("high", "completed"),
+            ("low", "failed"),
+            ("other", "cancelled"),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(id, status)| (id.to_string(), status.to_string()))
+            .collect();
+        assert_eq!(statuses, expected);
+
+        assert_eq!(store.prune(1).unwrap(), 0);
+        assert_eq!(store.prune(0).unwrap(), 3);
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    /// Blocked dependents are released when their dependency completes and
+    /// cancelled or failed, transitively, when it doesn't.
+    fn check_dependencies(store: &dyn JobStore) {
+        let json = serde_json::json!({});
+        let after = |parents: &[&str], policy| JobOptions {
+            depends_on: parents.iter().map(|id| id.to_string()).collect(),
+            dependency_policy: policy,
+            ..JobOptions::default()
+        };
+        let cancel = DependencyFailurePolicy::Cancel;
+        let fail = DependencyFailurePolicy::Fail;
+
+        store.insert("a", 2, &json, &JobOptions::default()).unwrap();
+        assert_eq!(
+            store.insert("b", 2, &json, &after(&["a"], cancel)).unwrap(),
+            "blocked"
+        );
+        assert!(store
+            .insert("c", 2, &json, &after(&["nope"], cancel))
+            .is_err());
+        assert!(store.get("c").unwrap().is_none());
+
+        // Blocked jobs are never claimed
+        assert_eq!(store.claim_next(&[], true).unwrap().unwrap().id, "a");
+        assert!(store.claim_next(&[], true).unwrap().is_none());
+        assert!(store.mark_completed("a", None).unwrap());
+        assert_eq!(
+            store.unblock_dependents("a").unwrap(),
+            vec!["b".to_string()]
+        );
+        assert_eq!(store.get("b").unwrap().unwrap().status, "pending");
+        assert_eq!(store.claim_next(&[], true).unwrap().unwrap().id, "b");
+
+        store.insert("p", 2, &json, &JobOptions::default()).unwrap();
+        store.insert("q", 2, &json, &after(&["p"], fail)).unwrap();
+        store.insert("r", 2, &json, &after(&["q"], cancel)).unwrap();
+        assert_eq!(store.claim_next(&[], true).unwrap().unwrap().id, "p");
+        store.mark_failed("p", "boom").unwrap();
+        let changed = store.fail_dependents("p").unwrap();
+        let expected = vec![
+            ("q".to_string(), "failed".to_string()),
+            ("r".to_string(), "cancelled".to_string()),
+        ];
+        assert_eq!(changed, expected);
+        assert_eq!(
+            store.get("q").unwrap().unwrap().error_message.as_deref(),
+            Some("Dependency 'p' failed")
+        );
+
+        // A dependency that has already failed settles the job at once
+        assert_eq!(
+            store.insert("s", 2, &json, &after(&["p"], cancel)).unwrap(),
+            "cancelled"
+        );
+
+        // Requeued, the failed job goes back to blocked behind its dependency
+        assert_eq!(store.requeue("q").unwrap(), "failed");
+        assert_eq!(store.get("q").unwrap().unwrap().status, "blocked");
+    }
+
+    /// Named queues, leases and reclaiming, and priority aging.
+    fn check_leases(store: &dyn JobStore) {
+        let json = serde_json::json!({});
+        let in_queue = |queue: &str, next_run_at: Option<String>| JobOptions {
+            queue: Some(queue.to_string()),
+            next_run_at,
+            ..JobOptions::default()
+        };
+        let minute = Duration::from_secs(60);
+
+        store
+            .insert("video", 2, &json, &in_queue("video", None))
+            .unwrap();
+        store
+            .insert("mail", 2, &json, &in_queue("mail", None))
+            .unwrap();
+        let later = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        store
+            .insert("later", 2, &json, &in_queue("later", Some(later)))
+            .unwrap();
+        assert_eq!(store.runnable_queues().unwrap(), vec!["mail", "video"]);
+        assert!(store
+            .claim_next_aged("later", &[], true, ("w1", minute), None)
+            .unwrap()
+            .is_none());
+
+        let claimed = store
+            .claim_next_aged("video", &[], true, ("w1", minute), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(claimed.id, "video");
+        assert_eq!(store.runnable_queues().unwrap(), vec!["mail"]);
+        assert!(!store.renew_lease("video", "w2", minute).unwrap());
+        assert!(store.renew_lease("video", "w1", minute).unwrap());
+        assert!(store.reclaim_expired_leases().unwrap().is_empty());
+
+        // A lease that runs out puts the job back, recording the lost attempt
+        assert!(store.renew_lease("video", "w1", Duration::ZERO).unwrap());
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(store.reclaim_expired_leases().unwrap(), vec!["video"]);
+        let video = store.get("video").unwrap().unwrap();
+        assert_eq!(video.status, "pending");
+        let attempts = store.attempts("video").unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].outcome, "lease_expired");
+        assert!(!store.renew_lease("video", "w1", minute).unwrap());
+
+        // Claiming without a lease, then a restart, requeues the job
+        let claimed = store.claim_next(&[], true).unwrap().unwrap();
+        assert_eq!(store.requeue_interrupted().unwrap(), 1);
+        assert_eq!(store.get(&claimed.id).unwrap().unwrap().status, "pending");
+
+        // Aging lifts a long-waiting low-priority job above a fresh one
+        let waited = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        store
+            .insert("old", 3, &json, &in_queue("aging", Some(waited)))
+            .unwrap();
+        store
+            .insert("new", 2, &json, &in_queue("aging", None))
+            .unwrap();
+        let aging = PriorityAging::new(Duration::from_secs(600));
+        let claim = |aging| {
+            store
+                .claim_next_aged("aging", &[], true, ("w1", minute), aging)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+        assert_eq!(claim(Some((&aging, Utc::now()))), "old");
+        assert_eq!(claim(None), "new");
+    }
+
+    /// Unique keys under each conflict policy, and all-or-none batches.
+    fn check_unique_keys(store: &dyn JobStore) {
+        let new_job = |id: &str, on_conflict| NewJob {
+            id: id.to_string(),
+            priority: 2,
+            data: serde_json::json!({ "id": id }),
+            options: JobOptions {
+                unique_key: Some("sync".to_string()),
+                ..JobOptions::default()
+            },
+            on_conflict,
+        };
+        let plain = |id: &str| NewJob {
+            options: JobOptions::default(),
+            ..new_job(id, UniqueConflictPolicy::Reject)
+        };
+        let status = |id: &str| store.get(id).unwrap().map(|job| job.status);
+
+        let inserted = store
+            .insert_many(&[
+                new_job("a", UniqueConflictPolicy::Reject),
+                new_job("b", UniqueConflictPolicy::ReturnExisting),
+            ])
+            .unwrap();
+        assert_eq!(
+            inserted,
+            vec![
+                UniqueInsert::Inserted {
+                    status: "pending".to_string(),
+                    replaced: None,
+                },
+                UniqueInsert::Conflict {
+                    existing_id: "a".to_string(),
+                },
+            ]
+        );
+
+        // One rejected job undoes the whole batch
+        let err = store
+            .insert_many(&[plain("c"), new_job("d", UniqueConflictPolicy::Reject)])
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<QueueError>(),
+            Some(QueueError::DuplicateJob { existing_id, .. }) if existing_id == "a"
+        ));
+        assert_eq!(status("c"), None);
+        assert_eq!(status("d"), None);
+
+        let inserted = store
+            .insert_many(&[new_job("e", UniqueConflictPolicy::ReplacePending)])
+            .unwrap();
+        assert_eq!(
+            inserted,
+            vec![UniqueInsert::Inserted {
+                status: "pending".to_string(),
+                replaced: Some("a".to_string()),
+            }]
+        );
+        assert_eq!(status("a").as_deref(), Some("cancelled"));
+
+        // A failed batch gives a replaced job back its status and key
+        assert!(store
+            .insert_many(&[
+                new_job("f", UniqueConflictPolicy::ReplacePending),
+                plain("f"),
+            ])
+            .is_err());
+        assert_eq!(status("e").as_deref(), Some("pending"));
+        assert_eq!(status("f"), None);
+        assert!(store.requeue("a").is_err());
+
+        // A running job is never replaced
+        assert_eq!(store.claim_next(&[], true).unwrap().unwrap().id, "e");
+        assert!(store
+            .insert_many(&[new_job("g", UniqueConflictPolicy::ReplacePending)])
+            .is_err());
+
+        // Once the holder finishes, the key is free again
+        assert!(store.mark_completed("e", None).unwrap());
+        store
+            .insert_many(&[new_job("h", UniqueConflictPolicy::Reject)])
+            .unwrap();
+        assert_eq!(status("h").as_deref(), Some("pending"));
+    }
+
+    /// Logs, checkpoints, progress, statistics and schedules.
+    fn check_records(store: &dyn JobStore) {
+        let json = serde_json::json!({});
+        store.insert("a", 2, &json, &JobOptions::default()).unwrap();
+        let first = store
+            .insert_log("a", LogLevel::Info, "one", &Value::Null)
+            .unwrap();
+        store.insert_log("a", LogLevel::Warn, "two", &json).unwrap();
+        assert_eq!(store.logs("a", None).unwrap().len(), 2);
+        let since: Vec<String> = store
+            .logs("a", Some(first.id))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(since, vec!["two"]);
+
+        let progress = JobProgress {
+            current: 1,
+            total: 4,
+            stage: Some("encoding".to_string()),
+            message: None,
+            updated_at: None,
+        };
+        store.update_progress("a", &progress).unwrap();
+        assert_eq!(store.progress("a").unwrap().unwrap().current, 1);
+        store.save_checkpoint("a", &serde_json::json!(3)).unwrap();
+        assert_eq!(
+            store.load_checkpoint("a").unwrap(),
+            Some(serde_json::json!(3))
+        );
+
+        store.claim_next(&[], true).unwrap().unwrap();
+        assert!(store.mark_completed("a", Some("1")).unwrap());
+        assert_eq!(store.load_checkpoint("a").unwrap(), None);
+        let metrics = store.metrics(Duration::from_secs(60)).unwrap();
+        assert_eq!(metrics.job_types.len(), 1);
+        assert_eq!(metrics.job_types[0].completed, 1);
+        assert!(metrics.job_types[0].p50_duration_ms.is_some());
+        assert_eq!(
+            store.status_counts().unwrap(),
+            vec![(DEFAULT_QUEUE.to_string(), "completed".to_string(), 1)]
+        );
+
+        let now = Utc::now().to_rfc3339();
+        let schedule = ScheduleInfo {
+            id: "nightly".to_string(),
+            spec: crate::schedule::ScheduleSpec::Interval(Duration::from_secs(60)),
+            job_type: None,
+            priority: QueuePriority::Normal,
+            data_json: "{}".to_string(),
+            retry_policy: None,
+            paused: false,
+            next_run_at: now.clone(),
+            last_run_at: None,
+            created_at: None,
+            payload_version: 1,
+        };
+        store.insert_schedule(&schedule).unwrap();
+        assert!(store.insert_schedule(&schedule).is_err());
+        assert_eq!(store.due_schedules(&now).unwrap().len(), 1);
+        assert!(store.set_schedule_paused("nightly", true).unwrap());
+        assert!(store.due_schedules(&now).unwrap().is_empty());
+        store
+            .fire_schedule(&schedule, "fired", Some("2999-01-01T00:00:00+00:00"))
+            .unwrap();
+        assert_eq!(store.get("fired").unwrap().unwrap().status, "pending");
+        let schedules = store.list_schedules().unwrap();
+        assert!(schedules[0].last_run_at.is_some());
+        store.fire_schedule(&schedule, "last", None).unwrap();
+        assert!(store.list_schedules().unwrap().is_empty());
+        assert!(!store.delete_schedule("nightly").unwrap());
+    }
+
+    /// Run every check against a fresh store from `new_store`.
+    fn check_all(new_store: impl Fn() -> Box<dyn JobStore>) {
+        check_store(&*new_store());
+        check_dependencies(&*new_store());
+        check_leases(&*new_store());
+        check_unique_keys(&*new_store());
+        check_records(&*new_store());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_all(|| Box::new(MemoryStore::new()));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        check_all(|| Box::new(SqliteStore::open(None).unwrap()));
+    }
+
+    #[test]
+    fn test_memory_store_claims_each_job_once() {
+        let store = Arc::new(MemoryStore::new());
+        let json = serde_json::json!({});
+        for i in 0..100 {
+            store
+                .insert(&format!("job-{}", i), 2, &json, &JobOptions::default())
+                .unwrap();
+        }
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    let mut claimed = Vec::new();
+                    while let Some(job) = store.claim_next(&[], true).unwrap() {
+                        claimed.push(job.id);
+                    }
+                    claimed
+                })
+            })
+            .collect();
+        let mut claimed: Vec<String> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        claimed.sort();
+        claimed.dedup();
+        assert_eq!(claimed.len(), 100);
+    }
+}
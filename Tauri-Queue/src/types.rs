@@ -1,4 +1,5 @@
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
 
 /// Priority levels for queue jobs.
 ///
@@ -29,39 +30,69 @@ impl QueuePriority {
     }
 }
 
-/// Job status lifecycle: Pending -> Processing -> Completed/Failed/Cancelled
+/// Job status lifecycle: Pending -> Processing -> Completed/Failed/Dead/Cancelled/TimedOut
+///
+/// Jobs with unfinished dependencies start out Blocked and become Pending
+/// once every job they depend on has completed.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QueueJobStatus {
+    Blocked,
     Pending,
     Processing,
     Completed,
     Failed,
     Cancelled,
+    /// The job ran longer than its timeout and was aborted.
+    TimedOut,
+    /// The job failed on every attempt its retry policy allowed. Stays
+    /// until it is requeued or pruned.
+    Dead,
+    /// The job was running when the queue shut down with
+    /// [`ShutdownMode::Abort`]. It is requeued on the next start.
+    Interrupted,
+    /// The job's stored payload couldn't be upcast or deserialized into its
+    /// handler type. Stays until it is requeued (e.g. after registering a
+    /// missing upcaster) or pruned.
+    Incompatible,
 }
 
 impl QueueJobStatus {
     pub fn as_str(&self) -> &str {
         match self {
+            QueueJobStatus::Blocked => "blocked",
             QueueJobStatus::Pending => "pending",
             QueueJobStatus::Processing => "processing",
             QueueJobStatus::Completed => "completed",
             QueueJobStatus::Failed => "failed",
             QueueJobStatus::Cancelled => "cancelled",
+            QueueJobStatus::TimedOut => "timed_out",
+            QueueJobStatus::Dead => "dead",
+            QueueJobStatus::Interrupted => "interrupted",
+            QueueJobStatus::Incompatible => "incompatible",
         }
     }
 
     pub fn parse(s: &str) -> Option<Self> {
         match s {
+            "blocked" => Some(QueueJobStatus::Blocked),
             "pending" => Some(QueueJobStatus::Pending),
             "processing" => Some(QueueJobStatus::Processing),
             "completed" => Some(QueueJobStatus::Completed),
             "failed" => Some(QueueJobStatus::Failed),
             "cancelled" => Some(QueueJobStatus::Cancelled),
+            "timed_out" => Some(QueueJobStatus::TimedOut),
+            "dead" => Some(QueueJobStatus::Dead),
+            "interrupted" => Some(QueueJobStatus::Interrupted),
+            "incompatible" => Some(QueueJobStatus::Incompatible),
             _ => None,
         }
     }
 }
 
+/// Name of the queue jobs run in unless they pick another with
+/// [`QueueJob::on_queue()`].
+pub const DEFAULT_QUEUE: &str = "default";
+
 /// A generic queue job carrying a custom data payload.
 ///
 /// The data field is stored as JSON in SQLite and deserialized back when the
@@ -75,12 +106,39 @@ where
 {
     pub id: String,
     pub priority: QueuePriority,
+    /// Priority the job is scheduled with after [`PriorityAging`]. Equal to
+    /// `priority` unless aging is configured and the job is pending.
+    pub effective_priority: QueuePriority,
     pub status: QueueJobStatus,
     pub data: T,
     pub created_at: Option<String>,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
     pub error_message: Option<String>,
+    /// Output of the successful run, from [`JobResult::output`].
+    pub output: Option<String>,
+    /// Last progress reported by the handler.
+    pub progress: Option<JobProgress>,
+    /// Retry policy for this job. `None` = use the queue's default policy.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Number of attempts made so far.
+    pub attempts: u32,
+    /// Earliest time the job may next be picked up (RFC 3339).
+    pub next_run_at: Option<String>,
+    /// IDs of jobs that must complete before this one may run.
+    pub depends_on: Vec<String>,
+    /// What happens to this job if one of its dependencies fails or is cancelled.
+    pub dependency_policy: DependencyFailurePolicy,
+    /// Maximum run time of one attempt. `None` = no limit.
+    pub timeout: Option<Duration>,
+    /// Deduplication key. At most one blocked, pending or processing job
+    /// may hold a given key.
+    pub unique_key: Option<String>,
+    /// What [`QueueManager::add()`](crate::QueueManager::add) does when another
+    /// active job already holds `unique_key`.
+    pub on_conflict: UniqueConflictPolicy,
+    /// Named queue the job runs in ([`DEFAULT_QUEUE`] unless set).
+    pub queue: String,
 }
 
 impl<T> QueueJob<T>
@@ -92,18 +150,31 @@ where
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             priority: QueuePriority::Normal,
+            effective_priority: QueuePriority::Normal,
             status: QueueJobStatus::Pending,
             data,
             created_at: None,
             started_at: None,
             completed_at: None,
             error_message: None,
+            output: None,
+            progress: None,
+            retry_policy: None,
+            attempts: 0,
+            next_run_at: None,
+            depends_on: Vec::new(),
+            dependency_policy: DependencyFailurePolicy::default(),
+            timeout: None,
+            unique_key: None,
+            on_conflict: UniqueConflictPolicy::default(),
+            queue: DEFAULT_QUEUE.to_string(),
         }
     }
 
     /// Set the priority for this job (builder pattern).
     pub fn with_priority(mut self, priority: QueuePriority) -> Self {
         self.priority = priority;
+        self.effective_priority = priority;
         self
     }
 
@@ -112,6 +183,494 @@ where
         self.id = id;
         self
     }
+
+    /// Delay this job until `at` (builder pattern).
+    pub fn run_at(mut self, at: chrono::DateTime<chrono::Utc>) -> Self {
+        self.next_run_at = Some(at.to_rfc3339());
+        self
+    }
+
+    /// Set the retry policy for this job (builder pattern).
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Only run this job after all of `job_ids` have completed (builder pattern).
+    pub fn depends_on(mut self, job_ids: Vec<String>) -> Self {
+        self.depends_on = job_ids;
+        self
+    }
+
+    /// Set what happens if a dependency fails or is cancelled (builder pattern).
+    pub fn with_dependency_policy(mut self, policy: DependencyFailurePolicy) -> Self {
+        self.dependency_policy = policy;
+        self
+    }
+
+    /// Abort the job and mark it `timed_out` if one attempt runs longer
+    /// than `timeout` (builder pattern).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Deduplicate this job by `key` while it is queued or running (builder pattern).
+    ///
+    /// Once the job has finished, the key is free again.
+    pub fn with_unique_key(mut self, key: impl Into<String>) -> Self {
+        self.unique_key = Some(key.into());
+        self
+    }
+
+    /// Set what happens if another active job holds the same unique key
+    /// (builder pattern).
+    pub fn on_conflict(mut self, policy: UniqueConflictPolicy) -> Self {
+        self.on_conflict = policy;
+        self
+    }
+
+    /// Run this job in the named queue `queue` (builder pattern).
+    pub fn on_queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = queue.into();
+        self
+    }
+}
+
+/// Progress reported by a running job, as stored in the database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub current: u32,
+    pub total: u32,
+    /// Short label for the current phase, e.g. "encoding".
+    pub stage: Option<String>,
+    /// Free-text detail, e.g. "Frame 120 of 2400".
+    pub message: Option<String>,
+    /// When the progress was written (RFC 3339).
+    pub updated_at: Option<String>,
+}
+
+impl JobProgress {
+    /// Completed fraction in `0.0..=1.0` (0 when `total` is 0).
+    pub fn fraction(&self) -> f64 {
+        if self.total > 0 {
+            (self.current as f64 / self.total as f64).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// One finished attempt of a job, from its attempt history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobAttempt {
+    /// Attempt number, starting at 1 (restarts after a requeue).
+    pub attempt: u32,
+    /// How the attempt ended: `completed`, `failed`, `timed_out`,
+    /// `lease_expired` (its worker stopped renewing the lease),
+    /// `interrupted` (the app shut down mid-run) or `incompatible` (the
+    /// payload could not be upcast to the handler's version).
+    pub outcome: String,
+    pub error: Option<String>,
+    pub started_at: Option<String>,
+    pub finished_at: String,
+}
+
+/// Severity of a [`JobLogEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// One line of a job's log, written with
+/// [`JobContext::log()`](crate::JobContext::log).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLogEntry {
+    /// Increasing ID, usable as the `since` cursor of
+    /// [`QueueManager::logs()`](crate::QueueManager::logs).
+    pub id: i64,
+    pub job_id: String,
+    pub level: LogLevel,
+    pub message: String,
+    /// Structured data attached to the line (`null` if none).
+    pub fields: serde_json::Value,
+    pub created_at: String,
+}
+
+/// Selects stored jobs for [`QueueManager::query()`](crate::QueueManager::query).
+///
+/// Empty fields match everything. Results are ordered newest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JobFilter {
+    /// Only jobs in one of these statuses. Empty = any status.
+    pub statuses: Vec<QueueJobStatus>,
+    /// Only jobs of this type. `None` = the handler's own type.
+    pub job_type: Option<String>,
+    /// Only jobs in this named queue. `None` = any queue.
+    pub queue: Option<String>,
+    /// Only jobs created at or after this time (RFC 3339).
+    pub created_after: Option<String>,
+    /// Only jobs created before this time (RFC 3339).
+    pub created_before: Option<String>,
+    /// Maximum number of jobs to return. `None` = no limit.
+    pub limit: Option<u32>,
+    /// Number of matching jobs to skip.
+    pub offset: u32,
+}
+
+impl JobFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also match jobs in `status` (builder pattern).
+    pub fn with_status(mut self, status: QueueJobStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    /// Only match jobs of `job_type` (builder pattern).
+    pub fn with_job_type(mut self, job_type: impl Into<String>) -> Self {
+        self.job_type = Some(job_type.into());
+        self
+    }
+
+    /// Only match jobs in the named queue `queue` (builder pattern).
+    pub fn with_queue(mut self, queue: impl Into<String>) -> Self {
+        self.queue = Some(queue.into());
+        self
+    }
+
+    /// Only match jobs created at or after `at` (builder pattern).
+    pub fn created_after(mut self, at: chrono::DateTime<chrono::Utc>) -> Self {
+        self.created_after = Some(at.to_rfc3339());
+        self
+    }
+
+    /// Only match jobs created before `at` (builder pattern).
+    pub fn created_before(mut self, at: chrono::DateTime<chrono::Utc>) -> Self {
+        self.created_before = Some(at.to_rfc3339());
+        self
+    }
+
+    /// Return one page of `limit` jobs, skipping the first `offset` (builder pattern).
+    pub fn paginate(mut self, limit: u32, offset: u32) -> Self {
+        self.limit = Some(limit);
+        self.offset = offset;
+        self
+    }
+}
+
+/// Job counts and pause state of one named queue, from
+/// [`QueueManager::queue_stats()`](crate::QueueManager::queue_stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    pub queue: String,
+    pub paused: bool,
+    pub blocked: u32,
+    pub pending: u32,
+    pub processing: u32,
+    pub completed: u32,
+    pub failed: u32,
+    pub dead: u32,
+    pub cancelled: u32,
+    pub timed_out: u32,
+    pub interrupted: u32,
+    pub incompatible: u32,
+}
+
+impl QueueStats {
+    /// Add `count` jobs in `status` to the matching counter.
+    pub(crate) fn add(&mut self, status: &str, count: u32) {
+        let counter = match QueueJobStatus::parse(status) {
+            Some(QueueJobStatus::Blocked) => &mut self.blocked,
+            Some(QueueJobStatus::Pending) => &mut self.pending,
+            Some(QueueJobStatus::Processing) => &mut self.processing,
+            Some(QueueJobStatus::Completed) => &mut self.completed,
+            Some(QueueJobStatus::Failed) => &mut self.failed,
+            Some(QueueJobStatus::Dead) => &mut self.dead,
+            Some(QueueJobStatus::Cancelled) => &mut self.cancelled,
+            Some(QueueJobStatus::TimedOut) => &mut self.timed_out,
+            Some(QueueJobStatus::Interrupted) => &mut self.interrupted,
+            Some(QueueJobStatus::Incompatible) => &mut self.incompatible,
+            None => return,
+        };
+        *counter += count;
+    }
+}
+
+/// Queue-wide statistics from [`QueueManager::stats()`](crate::QueueManager::stats),
+/// also emitted periodically as `queue:stats`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueMetrics {
+    /// When the statistics were computed (RFC 3339).
+    pub generated_at: String,
+    /// Length of the window durations, failure rate and throughput cover.
+    pub window_secs: u64,
+    /// Blocked and pending jobs across all job types.
+    pub depth: u32,
+    /// One entry per job type, in name order. Rows without a job type are
+    /// listed under `""`.
+    pub job_types: Vec<JobTypeMetrics>,
+}
+
+/// Statistics of one job type.
+///
+/// `counts` and `depth` describe the queue right now. Durations, failure
+/// rate and throughput only cover jobs that finished within the window.
+/// A duration runs from the start of a job's last attempt to its end.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTypeMetrics {
+    pub job_type: String,
+    /// Number of jobs per status, keyed by [`QueueJobStatus::as_str()`].
+    pub counts: std::collections::BTreeMap<String, u32>,
+    /// Blocked and pending jobs of this type.
+    pub depth: u32,
+    /// Jobs that completed within the window.
+    pub completed: u32,
+    /// Jobs that failed, were dead-lettered or timed out within the window.
+    pub failed: u32,
+    /// `failed / (completed + failed)`, or 0 if nothing finished.
+    pub failure_rate: f64,
+    /// Completed jobs per minute over the window.
+    pub throughput_per_minute: f64,
+    pub avg_duration_ms: Option<f64>,
+    pub p50_duration_ms: Option<f64>,
+    pub p95_duration_ms: Option<f64>,
+}
+
+/// What happens to a blocked job when a job it depends on fails or is
+/// cancelled. Either way the outcome propagates to the job's own dependents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DependencyFailurePolicy {
+    /// Cancel the dependent job.
+    #[default]
+    Cancel,
+    /// Mark the dependent job as failed.
+    Fail,
+}
+
+impl DependencyFailurePolicy {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DependencyFailurePolicy::Cancel => "cancel",
+            DependencyFailurePolicy::Fail => "fail",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cancel" => Some(DependencyFailurePolicy::Cancel),
+            "fail" => Some(DependencyFailurePolicy::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// What happens when a job is added while another blocked, pending or
+/// processing job holds the same unique key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UniqueConflictPolicy {
+    /// Fail with [`QueueError::DuplicateJob`](crate::QueueError::DuplicateJob).
+    #[default]
+    Reject,
+    /// Don't add the job; return the ID of the existing one instead.
+    ReturnExisting,
+    /// Cancel the existing job and add this one in its place, as long as the
+    /// existing job hasn't started yet. A processing job is never replaced;
+    /// the add is rejected instead.
+    ReplacePending,
+}
+
+/// How [`QueueManager::shutdown()`](crate::QueueManager::shutdown) stops the
+/// executor. Either way no new jobs are started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownMode {
+    /// Let running jobs finish.
+    Drain,
+    /// Cancel running jobs and mark them `interrupted`, so they run again
+    /// on the next start.
+    Abort,
+}
+
+/// Retry behaviour for failed jobs.
+///
+/// The delay before retry `n` is `base_delay * backoff_factor^(n - 1)`,
+/// capped at `max_delay`, then spread by up to `jitter` (a fraction of the
+/// delay) in either direction so that jobs failing together don't retry
+/// in lockstep.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first run (1 = never retry).
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Multiplier applied to the delay after each failed attempt.
+    pub backoff_factor: f64,
+    /// Upper bound for the delay between attempts.
+    pub max_delay: Duration,
+    /// Random spread applied to each delay, as a fraction between 0.0 and 1.0.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries: the first failure is final.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Exponential backoff with `max_attempts` total attempts, starting at
+    /// 5 seconds and doubling up to one hour, with 10% jitter.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_secs(5),
+            backoff_factor: 2.0,
+            max_delay: Duration::from_secs(3600),
+            jitter: 0.1,
+        }
+    }
+
+    /// Set the delay before the first retry.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the multiplier applied to the delay after each failed attempt.
+    pub fn with_backoff_factor(mut self, factor: f64) -> Self {
+        self.backoff_factor = factor;
+        self
+    }
+
+    /// Set the upper bound for the delay between attempts.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the random spread applied to each delay (0.0 = no jitter).
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether another attempt is allowed after `attempts` failed attempts.
+    pub fn should_retry(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    /// The delay before the next attempt after `attempts` failed attempts,
+    /// without jitter.
+    pub fn backoff_delay(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.base_delay.as_secs_f64() * self.backoff_factor.max(1.0).powi(exponent);
+        let capped = secs.min(self.max_delay.as_secs_f64());
+        Duration::try_from_secs_f64(capped).unwrap_or(self.max_delay)
+    }
+
+    /// The delay before the next attempt after `attempts` failed attempts,
+    /// with jitter applied.
+    pub fn delay_for_attempt(&self, attempts: u32) -> Duration {
+        use rand::Rng;
+
+        let delay = self.backoff_delay(attempts).as_secs_f64();
+        let spread = delay * self.jitter.clamp(0.0, 1.0);
+        if spread <= 0.0 {
+            return Duration::from_secs_f64(delay);
+        }
+        let offset = rand::rng().random_range(-spread..=spread);
+        Duration::try_from_secs_f64((delay + offset).max(0.0)).unwrap_or(self.max_delay)
+    }
+}
+
+/// Raises the effective priority of pending jobs as they wait, so a steady
+/// stream of high-priority jobs can't starve low-priority ones.
+///
+/// A pending job moves up one level (Low → Normal → High) for every
+/// `interval` it has waited since it became runnable: its `next_run_at`, or
+/// its creation time. Its stored priority is unchanged; within one effective
+/// level, older jobs still run first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityAging {
+    /// How long a job waits before moving up one priority level.
+    pub interval: Duration,
+}
+
+impl PriorityAging {
+    /// Move waiting jobs up one level every `interval` (at least 1ms).
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval: interval.max(Duration::from_millis(1)),
+        }
+    }
+
+    /// The effective priority at `now` of a job with `priority` that has
+    /// been runnable since `waiting_since` (RFC 3339 or SQLite's
+    /// `YYYY-MM-DD HH:MM:SS`, in UTC). Unparseable times don't age.
+    pub fn effective_priority(
+        &self,
+        priority: QueuePriority,
+        waiting_since: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> QueuePriority {
+        let since = chrono::DateTime::parse_from_rfc3339(waiting_since)
+            .map(|at| at.with_timezone(&chrono::Utc))
+            .or_else(|_| {
+                chrono::NaiveDateTime::parse_from_str(waiting_since, "%Y-%m-%d %H:%M:%S")
+                    .map(|at| at.and_utc())
+            });
+        let Ok(since) = since else {
+            return priority;
+        };
+        let waited = (now - since).to_std().unwrap_or_default();
+        let levels = waited.as_millis() / self.interval.as_millis().max(1);
+        let levels = i32::try_from(levels).unwrap_or(i32::MAX);
+        QueuePriority::from_i32(
+            priority
+                .as_i32()
+                .saturating_sub(levels)
+                .max(QueuePriority::High.as_i32()),
+        )
+    }
 }
 
 /// Result returned by a job handler after execution.
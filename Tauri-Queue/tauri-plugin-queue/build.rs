@@ -0,0 +1,16 @@
+const COMMANDS: &[&str] = &[
+    "add",
+    "cancel",
+    "get",
+    "reorder",
+    "pause",
+    "resume",
+    "is_paused",
+    "list_jobs",
+    "prune",
+];
+
+fn main() {
+    // Generates the `allow-<command>` / `deny-<command>` permissions
+    tauri_plugin::Builder::new(COMMANDS).build();
+}
@@ -0,0 +1,157 @@
+//! Tauri plugin exposing a [`tauri_queue`] queue to the frontend.
+//!
+//! [`plugin()`] creates the [`QueueManager`] from a [`QueueConfig`], stores
+//! it in Tauri's managed state as `Arc<QueueManager>`, starts the executor
+//! with the app handle as its [`EventSink`](tauri_queue::EventSink), and
+//! registers these commands:
+//!
+//! | Command | Arguments | Returns |
+//! |---------|-----------|---------|
+//! | `plugin:queue\|add` | `{ data, priority?, id? }` | The job ID |
+//! | `plugin:queue\|cancel` | `{ jobId }` | |
+//! | `plugin:queue\|get` | `{ jobId }` | The job, or `null` |
+//! | `plugin:queue\|reorder` | `{ jobId, priority }` | |
+//! | `plugin:queue\|pause` | | |
+//! | `plugin:queue\|resume` | | |
+//! | `plugin:queue\|is_paused` | | `bool` |
+//! | `plugin:queue\|list_jobs` | | `[id, status][]` |
+//! | `plugin:queue\|prune` | `{ days }` | Number of jobs deleted |
+//!
+//! Commands are allowed through the `queue:default` permission, or one by
+//! one through `queue:allow-<command>`.
+
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    Manager, Runtime, State,
+};
+use tauri_queue::{JobHandler, QueueConfig, QueueError, QueueJob, QueueManager, QueuePriority};
+
+/// Adds a job from the JSON the frontend sent, typed by the plugin's handler.
+type AddJob =
+    Box<dyn Fn(Value, QueuePriority, Option<String>) -> Result<String, QueueError> + Send + Sync>;
+
+/// Gets a job with its payload decoded as the plugin's handler.
+type GetJob = Box<dyn Fn(&str) -> Result<Option<Value>, QueueError> + Send + Sync>;
+
+/// Managed state backing the plugin's commands.
+struct PluginState {
+    manager: Arc<QueueManager>,
+    add: AddJob,
+    get: GetJob,
+}
+
+/// Build the queue plugin for job type `H`.
+///
+/// The executor starts when the plugin is set up. Other handler types can be
+/// registered on the managed `Arc<QueueManager>` afterwards; the frontend's
+/// `add` and `get` commands always work with `H` jobs.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// tauri::Builder::default()
+///     .plugin(tauri_plugin_queue::plugin::<EmailJob>(config))
+///     .run(tauri::generate_context!())
+///     .expect("error while running tauri application");
+/// ```
+pub fn plugin<H>(config: QueueConfig) -> TauriPlugin<tauri::Wry>
+where
+    H: JobHandler + 'static,
+{
+    build::<tauri::Wry, H>(config)
+}
+
+/// Build the queue plugin for job type `H` on any Tauri runtime.
+pub fn build<R, H>(config: QueueConfig) -> TauriPlugin<R>
+where
+    R: Runtime,
+    H: JobHandler + 'static,
+{
+    Builder::new("queue")
+        .invoke_handler(tauri::generate_handler![
+            add, cancel, get, reorder, pause, resume, is_paused, list_jobs, prune
+        ])
+        .setup(move |app, _api| {
+            let manager = QueueManager::new(config)?.spawn::<H>(app.clone());
+            let add_manager = Arc::clone(&manager);
+            let add: AddJob = Box::new(move |data, priority, id| {
+                let mut job =
+                    QueueJob::new(serde_json::from_value::<H>(data)?).with_priority(priority);
+                if let Some(id) = id {
+                    job = job.with_id(id);
+                }
+                add_manager.add(job)
+            });
+            let get_manager = Arc::clone(&manager);
+            let get: GetJob = Box::new(move |job_id| {
+                get_manager
+                    .get::<H>(job_id)?
+                    .map(serde_json::to_value)
+                    .transpose()
+                    .map_err(QueueError::from)
+            });
+            app.manage(Arc::clone(&manager));
+            app.manage(PluginState { manager, add, get });
+            Ok(())
+        })
+        .build()
+}
+
+#[tauri::command]
+fn add(
+    state: State<'_, PluginState>,
+    data: Value,
+    priority: Option<QueuePriority>,
+    id: Option<String>,
+) -> Result<String, String> {
+    (state.add)(data, priority.unwrap_or(QueuePriority::Normal), id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cancel(state: State<'_, PluginState>, job_id: String) -> Result<(), String> {
+    state.manager.cancel(&job_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get(state: State<'_, PluginState>, job_id: String) -> Result<Option<Value>, String> {
+    (state.get)(&job_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn reorder(
+    state: State<'_, PluginState>,
+    job_id: String,
+    priority: QueuePriority,
+) -> Result<(), String> {
+    state
+        .manager
+        .reorder(&job_id, priority)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn pause(state: State<'_, PluginState>) {
+    state.manager.pause();
+}
+
+#[tauri::command]
+fn resume(state: State<'_, PluginState>) {
+    state.manager.resume();
+}
+
+#[tauri::command]
+fn is_paused(state: State<'_, PluginState>) -> bool {
+    state.manager.is_paused()
+}
+
+#[tauri::command]
+fn list_jobs(state: State<'_, PluginState>) -> Result<Vec<(String, String)>, String> {
+    state.manager.list_jobs().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn prune(state: State<'_, PluginState>, days: u32) -> Result<u32, String> {
+    state.manager.prune(days).map_err(|e| e.to_string())
+}
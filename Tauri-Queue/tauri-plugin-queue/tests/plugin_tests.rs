@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tauri::ipc::{CallbackFn, InvokeBody, RuntimeAuthority};
+use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::utils::acl::resolved::{Resolved, ResolvedCommand};
+use tauri::webview::InvokeRequest;
+use tauri::{App, Manager, WebviewWindow, WebviewWindowBuilder};
+use tauri_queue::*;
+
+const COMMANDS: &[&str] = &[
+    "add",
+    "cancel",
+    "get",
+    "reorder",
+    "pause",
+    "resume",
+    "is_paused",
+    "list_jobs",
+    "prune",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TestJob {
+    data: String,
+}
+
+impl JobHandler for TestJob {
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::success_with_output(self.data.clone()))
+    }
+}
+
+/// An app running the queue plugin for `TestJob`, with every plugin command
+/// allowed for the `main` window, and that window.
+fn mock_app() -> (App<MockRuntime>, WebviewWindow<MockRuntime>) {
+    let mut resolved = Resolved::default();
+    for command in COMMANDS {
+        resolved.allowed_commands.insert(
+            format!("plugin:queue|{}", command),
+            vec![ResolvedCommand {
+                windows: vec![glob::Pattern::new("main").unwrap()],
+                ..ResolvedCommand::default()
+            }],
+        );
+    }
+    let mut context = mock_context(noop_assets());
+    *context.runtime_authority_mut() = RuntimeAuthority::new(BTreeMap::new(), resolved);
+
+    let app = mock_builder()
+        .plugin(tauri_plugin_queue::build::<MockRuntime, TestJob>(
+            QueueConfig::default(),
+        ))
+        .build(context)
+        .unwrap();
+    let window = WebviewWindowBuilder::new(&app, "main", Default::default())
+        .build()
+        .unwrap();
+    (app, window)
+}
+
+/// Invoke `plugin:queue|<command>` with `args`, returning its JSON response.
+fn invoke(window: &WebviewWindow<MockRuntime>, command: &str, args: Value) -> Result<Value, Value> {
+    get_ipc_response(
+        window,
+        InvokeRequest {
+            cmd: format!("plugin:queue|{}", command),
+            callback: CallbackFn(0),
+            error: CallbackFn(1),
+            url: "http://tauri.localhost".parse().unwrap(),
+            body: InvokeBody::Json(args),
+            headers: Default::default(),
+            invoke_key: tauri::test::INVOKE_KEY.to_string(),
+        },
+    )
+    .map(|body| body.deserialize::<Value>().unwrap())
+}
+
+#[test]
+fn test_plugin_manages_the_queue() {
+    let (app, _window) = mock_app();
+    assert!(app.try_state::<std::sync::Arc<QueueManager>>().is_some());
+}
+
+#[test]
+fn test_plugin_add_and_get() {
+    let (_app, window) = mock_app();
+
+    invoke(&window, "pause", json!({})).unwrap();
+    let job_id = invoke(
+        &window,
+        "add",
+        json!({ "data": { "data": "hello" }, "priority": "High", "id": "job-1" }),
+    )
+    .unwrap();
+    assert_eq!(job_id, json!("job-1"));
+
+    let job: QueueJob<TestJob> =
+        serde_json::from_value(invoke(&window, "get", json!({ "jobId": "job-1" })).unwrap())
+            .unwrap();
+    assert_eq!(job.id, "job-1");
+    assert_eq!(job.priority, QueuePriority::High);
+    assert_eq!(job.status, QueueJobStatus::Pending);
+    assert_eq!(job.data.data, "hello");
+
+    assert_eq!(
+        invoke(&window, "get", json!({ "jobId": "missing" })).unwrap(),
+        Value::Null
+    );
+}
+
+#[test]
+fn test_plugin_add_rejects_invalid_payload() {
+    let (_app, window) = mock_app();
+
+    let err = invoke(&window, "add", json!({ "data": { "wrong": 1 } })).unwrap_err();
+    assert!(err.as_str().unwrap().contains("Serialization"));
+}
+
+#[test]
+fn test_plugin_cancel() {
+    let (_app, window) = mock_app();
+
+    invoke(&window, "pause", json!({})).unwrap();
+    assert_eq!(
+        invoke(&window, "is_paused", json!({})).unwrap(),
+        json!(true)
+    );
+    let job_id = invoke(&window, "add", json!({ "data": { "data": "bye" } })).unwrap();
+
+    invoke(&window, "cancel", json!({ "jobId": job_id })).unwrap();
+
+    let job: QueueJob<TestJob> =
+        serde_json::from_value(invoke(&window, "get", json!({ "jobId": job_id })).unwrap())
+            .unwrap();
+    assert_eq!(job.status, QueueJobStatus::Cancelled);
+    assert_eq!(
+        invoke(&window, "list_jobs", json!({})).unwrap(),
+        json!([[job_id, "cancelled"]])
+    );
+}
@@ -1,9 +1,65 @@
 mod test_helpers;
 
+use std::sync::Arc;
 use tauri_queue::*;
+#[cfg(feature = "sqlite")]
 use tempfile::tempdir;
-use test_helpers::TestJob;
+use test_helpers::{
+    FailingJob, GreetingJob, GreetingJobV1, LabelledJob, LoggingJob, OtherJob, ResumableJob,
+    SlowJob, TestJob,
+};
+
+#[cfg(feature = "sqlite")]
+fn sqlite_queue(config: QueueConfig) -> QueueManager {
+    QueueManager::new(config).unwrap()
+}
+
+fn memory_queue(config: QueueConfig) -> QueueManager {
+    QueueManager::with_store(config, Arc::new(MemoryStore::new())).unwrap()
+}
+
+/// Run a test against both the SQLite and the in-memory store. The body
+/// builds its queues with `new_queue`.
+macro_rules! backend_test {
+    (fn $name:ident($new_queue:ident) $body:block) => {
+        mod $name {
+            use super::*;
+
+            fn run($new_queue: fn(QueueConfig) -> QueueManager) $body
+
+            #[cfg(feature = "sqlite")]
+            #[test]
+            fn sqlite() {
+                run(sqlite_queue)
+            }
+
+            #[test]
+            fn memory() {
+                run(memory_queue)
+            }
+        }
+    };
+    (async fn $name:ident($new_queue:ident) $body:block) => {
+        mod $name {
+            use super::*;
+
+            async fn run($new_queue: fn(QueueConfig) -> QueueManager) $body
+
+            #[cfg(feature = "sqlite")]
+            #[tokio::test]
+            async fn sqlite() {
+                run(sqlite_queue).await
+            }
+
+            #[tokio::test]
+            async fn memory() {
+                run(memory_queue).await
+            }
+        }
+    };
+}
 
+#[cfg(feature = "sqlite")]
 #[test]
 fn test_queue_creation_in_memory() {
     let config = QueueConfig::default();
@@ -11,6 +67,7 @@ fn test_queue_creation_in_memory() {
     assert!(queue.is_ok());
 }
 
+#[cfg(feature = "sqlite")]
 #[test]
 fn test_queue_creation_with_db() {
     let temp = tempdir().unwrap();
@@ -21,163 +78,174 @@ fn test_queue_creation_with_db() {
     assert!(queue.is_ok());
 }
 
-#[test]
-fn test_add_job() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_add_job(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let job = QueueJob::new(TestJob {
-        data: "test".to_string(),
-    });
+        let job = QueueJob::new(TestJob {
+            data: "test".to_string(),
+        });
 
-    let job_id = queue.add(job).unwrap();
-    assert!(!job_id.is_empty());
+        let job_id = queue.add(job).unwrap();
+        assert!(!job_id.is_empty());
+    }
 }
 
-#[test]
-fn test_add_job_with_custom_id() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_add_job_with_custom_id(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let job = QueueJob::new(TestJob {
-        data: "test".to_string(),
-    })
-    .with_id("my-custom-id".to_string());
+        let job = QueueJob::new(TestJob {
+            data: "test".to_string(),
+        })
+        .with_id("my-custom-id".to_string());
 
-    let job_id = queue.add(job).unwrap();
-    assert_eq!(job_id, "my-custom-id");
+        let job_id = queue.add(job).unwrap();
+        assert_eq!(job_id, "my-custom-id");
+    }
 }
 
-#[test]
-fn test_priority_ordering() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_priority_ordering(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let low = QueueJob::new(TestJob { data: "low".into() })
-        .with_priority(QueuePriority::Low)
-        .with_id("low".into());
+        let low = QueueJob::new(TestJob { data: "low".into() })
+            .with_priority(QueuePriority::Low)
+            .with_id("low".into());
 
-    let high = QueueJob::new(TestJob {
-        data: "high".into(),
-    })
-    .with_priority(QueuePriority::High)
-    .with_id("high".into());
+        let high = QueueJob::new(TestJob {
+            data: "high".into(),
+        })
+        .with_priority(QueuePriority::High)
+        .with_id("high".into());
 
-    let normal = QueueJob::new(TestJob {
-        data: "normal".into(),
-    })
-    .with_priority(QueuePriority::Normal)
-    .with_id("normal".into());
+        let normal = QueueJob::new(TestJob {
+            data: "normal".into(),
+        })
+        .with_priority(QueuePriority::Normal)
+        .with_id("normal".into());
 
-    queue.add(low).unwrap();
-    queue.add(high).unwrap();
-    queue.add(normal).unwrap();
+        queue.add(low).unwrap();
+        queue.add(high).unwrap();
+        queue.add(normal).unwrap();
 
-    let jobs = queue.list_jobs().unwrap();
-    assert_eq!(jobs.len(), 3);
-    // All pending, sorted by priority: high(1), normal(2), low(3)
-    assert_eq!(jobs[0].0, "high");
-    assert_eq!(jobs[1].0, "normal");
-    assert_eq!(jobs[2].0, "low");
+        let jobs = queue.list_jobs().unwrap();
+        assert_eq!(jobs.len(), 3);
+        // All pending, sorted by priority: high(1), normal(2), low(3)
+        assert_eq!(jobs[0].0, "high");
+        assert_eq!(jobs[1].0, "normal");
+        assert_eq!(jobs[2].0, "low");
+    }
 }
 
-#[test]
-fn test_cancel_pending_job() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_cancel_pending_job(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let job = QueueJob::new(TestJob {
-        data: "test".into(),
-    })
-    .with_id("cancel-me".into());
+        let job = QueueJob::new(TestJob {
+            data: "test".into(),
+        })
+        .with_id("cancel-me".into());
 
-    queue.add(job).unwrap();
-    queue.cancel("cancel-me").unwrap();
+        queue.add(job).unwrap();
+        queue.cancel("cancel-me").unwrap();
 
-    let jobs = queue.list_jobs().unwrap();
-    assert_eq!(jobs[0].1, "cancelled");
+        let jobs = queue.list_jobs().unwrap();
+        assert_eq!(jobs[0].1, "cancelled");
+    }
 }
 
-#[test]
-fn test_cancel_nonexistent_fails() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_cancel_nonexistent_fails(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let result = queue.cancel("nonexistent");
-    assert!(result.is_err());
+        let result = queue.cancel("nonexistent");
+        assert!(result.is_err());
+    }
 }
 
-#[test]
-fn test_reorder_pending_job() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_reorder_pending_job(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let job = QueueJob::new(TestJob {
-        data: "test".into(),
-    })
-    .with_priority(QueuePriority::Low)
-    .with_id("reorder-me".into());
+        let job = QueueJob::new(TestJob {
+            data: "test".into(),
+        })
+        .with_priority(QueuePriority::Low)
+        .with_id("reorder-me".into());
 
-    queue.add(job).unwrap();
-    queue.reorder("reorder-me", QueuePriority::High).unwrap();
+        queue.add(job).unwrap();
+        queue.reorder("reorder-me", QueuePriority::High).unwrap();
 
-    // Verify by listing — job should be present
-    let jobs = queue.list_jobs().unwrap();
-    assert_eq!(jobs.len(), 1);
-    assert_eq!(jobs[0].0, "reorder-me");
+        // Verify by listing — job should be present
+        let jobs = queue.list_jobs().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].0, "reorder-me");
+    }
 }
 
-#[test]
-fn test_reorder_nonexistent_fails() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_reorder_nonexistent_fails(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let result = queue.reorder("nonexistent", QueuePriority::High);
-    assert!(result.is_err());
+        let result = queue.reorder("nonexistent", QueuePriority::High);
+        assert!(result.is_err());
+    }
 }
 
-#[test]
-fn test_pause_resume() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_pause_resume(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    assert!(!queue.is_paused());
+        assert!(!queue.is_paused());
 
-    queue.pause();
-    assert!(queue.is_paused());
+        queue.pause();
+        assert!(queue.is_paused());
 
-    queue.resume();
-    assert!(!queue.is_paused());
+        queue.resume();
+        assert!(!queue.is_paused());
+    }
 }
 
-#[test]
-fn test_list_jobs_empty() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_list_jobs_empty(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let jobs = queue.list_jobs().unwrap();
-    assert!(jobs.is_empty());
+        let jobs = queue.list_jobs().unwrap();
+        assert!(jobs.is_empty());
+    }
 }
 
-#[test]
-fn test_list_jobs_with_data() {
-    let config = QueueConfig::default();
-    let queue = QueueManager::new(config).unwrap();
+backend_test! {
+    fn test_list_jobs_with_data(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
 
-    let job = QueueJob::new(TestJob {
-        data: "hello world".into(),
-    })
-    .with_id("data-job".into());
+        let job = QueueJob::new(TestJob {
+            data: "hello world".into(),
+        })
+        .with_id("data-job".into());
 
-    queue.add(job).unwrap();
+        queue.add(job).unwrap();
 
-    let jobs = queue.list_jobs_with_data().unwrap();
-    assert_eq!(jobs.len(), 1);
-    assert_eq!(jobs[0].0, "data-job");
-    assert_eq!(jobs[0].1, "pending");
-    assert!(jobs[0].2.contains("hello world"));
+        let jobs = queue.list_jobs_with_data().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].0, "data-job");
+        assert_eq!(jobs[0].1, "pending");
+        assert!(jobs[0].2.contains("hello world"));
+    }
 }
 
+#[cfg(feature = "sqlite")]
 #[test]
 fn test_persistence_across_instances() {
     let temp = tempdir().unwrap();
@@ -216,6 +284,7 @@ fn test_persistence_across_instances() {
     }
 }
 
+#[cfg(feature = "sqlite")]
 #[test]
 fn test_crash_recovery_requeues_processing() {
     let temp = tempdir().unwrap();
@@ -246,64 +315,1233 @@ fn test_crash_recovery_requeues_processing() {
     }
 }
 
+backend_test! {
+    fn test_prune_no_completed_jobs(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        queue
+            .add(QueueJob::new(TestJob {
+                data: "pending".into(),
+            }))
+            .unwrap();
+
+        let pruned = queue.prune(0).unwrap();
+        assert_eq!(pruned, 0);
+    }
+}
+
+backend_test! {
+    fn test_multiple_priorities_interleaved(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        for i in 0..3 {
+            queue
+                .add(
+                    QueueJob::new(TestJob {
+                        data: format!("high-{}", i),
+                    })
+                    .with_priority(QueuePriority::High)
+                    .with_id(format!("h-{}", i)),
+                )
+                .unwrap();
+            queue
+                .add(
+                    QueueJob::new(TestJob {
+                        data: format!("low-{}", i),
+                    })
+                    .with_priority(QueuePriority::Low)
+                    .with_id(format!("l-{}", i)),
+                )
+                .unwrap();
+        }
+
+        let jobs = queue.list_jobs().unwrap();
+        assert_eq!(jobs.len(), 6);
+
+        // First 3 should be high priority
+        for (i, job) in jobs.iter().enumerate().take(3) {
+            assert!(
+                job.0.starts_with("h-"),
+                "Expected high priority at index {}",
+                i
+            );
+        }
+        // Last 3 should be low priority
+        for (i, job) in jobs.iter().enumerate().skip(3) {
+            assert!(
+                job.0.starts_with("l-"),
+                "Expected low priority at index {}",
+                i
+            );
+        }
+    }
+}
+
+backend_test! {
+    fn test_schedule_lifecycle(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        let hourly = QueueSchedule::every(
+            Duration::from_secs(3600),
+            TestJob {
+                data: "cleanup".into(),
+            },
+        )
+        .with_id("hourly".into());
+        let nightly = QueueSchedule::cron(
+            "0 3 * * *",
+            TestJob {
+                data: "reindex".into(),
+            },
+        )
+        .with_id("nightly".into());
+
+        queue.add_schedule(hourly).unwrap();
+        queue.add_schedule(nightly).unwrap();
+        assert_eq!(queue.list_schedules().unwrap().len(), 2);
+
+        queue.pause_schedule("nightly").unwrap();
+        let schedules = queue.list_schedules().unwrap();
+        let nightly = schedules.iter().find(|s| s.id == "nightly").unwrap();
+        assert!(nightly.paused);
+
+        queue.resume_schedule("nightly").unwrap();
+        queue.delete_schedule("hourly").unwrap();
+        assert_eq!(queue.list_schedules().unwrap().len(), 1);
+
+        assert!(queue.pause_schedule("missing").is_err());
+        assert!(queue.delete_schedule("hourly").is_err());
+    }
+}
+
+backend_test! {
+    fn test_schedule_rejects_invalid_spec(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        let bad_cron = QueueSchedule::cron("not a cron", TestJob { data: "x".into() });
+        assert!(matches!(
+            queue.add_schedule(bad_cron),
+            Err(QueueError::InvalidSchedule(_))
+        ));
+
+        let zero = QueueSchedule::every(std::time::Duration::ZERO, TestJob { data: "x".into() });
+        assert!(queue.add_schedule(zero).is_err());
+    }
+}
+
 #[test]
-fn test_prune_no_completed_jobs() {
-    let config = QueueConfig::default();
+fn test_schedule_spec_next_after() {
+    use chrono::TimeZone;
+
+    let at = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 12, 30, 0).unwrap();
+
+    let cron = ScheduleSpec::Cron("0 * * * *".into());
+    assert_eq!(
+        cron.next_after(at).unwrap(),
+        Some(chrono::Utc.with_ymd_and_hms(2024, 1, 1, 13, 0, 0).unwrap())
+    );
+
+    let interval = ScheduleSpec::Interval(std::time::Duration::from_secs(90));
+    assert_eq!(
+        interval.next_after(at).unwrap(),
+        Some(
+            chrono::Utc
+                .with_ymd_and_hms(2024, 1, 1, 12, 31, 30)
+                .unwrap()
+        )
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_schedules_persist_across_instances() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("schedules.db");
+
+    {
+        let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
+        let queue = QueueManager::new(config).unwrap();
+        queue
+            .add_schedule(QueueSchedule::cron(
+                "*/5 * * * *",
+                TestJob {
+                    data: "sync".into(),
+                },
+            ))
+            .unwrap();
+    }
+
+    {
+        let config = QueueConfig::builder().with_db_path(db_path).build();
+        let queue = QueueManager::new(config).unwrap();
+        let schedules = queue.list_schedules().unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].spec, ScheduleSpec::Cron("*/5 * * * *".into()));
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_delayed_job_keeps_run_at() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("delayed.db");
+    let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
     let queue = QueueManager::new(config).unwrap();
 
+    let run_at = chrono::Utc::now() + chrono::Duration::hours(2);
     queue
-        .add(QueueJob::new(TestJob {
-            data: "pending".into(),
-        }))
+        .add(
+            QueueJob::new(TestJob {
+                data: "later".into(),
+            })
+            .run_at(run_at)
+            .with_id("later".into()),
+        )
         .unwrap();
 
-    let pruned = queue.prune(0).unwrap();
-    assert_eq!(pruned, 0);
+    let conn = tauri_queue::db::open_database(Some(&db_path)).unwrap();
+    assert!(tauri_queue::db::get_next_pending(&conn).unwrap().is_none());
 }
 
+backend_test! {
+    fn test_register_job_types(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+        assert!(queue.job_types().is_empty());
+
+        queue.register::<TestJob>().register::<OtherJob>();
+
+        let mut types = queue.job_types();
+        types.sort();
+        let mut expected = vec!["other".to_string(), TestJob::type_key().to_string()];
+        expected.sort();
+        assert_eq!(types, expected);
+    }
+}
+
+backend_test! {
+    async fn test_job_type_label_does_not_affect_routing(new_queue) {
+        let queue = new_queue(QueueConfig::default());
+        let job_id = queue.add(QueueJob::new(LabelledJob)).unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<LabelledJob>(sink);
+        let completed = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(completed.payload["jobId"], job_id.as_str());
+        let stats = queue.stats().unwrap();
+        assert_eq!(stats.job_types[0].job_type, "labelled");
+    }
+}
+
+#[cfg(feature = "sqlite")]
 #[test]
-fn test_multiple_priorities_interleaved() {
-    let config = QueueConfig::default();
+fn test_mixed_job_types_record_their_type() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("mixed.db");
+    let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
     let queue = QueueManager::new(config).unwrap();
 
-    for i in 0..3 {
+    queue
+        .add(QueueJob::new(TestJob { data: "a".into() }).with_id("test-job".into()))
+        .unwrap();
+    queue
+        .add(QueueJob::new(OtherJob { count: 3 }).with_id("other-job".into()))
+        .unwrap();
+
+    // Only "other" is claimable; the TestJob row is left pending
+    let conn = tauri_queue::db::open_database(Some(&db_path)).unwrap();
+    let claimed = tauri_queue::db::claim_next(&conn, &["other".to_string()], false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id, "other-job");
+    assert_eq!(claimed.data["count"], 3);
+    assert!(
+        tauri_queue::db::claim_next(&conn, &["other".to_string()], false)
+            .unwrap()
+            .is_none()
+    );
+
+    let jobs = queue.list_jobs().unwrap();
+    let test_job = jobs.iter().find(|(id, _)| id == "test-job").unwrap();
+    assert_eq!(test_job.1, "pending");
+}
+
+backend_test! {
+    fn test_dependent_job_is_blocked(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        queue
+            .add(QueueJob::new(TestJob { data: "a".into() }).with_id("parent".into()))
+            .unwrap();
         queue
             .add(
-                QueueJob::new(TestJob {
-                    data: format!("high-{}", i),
-                })
-                .with_priority(QueuePriority::High)
-                .with_id(format!("h-{}", i)),
+                QueueJob::new(TestJob { data: "b".into() })
+                    .with_id("child".into())
+                    .depends_on(vec!["parent".into()]),
             )
             .unwrap();
+
+        let jobs = queue.list_jobs().unwrap();
+        let child = jobs.iter().find(|(id, _)| id == "child").unwrap();
+        assert_eq!(child.1, "blocked");
+    }
+}
+
+backend_test! {
+    fn test_dependency_must_exist(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        let result =
+            queue.add(QueueJob::new(TestJob { data: "b".into() }).depends_on(vec!["missing".into()]));
+        assert!(result.is_err());
+    }
+}
+
+backend_test! {
+    fn test_cancel_parent_cancels_dependents(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        queue
+            .add(QueueJob::new(TestJob { data: "a".into() }).with_id("parent".into()))
+            .unwrap();
         queue
             .add(
-                QueueJob::new(TestJob {
-                    data: format!("low-{}", i),
+                QueueJob::new(TestJob { data: "b".into() })
+                    .with_id("cancel-child".into())
+                    .depends_on(vec!["parent".into()]),
+            )
+            .unwrap();
+        queue
+            .add(
+                QueueJob::new(TestJob { data: "c".into() })
+                    .with_id("fail-child".into())
+                    .depends_on(vec!["parent".into()])
+                    .with_dependency_policy(DependencyFailurePolicy::Fail),
+            )
+            .unwrap();
+
+        queue.cancel("parent").unwrap();
+
+        let jobs = queue.list_jobs().unwrap();
+        let status = |id: &str| jobs.iter().find(|(j, _)| j == id).unwrap().1.clone();
+        assert_eq!(status("parent"), "cancelled");
+        assert_eq!(status("cancel-child"), "cancelled");
+        assert_eq!(status("fail-child"), "failed");
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_get_typed_job_with_output() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("results.db");
+    let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
+    let queue = QueueManager::new(config).unwrap();
+
+    queue
+        .add(
+            QueueJob::new(TestJob {
+                data: "render".into(),
+            })
+            .with_id("job-1".into()),
+        )
+        .unwrap();
+    assert!(queue.get::<TestJob>("missing").unwrap().is_none());
+
+    {
+        let conn = tauri_queue::db::open_database(Some(&db_path)).unwrap();
+        tauri_queue::db::mark_processing(&conn, "job-1").unwrap();
+        tauri_queue::db::mark_completed_with_output(&conn, "job-1", Some("out.mp4")).unwrap();
+    }
+
+    let job = queue.get::<TestJob>("job-1").unwrap().unwrap();
+    assert_eq!(job.data.data, "render");
+    assert_eq!(job.status, QueueJobStatus::Completed);
+    assert_eq!(job.output.as_deref(), Some("out.mp4"));
+    assert!(job.created_at.is_some());
+    assert!(job.completed_at.is_some());
+}
+
+backend_test! {
+    fn test_query_jobs_by_type_and_status(new_queue) {
+        let config = QueueConfig::default();
+        let queue = new_queue(config);
+
+        for i in 0..3 {
+            queue
+                .add(QueueJob::new(TestJob {
+                    data: format!("t{}", i),
+                }))
+                .unwrap();
+        }
+        queue.add(QueueJob::new(OtherJob { count: 1 })).unwrap();
+        let cancelled = queue
+            .add(QueueJob::new(TestJob {
+                data: "gone".into(),
+            }))
+            .unwrap();
+        queue.cancel(&cancelled).unwrap();
+
+        // Defaults to the handler's own type
+        let tests = queue.query::<TestJob>(JobFilter::new()).unwrap();
+        assert_eq!(tests.len(), 4);
+        let others = queue.query::<OtherJob>(JobFilter::new()).unwrap();
+        assert_eq!(others.len(), 1);
+        assert_eq!(others[0].data.count, 1);
+
+        let pending = queue
+            .query::<TestJob>(JobFilter::new().with_status(QueueJobStatus::Pending))
+            .unwrap();
+        assert_eq!(pending.len(), 3);
+
+        let page = queue
+            .query::<TestJob>(JobFilter::new().paginate(2, 0))
+            .unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].id, cancelled);
+        assert_eq!(page[0].status, QueueJobStatus::Cancelled);
+    }
+}
+
+backend_test! {
+    async fn test_runs_jobs_without_tauri(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::JobCompletedEvent;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let job_id = queue
+            .add(QueueJob::new(TestJob {
+                data: "headless".into(),
+            }))
+            .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<TestJob>(sink);
+
+        let completed = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = events.recv().await {
+                if event.name == "queue:job_completed" {
+                    return event.decode::<JobCompletedEvent>().unwrap();
+                }
+            }
+            panic!("event channel closed");
+        })
+        .await
+        .expect("job did not complete in time");
+
+        assert_eq!(completed.job_id, job_id);
+        assert_eq!(completed.output.as_deref(), Some("headless"));
+        let job = queue.get::<TestJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Completed);
+    }
+}
+
+/// Wait for the next event named `name`, skipping others.
+async fn next_event(
+    events: &mut tokio::sync::mpsc::UnboundedReceiver<QueueEvent>,
+    name: &str,
+) -> QueueEvent {
+    let wait = async {
+        while let Some(event) = events.recv().await {
+            if event.name == name {
+                return event;
+            }
+        }
+        panic!("event channel closed");
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(5), wait)
+        .await
+        .unwrap_or_else(|_| panic!("no {} event in time", name))
+}
+
+backend_test! {
+    async fn test_cancel_wakes_running_job(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let job_id = queue
+            .add(QueueJob::new(SlowJob { millis: 60_000 }))
+            .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<SlowJob>(sink);
+        next_event(&mut events, "queue:job_started").await;
+
+        let cancelled_at = std::time::Instant::now();
+        queue.cancel(&job_id).unwrap();
+        let event = next_event(&mut events, "queue:job_cancelled").await;
+        assert_eq!(event.payload["jobId"], job_id.as_str());
+        assert!(cancelled_at.elapsed() < Duration::from_secs(2));
+
+        let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Cancelled);
+    }
+}
+
+backend_test! {
+    async fn test_add_and_resume_wake_the_executor(new_queue) {
+        use std::time::{Duration, Instant};
+
+        // Polling alone would take a minute to notice the jobs
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_secs(60))
+            .build();
+        let queue = new_queue(config);
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<TestJob>(sink);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let added_at = Instant::now();
+        let job_id = queue
+            .add(QueueJob::new(TestJob {
+                data: "fast".into(),
+            }))
+            .unwrap();
+        let event = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(event.payload["jobId"], job_id.as_str());
+        assert!(added_at.elapsed() < Duration::from_millis(500));
+
+        queue.pause();
+        let job_id = queue
+            .add(QueueJob::new(TestJob {
+                data: "paused".into(),
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let resumed_at = Instant::now();
+        queue.resume();
+        let event = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(event.payload["jobId"], job_id.as_str());
+        assert!(resumed_at.elapsed() < Duration::from_millis(500));
+    }
+}
+
+backend_test! {
+    async fn test_concurrency_runs_jobs_in_parallel_within_type_caps(new_queue) {
+        use std::time::Duration;
+
+        /// Names of the started/completed events, in order, for `count` jobs.
+        async fn lifecycle(
+            events: &mut tokio::sync::mpsc::UnboundedReceiver<QueueEvent>,
+            count: usize,
+        ) -> Vec<String> {
+            let mut names = Vec::new();
+            while names.iter().filter(|n| *n == "queue:job_completed").count() < count {
+                let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                    .await
+                    .expect("jobs did not finish in time")
+                    .expect("event channel closed");
+                if event.name == "queue:job_started" || event.name == "queue:job_completed" {
+                    names.push(event.name);
+                }
+            }
+            names
+        }
+
+        // Three workers start all three jobs before any of them finishes
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_concurrency(3)
+            .build();
+        let queue = new_queue(config);
+        for _ in 0..3 {
+            queue.add(QueueJob::new(SlowJob { millis: 300 })).unwrap();
+        }
+        let (sink, mut events) = ChannelEventSink::new();
+        let _queue = queue.spawn::<SlowJob>(sink);
+        let names = lifecycle(&mut events, 3).await;
+        assert!(names[..3].iter().all(|n| n == "queue:job_started"));
+
+        // A cap of one per type runs them one after another despite free workers
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_concurrency(3)
+            .with_job_type_limit("slow", 1)
+            .build();
+        let queue = new_queue(config);
+        for _ in 0..2 {
+            queue.add(QueueJob::new(SlowJob { millis: 100 })).unwrap();
+        }
+        let (sink, mut events) = ChannelEventSink::new();
+        let _queue = queue.spawn::<SlowJob>(sink);
+        let names = lifecycle(&mut events, 2).await;
+        assert_eq!(
+            names,
+            [
+                "queue:job_started",
+                "queue:job_completed",
+                "queue:job_started",
+                "queue:job_completed"
+            ]
+        );
+    }
+}
+
+backend_test! {
+    async fn test_job_timeout(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::JobTimedOutEvent;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let job_id = queue
+            .add(QueueJob::new(SlowJob { millis: 60_000 }).with_timeout(Duration::from_millis(50)))
+            .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<SlowJob>(sink);
+
+        let event: JobTimedOutEvent = next_event(&mut events, "queue:job_timed_out")
+            .await
+            .decode()
+            .unwrap();
+        assert_eq!(event.job_id, job_id);
+        assert_eq!(event.timeout_ms, 50);
+
+        let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::TimedOut);
+        assert_eq!(job.timeout, Some(Duration::from_millis(50)));
+    }
+}
+
+backend_test! {
+    async fn test_shutdown_drain_finishes_running_jobs(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let job_id = queue.add(QueueJob::new(SlowJob { millis: 200 })).unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<SlowJob>(sink);
+        next_event(&mut events, "queue:job_started").await;
+
+        queue.shutdown(ShutdownMode::Drain).await;
+        let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Completed);
+
+        // Jobs added after the shutdown are kept but not started
+        let later = queue.add(QueueJob::new(SlowJob { millis: 1 })).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let job = queue.get::<SlowJob>(&later).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Pending);
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_shutdown_abort_interrupts_running_jobs() {
+    use std::time::Duration;
+
+    let temp = tempdir().unwrap();
+    let config = QueueConfig::builder()
+        .with_db_path(temp.path().join("shutdown.db"))
+        .with_poll_interval(Duration::from_millis(20))
+        .build();
+    let queue = QueueManager::new(config.clone()).unwrap();
+    let job_id = queue
+        .add(QueueJob::new(SlowJob { millis: 60_000 }))
+        .unwrap();
+
+    let (sink, mut events) = ChannelEventSink::new();
+    let queue = queue.spawn::<SlowJob>(sink);
+    next_event(&mut events, "queue:job_started").await;
+
+    tokio::time::timeout(Duration::from_secs(2), queue.shutdown(ShutdownMode::Abort))
+        .await
+        .expect("shutdown did not finish in time");
+    let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+    assert_eq!(job.status, QueueJobStatus::Interrupted);
+    drop(queue);
+
+    // The next start picks the job up again
+    let queue = QueueManager::new(config).unwrap();
+    let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+    assert_eq!(job.status, QueueJobStatus::Pending);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_cancel_from_another_process_stops_running_job() {
+    use std::time::Duration;
+
+    let temp = tempdir().unwrap();
+    let config = QueueConfig::builder()
+        .with_db_path(temp.path().join("shared.db"))
+        .with_poll_interval(Duration::from_millis(20))
+        .with_lease_duration(Duration::from_millis(300))
+        .build();
+    let queue = QueueManager::new(config.clone()).unwrap();
+    let job_id = queue
+        .add(QueueJob::new(SlowJob { millis: 60_000 }))
+        .unwrap();
+
+    let (sink, mut events) = ChannelEventSink::new();
+    let queue = queue.spawn::<SlowJob>(sink);
+    next_event(&mut events, "queue:job_started").await;
+
+    // A second process sharing the database, with no executor of its own
+    let other = QueueManager::new(config).unwrap();
+    let cancelled_at = std::time::Instant::now();
+    other.cancel(&job_id).unwrap();
+    let event = next_event(&mut events, "queue:job_cancelled").await;
+    assert_eq!(event.payload["jobId"], job_id.as_str());
+    assert!(cancelled_at.elapsed() < Duration::from_secs(2));
+
+    let job = queue.get::<SlowJob>(&job_id).unwrap().unwrap();
+    assert_eq!(job.status, QueueJobStatus::Cancelled);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_two_processes_share_a_database() {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    let temp = tempdir().unwrap();
+    let config = |worker: &str| {
+        QueueConfig::builder()
+            .with_db_path(temp.path().join("shared.db"))
+            .with_poll_interval(Duration::from_millis(10))
+            .with_concurrency(2)
+            .with_worker_id(worker)
+            .build()
+    };
+    let first = QueueManager::new(config("first")).unwrap();
+    let second = QueueManager::new(config("second")).unwrap();
+    let mut job_ids = HashSet::new();
+    for i in 0..20 {
+        let job = QueueJob::new(SlowJob { millis: 5 + i % 3 });
+        job_ids.insert(first.add(job).unwrap());
+    }
+
+    let (sink, mut events) = ChannelEventSink::new();
+    let first = first.spawn::<SlowJob>(sink.clone());
+    let _second = second.spawn::<SlowJob>(sink);
+
+    let mut completed = HashSet::new();
+    while completed.len() < job_ids.len() {
+        let event = next_event(&mut events, "queue:job_completed").await;
+        let job_id = event.payload["jobId"].as_str().unwrap().to_string();
+        assert!(completed.insert(job_id), "a job completed twice");
+    }
+    assert_eq!(completed, job_ids);
+    for job_id in &job_ids {
+        assert_eq!(first.attempts(job_id).unwrap().len(), 1);
+    }
+}
+
+backend_test! {
+    async fn test_named_queues_run_and_pause_independently(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_queue("downloads", NamedQueueConfig::default().with_concurrency(2))
+            .build();
+        let queue = new_queue(config);
+        queue.register::<TestJob>().register::<SlowJob>();
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.start(sink);
+
+        // A long job in the default queue doesn't hold up the "downloads" queue
+        let slow_id = queue
+            .add(QueueJob::new(SlowJob { millis: 60_000 }))
+            .unwrap();
+        next_event(&mut events, "queue:job_started").await;
+        let download = |data: &str| {
+            QueueJob::new(TestJob {
+                data: data.to_string(),
+            })
+            .on_queue("downloads")
+        };
+        let first = queue.add(download("first")).unwrap();
+        let event = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(event.payload["jobId"], first.as_str());
+
+        queue.pause_queue("downloads");
+        let second = queue.add(download("second")).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let stats = queue.queue_stats("downloads").unwrap();
+        assert!(stats.paused);
+        assert_eq!((stats.pending, stats.completed), (1, 1));
+        let default = queue.queue_stats(DEFAULT_QUEUE).unwrap();
+        assert_eq!((default.paused, default.processing), (false, 1));
+
+        queue.resume_queue("downloads");
+        let event = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(event.payload["jobId"], second.as_str());
+
+        let job = queue.get::<TestJob>(&second).unwrap().unwrap();
+        assert_eq!(job.queue, "downloads");
+        let in_downloads = queue
+            .query::<TestJob>(JobFilter::new().with_queue("downloads"))
+            .unwrap();
+        assert_eq!(in_downloads.len(), 2);
+        let names: Vec<String> = queue
+            .queues()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.queue)
+            .collect();
+        assert_eq!(names, vec!["default", "downloads"]);
+        queue.cancel(&slow_id).unwrap();
+    }
+}
+
+backend_test! {
+    async fn test_retry_resumes_from_checkpoint(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let policy = RetryPolicy::new(2)
+            .with_base_delay(Duration::from_millis(10))
+            .with_jitter(0.0);
+        let job_id = queue
+            .add(
+                QueueJob::new(ResumableJob {
+                    files: 10_000,
+                    crash_at: 6_200,
                 })
-                .with_priority(QueuePriority::Low)
-                .with_id(format!("l-{}", i)),
+                .with_retry_policy(policy),
             )
             .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<ResumableJob>(sink);
+        let event = next_event(&mut events, "queue:job_completed").await;
+        assert_eq!(event.payload["output"], "resumed at 6200");
+
+        let job = queue.get::<ResumableJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.attempts, 2);
     }
+}
 
-    let jobs = queue.list_jobs().unwrap();
-    assert_eq!(jobs.len(), 6);
-
-    // First 3 should be high priority
-    for i in 0..3 {
-        assert!(
-            jobs[i].0.starts_with("h-"),
-            "Expected high priority at index {}",
-            i
+backend_test! {
+    async fn test_job_logs_are_stored_and_emitted(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::JobLogEvent;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        queue.register::<LoggingJob>().register::<FailingJob>();
+        let logged = queue
+            .add(QueueJob::new(LoggingJob {
+                message: "Hello".into(),
+            }))
+            .unwrap();
+        let failed = queue
+            .add(QueueJob::new(FailingJob {
+                error: "disk full".into(),
+            }))
+            .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.start(sink);
+        let event: JobLogEvent = next_event(&mut events, "queue:job_log")
+            .await
+            .decode()
+            .unwrap();
+        assert_eq!(event.job_id, logged);
+        assert_eq!(event.level, LogLevel::Info);
+        assert_eq!(event.fields["message"], "Hello");
+        next_event(&mut events, "queue:job_failed").await;
+
+        let logs = queue.logs(&logged, None).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, event.id);
+        assert!(queue.logs(&logged, Some(event.id)).unwrap().is_empty());
+
+        // Failures are logged by the queue itself
+        let logs = queue.logs(&failed, None).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].level, LogLevel::Error);
+        assert!(logs[0].message.contains("disk full"));
+    }
+}
+
+backend_test! {
+    async fn test_stats_per_job_type_and_periodic_event(new_queue) {
+        use std::time::Duration;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_stats_interval(Duration::from_millis(50))
+            .build();
+        let queue = new_queue(config);
+        queue.register::<OtherJob>().register::<FailingJob>();
+        for count in 0..3 {
+            queue.add(QueueJob::new(OtherJob { count })).unwrap();
+        }
+        queue
+            .add(QueueJob::new(FailingJob {
+                error: "boom".into(),
+            }))
+            .unwrap();
+
+        let before = queue.stats().unwrap();
+        assert_eq!(before.depth, 4);
+        assert_eq!(before.window_secs, 3600);
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.start(sink);
+        let metrics = loop {
+            let metrics: QueueMetrics = next_event(&mut events, "queue:stats")
+                .await
+                .decode()
+                .unwrap();
+            if metrics.depth == 0 {
+                break metrics;
+            }
+        };
+        assert_eq!(
+            metrics,
+            QueueMetrics {
+                generated_at: metrics.generated_at.clone(),
+                ..queue.stats().unwrap()
+            }
         );
+
+        let other = metrics
+            .job_types
+            .iter()
+            .find(|m| m.job_type == "other")
+            .unwrap();
+        assert_eq!(other.counts["completed"], 3);
+        assert_eq!((other.completed, other.failed), (3, 0));
+        assert_eq!(other.failure_rate, 0.0);
+        assert!(other.throughput_per_minute > 0.0);
+        assert!(other.p50_duration_ms.is_some());
+        assert!(other.p95_duration_ms >= other.p50_duration_ms);
+
+        let failing = metrics
+            .job_types
+            .iter()
+            .find(|m| m.job_type == "failing")
+            .unwrap();
+        assert_eq!(failing.counts["failed"], 1);
+        assert_eq!(failing.failure_rate, 1.0);
+        assert!(failing.avg_duration_ms.is_none());
     }
-    // Last 3 should be low priority
-    for i in 3..6 {
-        assert!(
-            jobs[i].0.starts_with("l-"),
-            "Expected low priority at index {}",
-            i
+}
+
+backend_test! {
+    async fn test_priority_aging_runs_starved_low_priority_jobs(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::JobStartedEvent;
+
+        let clock = MockClock::default();
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .with_priority_aging(Duration::from_secs(60))
+            .with_clock(clock.clone())
+            .build();
+        let queue = new_queue(config);
+        let high = || QueueJob::new(SlowJob { millis: 20 }).with_priority(QueuePriority::High);
+        let low = queue
+            .add(QueueJob::new(SlowJob { millis: 20 }).with_priority(QueuePriority::Low))
+            .unwrap();
+        for _ in 0..3 {
+            queue.add(high()).unwrap();
+        }
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<SlowJob>(sink);
+
+        // Keep High jobs coming: every start is replaced by a new one. The Low
+        // job only runs once the clock has aged it to High.
+        let mut high_runs = 0;
+        loop {
+            let started: JobStartedEvent = next_event(&mut events, "queue:job_started")
+                .await
+                .decode()
+                .unwrap();
+            if started.job_id == low {
+                break;
+            }
+            high_runs += 1;
+            assert!(high_runs <= 20, "the low priority job was starved");
+            queue.add(high()).unwrap();
+
+            if high_runs == 5 {
+                let job = queue.get::<SlowJob>(&low).unwrap().unwrap();
+                assert_eq!(job.effective_priority, QueuePriority::Low);
+                clock.advance(Duration::from_secs(150));
+                let job = queue.get::<SlowJob>(&low).unwrap().unwrap();
+                assert_eq!(job.priority, QueuePriority::Low);
+                assert_eq!(job.effective_priority, QueuePriority::High);
+            }
+        }
+        assert!(high_runs >= 5, "the low job ran before it had aged");
+        let waiting = queue
+            .query::<SlowJob>(JobFilter::new().with_status(QueueJobStatus::Pending))
+            .unwrap();
+        assert!(!waiting.is_empty(), "expected High jobs to still be queued");
+    }
+}
+
+backend_test! {
+    async fn test_exhausted_retries_dead_letter_and_requeue(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::JobDeadEvent;
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let policy = RetryPolicy::new(2)
+            .with_base_delay(Duration::from_millis(10))
+            .with_jitter(0.0);
+        let job_id = queue
+            .add(
+                QueueJob::new(FailingJob {
+                    error: "model not found".into(),
+                })
+                .with_retry_policy(policy),
+            )
+            .unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<FailingJob>(sink);
+
+        let dead: JobDeadEvent = next_event(&mut events, "queue:job_dead")
+            .await
+            .decode()
+            .unwrap();
+        assert_eq!(dead.job_id, job_id);
+        assert_eq!(dead.attempts, 2);
+        let job = queue.get::<FailingJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Dead);
+
+        let attempts = queue.attempts(&job_id).unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts
+            .iter()
+            .all(|a| a.error.as_deref() == Some("model not found")));
+
+        queue.pause();
+        queue.requeue(&job_id).unwrap();
+        let requeued = next_event(&mut events, "queue:job_requeued").await;
+        assert_eq!(requeued.payload["previousStatus"], "dead");
+        let job = queue.get::<FailingJob>(&job_id).unwrap().unwrap();
+        assert_eq!(job.status, QueueJobStatus::Pending);
+        assert_eq!(job.attempts, 0);
+        assert_eq!(queue.attempts(&job_id).unwrap().len(), 2);
+    }
+}
+
+backend_test! {
+    async fn test_old_payloads_are_upcast_or_marked_incompatible(new_queue) {
+        use std::time::Duration;
+        use tauri_queue::events::{JobCompletedEvent, JobIncompatibleEvent};
+
+        let config = QueueConfig::builder()
+            .with_poll_interval(Duration::from_millis(20))
+            .build();
+        let queue = new_queue(config);
+        let old = queue
+            .add(QueueJob::new(GreetingJobV1 {
+                title: "Ada".into(),
+            }))
+            .unwrap();
+        let broken = queue
+            .add(QueueJob::new(GreetingJobV1 { title: "".into() }))
+            .unwrap();
+        queue.upcaster::<GreetingJob, _>(1, |data| match data["title"].as_str() {
+            Some(title) if !title.is_empty() => Ok(serde_json::json!({ "name": title })),
+            _ => Err(QueueError::Other("missing title".into())),
+        });
+
+        // Typed lookups see the current shape too
+        let job = queue.get::<GreetingJob>(&old).unwrap().unwrap();
+        assert_eq!(job.data.name, "Ada");
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<GreetingJob>(sink);
+
+        let completed: JobCompletedEvent = next_event(&mut events, "queue:job_completed")
+            .await
+            .decode()
+            .unwrap();
+        assert_eq!(completed.job_id, old);
+        assert_eq!(completed.output.as_deref(), Some("Hello, Ada"));
+
+        let incompatible: JobIncompatibleEvent = next_event(&mut events, "queue:job_incompatible")
+            .await
+            .decode()
+            .unwrap();
+        assert_eq!(incompatible.job_id, broken);
+        assert_eq!(incompatible.payload_version, 1);
+        assert!(incompatible.error.contains("missing title"));
+        assert!(matches!(
+            queue.get::<GreetingJob>(&broken),
+            Err(QueueError::IncompatiblePayload(_))
+        ));
+        let stats = queue.queue_stats(DEFAULT_QUEUE).unwrap();
+        assert_eq!(stats.incompatible, 1);
+        assert_eq!(stats.failed, 0);
+
+        // Incompatible jobs can be requeued, e.g. after fixing the upcaster
+        queue.pause();
+        queue.requeue(&broken).unwrap();
+        let jobs = queue.list_jobs().unwrap();
+        assert!(jobs.contains(&(broken, "pending".to_string())));
+    }
+}
+
+backend_test! {
+    fn test_requeue_missing_job(new_queue) {
+        let queue = new_queue(QueueConfig::default());
+        assert!(matches!(
+            queue.requeue("missing"),
+            Err(QueueError::NotFound(_))
+        ));
+        assert_eq!(queue.requeue_all_failed(JobFilter::new()).unwrap(), 0);
+    }
+}
+
+backend_test! {
+    fn test_unique_key_policies(new_queue) {
+        let queue = new_queue(QueueConfig::default());
+        let job = |data: &str| {
+            QueueJob::new(TestJob {
+                data: data.to_string(),
+            })
+            .with_unique_key("sync:inbox")
+        };
+
+        let first = queue.add(job("a")).unwrap();
+        assert!(matches!(
+            queue.add(job("b")),
+            Err(QueueError::DuplicateJob { existing_id, .. }) if existing_id == first
+        ));
+        let existing = queue
+            .add(job("c").on_conflict(UniqueConflictPolicy::ReturnExisting))
+            .unwrap();
+        assert_eq!(existing, first);
+
+        let replacement = queue
+            .add(job("d").on_conflict(UniqueConflictPolicy::ReplacePending))
+            .unwrap();
+        assert_ne!(replacement, first);
+        let old = queue.get::<TestJob>(&first).unwrap().unwrap();
+        assert_eq!(old.status, QueueJobStatus::Cancelled);
+        let new = queue.get::<TestJob>(&replacement).unwrap().unwrap();
+        assert_eq!(new.data.data, "d");
+        assert_eq!(new.unique_key.as_deref(), Some("sync:inbox"));
+
+        // Jobs without a key are never deduplicated
+        let plain = || {
+            QueueJob::new(TestJob {
+                data: "x".to_string(),
+            })
+        };
+        assert_ne!(queue.add(plain()).unwrap(), queue.add(plain()).unwrap());
+    }
+}
+
+backend_test! {
+    fn test_add_many_is_atomic(new_queue) {
+        let queue = new_queue(QueueConfig::default());
+        let job = |data: &str| {
+            QueueJob::new(TestJob {
+                data: data.to_string(),
+            })
+        };
+
+        let first = job("a");
+        let second = job("b").depends_on(vec![first.id.clone()]);
+        let expected = vec![first.id.clone(), second.id.clone()];
+        assert_eq!(queue.add_many(vec![first, second]).unwrap(), expected);
+        let second = queue.get::<TestJob>(&expected[1]).unwrap().unwrap();
+        assert_eq!(second.status, QueueJobStatus::Blocked);
+
+        // A conflict anywhere in the batch rolls back the jobs before it
+        queue.add(job("c").with_unique_key("sync:inbox")).unwrap();
+        let result = queue.add_many(vec![job("d"), job("e").with_unique_key("sync:inbox")]);
+        assert!(matches!(result, Err(QueueError::DuplicateJob { .. })));
+        assert_eq!(queue.list_jobs().unwrap().len(), 3);
+
+        assert!(queue
+            .add_many(Vec::<QueueJob<TestJob>>::new())
+            .unwrap()
+            .is_empty());
+    }
+}
+
+backend_test! {
+    async fn test_bulk_cancel_and_reorder_emit_summary_events(new_queue) {
+        let queue = new_queue(QueueConfig::default());
+        queue.pause();
+        let job_ids = queue
+            .add_many(
+                (0..5)
+                    .map(|_| QueueJob::new(SlowJob { millis: 10 }).with_priority(QueuePriority::Low))
+                    .collect(),
+            )
+            .unwrap();
+        let other = queue.add(QueueJob::new(OtherJob { count: 1 })).unwrap();
+
+        let (sink, mut events) = ChannelEventSink::new();
+        let queue = queue.spawn::<SlowJob>(sink);
+
+        let moved = queue
+            .reorder_many(&job_ids[..2], QueuePriority::High)
+            .unwrap();
+        assert_eq!(moved, 2);
+        let reordered = next_event(&mut events, "queue:jobs_reordered").await;
+        assert_eq!(reordered.payload["jobIds"].as_array().unwrap().len(), 2);
+        assert_eq!(reordered.payload["priority"], "High");
+        let job = queue.get::<SlowJob>(&job_ids[0]).unwrap().unwrap();
+        assert_eq!(job.priority, QueuePriority::High);
+
+        let count = queue
+            .cancel_where(JobFilter::new().with_job_type("slow"))
+            .unwrap();
+        assert_eq!(count, 5);
+        let cancelled = next_event(&mut events, "queue:jobs_cancelled").await;
+        assert_eq!(cancelled.payload["count"], 5);
+        let jobs = queue
+            .query::<SlowJob>(JobFilter::new().with_status(QueueJobStatus::Cancelled))
+            .unwrap();
+        assert_eq!(jobs.len(), 5);
+        let other = queue.get::<OtherJob>(&other).unwrap().unwrap();
+        assert_eq!(other.status, QueueJobStatus::Pending);
+
+        // Nothing left to cancel or reorder
+        assert_eq!(
+            queue
+                .cancel_where(JobFilter::new().with_job_type("slow"))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            queue.reorder_many(&job_ids, QueuePriority::Normal).unwrap(),
+            0
         );
     }
 }
@@ -331,11 +1569,16 @@ fn test_queue_priority_roundtrip() {
 #[test]
 fn test_job_status_roundtrip() {
     let statuses = [
+        QueueJobStatus::Blocked,
         QueueJobStatus::Pending,
         QueueJobStatus::Processing,
         QueueJobStatus::Completed,
         QueueJobStatus::Failed,
         QueueJobStatus::Cancelled,
+        QueueJobStatus::TimedOut,
+        QueueJobStatus::Dead,
+        QueueJobStatus::Interrupted,
+        QueueJobStatus::Incompatible,
     ];
 
     for status in &statuses {
@@ -393,10 +1636,13 @@ fn test_event_serialization() {
         current_step: 5,
         total_steps: 10,
         progress: 0.5,
+        stage: Some("encoding".to_string()),
+        message: None,
     };
     let json = serde_json::to_string(&progress).unwrap();
     assert!(json.contains("currentStep"));
     assert!(json.contains("totalSteps"));
+    assert!(json.contains("\"stage\":\"encoding\""));
 
     let cancelled = JobCancelledEvent {
         job_id: "j1".to_string(),
@@ -405,6 +1651,64 @@ fn test_event_serialization() {
     assert!(json.contains("jobId"));
 }
 
+#[test]
+fn test_retry_policy_backoff() {
+    use std::time::Duration;
+
+    let policy = RetryPolicy::new(5)
+        .with_base_delay(Duration::from_secs(2))
+        .with_backoff_factor(3.0)
+        .with_max_delay(Duration::from_secs(60))
+        .with_jitter(0.0);
+
+    assert_eq!(policy.backoff_delay(1), Duration::from_secs(2));
+    assert_eq!(policy.backoff_delay(2), Duration::from_secs(6));
+    assert_eq!(policy.backoff_delay(3), Duration::from_secs(18));
+    assert_eq!(policy.backoff_delay(4), Duration::from_secs(54));
+    // Capped at max_delay
+    assert_eq!(policy.backoff_delay(5), Duration::from_secs(60));
+    assert_eq!(policy.delay_for_attempt(2), Duration::from_secs(6));
+
+    assert!(policy.should_retry(4));
+    assert!(!policy.should_retry(5));
+    assert!(!RetryPolicy::none().should_retry(1));
+}
+
+#[test]
+fn test_retry_policy_jitter_bounds() {
+    use std::time::Duration;
+
+    let policy = RetryPolicy::new(3)
+        .with_base_delay(Duration::from_secs(10))
+        .with_jitter(0.5);
+
+    for _ in 0..100 {
+        let delay = policy.delay_for_attempt(1);
+        assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(15));
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_add_job_with_retry_policy() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("retry.db");
+    let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
+    let queue = QueueManager::new(config).unwrap();
+
+    let job = QueueJob::new(TestJob {
+        data: "flaky".into(),
+    })
+    .with_retry_policy(RetryPolicy::new(4))
+    .with_id("retry-me".into());
+    queue.add(job).unwrap();
+
+    let conn = tauri_queue::db::open_database(Some(&db_path)).unwrap();
+    let (attempts, policy) = tauri_queue::db::get_retry_state(&conn, "retry-me").unwrap();
+    assert_eq!(attempts, 0);
+    assert_eq!(policy.unwrap().max_attempts, 4);
+}
+
 #[test]
 fn test_config_builder() {
     use std::time::Duration;
@@ -428,4 +1732,69 @@ fn test_config_defaults() {
     assert_eq!(config.cooldown, std::time::Duration::from_secs(0));
     assert_eq!(config.max_consecutive, 0);
     assert_eq!(config.poll_interval, std::time::Duration::from_secs(3));
+    assert_eq!(config.retry_policy, RetryPolicy::none());
+    assert_eq!(config.concurrency, 1);
+    assert!(config.job_type_limits.is_empty());
+}
+
+#[test]
+fn test_config_progress_interval() {
+    use std::time::Duration;
+
+    assert_eq!(
+        QueueConfig::default().progress_persist_interval,
+        Duration::from_millis(500)
+    );
+    let config = QueueConfig::builder()
+        .with_progress_persist_interval(Duration::from_secs(2))
+        .build();
+    assert_eq!(config.progress_persist_interval, Duration::from_secs(2));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_progress_shows_in_lookup() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("progress.db");
+    let config = QueueConfig::builder().with_db_path(db_path.clone()).build();
+    let queue = QueueManager::new(config).unwrap();
+
+    let job_id = queue
+        .add(QueueJob::new(TestJob { data: "a".into() }))
+        .unwrap();
+    assert!(queue.progress(&job_id).unwrap().is_none());
+
+    {
+        let conn = tauri_queue::db::open_database(Some(&db_path)).unwrap();
+        let progress = JobProgress {
+            current: 40,
+            total: 100,
+            stage: Some("upload".into()),
+            message: None,
+            updated_at: None,
+        };
+        tauri_queue::db::update_progress(&conn, &job_id, &progress).unwrap();
+    }
+
+    let progress = queue.progress(&job_id).unwrap().unwrap();
+    assert_eq!(progress.fraction(), 0.4);
+    assert_eq!(progress.stage.as_deref(), Some("upload"));
+
+    let job = queue.get::<TestJob>(&job_id).unwrap().unwrap();
+    assert_eq!(job.progress, Some(progress));
+}
+
+#[test]
+fn test_config_concurrency() {
+    let config = QueueConfig::builder()
+        .with_concurrency(4)
+        .with_job_type_limit("gpu", 1)
+        .build();
+
+    assert_eq!(config.concurrency, 4);
+    assert_eq!(config.job_type_limits.get("gpu"), Some(&1));
+
+    // Zero workers would never run anything
+    let config = QueueConfig::builder().with_concurrency(0).build();
+    assert_eq!(config.concurrency, 1);
 }
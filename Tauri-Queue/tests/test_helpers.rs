@@ -11,3 +11,158 @@ impl JobHandler for TestJob {
         Ok(JobResult::success_with_output(self.data.clone()))
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtherJob {
+    pub count: u32,
+}
+
+impl JobHandler for OtherJob {
+    fn type_key() -> &'static str {
+        "other"
+    }
+
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::success())
+    }
+}
+
+/// Sleeps for `millis`, stopping early if the job is cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowJob {
+    pub millis: u64,
+}
+
+impl JobHandler for SlowJob {
+    fn type_key() -> &'static str {
+        "slow"
+    }
+
+    async fn execute(&self, ctx: &JobContext) -> Result<JobResult, QueueError> {
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_millis(self.millis)) => {
+                Ok(JobResult::success())
+            }
+            _ = ctx.cancelled() => Err(QueueError::Cancelled),
+        }
+    }
+}
+
+/// Always fails with `error`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailingJob {
+    pub error: String,
+}
+
+impl JobHandler for FailingJob {
+    fn type_key() -> &'static str {
+        "failing"
+    }
+
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::failure(self.error.clone()))
+    }
+}
+
+/// Works through `files` items, checkpointing after each one. The first
+/// attempt fails after `crash_at` items; the output names where the
+/// successful attempt resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumableJob {
+    pub files: u32,
+    pub crash_at: u32,
+}
+
+impl JobHandler for ResumableJob {
+    fn type_key() -> &'static str {
+        "resumable"
+    }
+
+    async fn execute(&self, ctx: &JobContext) -> Result<JobResult, QueueError> {
+        let start = ctx.load_checkpoint::<u32>()?.unwrap_or(0);
+        for file in start..self.files {
+            if start == 0 && file == self.crash_at {
+                return Ok(JobResult::failure("crashed".to_string()));
+            }
+            ctx.save_checkpoint(&(file + 1))?;
+        }
+        Ok(JobResult::success_with_output(format!(
+            "resumed at {}",
+            start
+        )))
+    }
+}
+
+/// Logs `message` with the job's data as fields, then succeeds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingJob {
+    pub message: String,
+}
+
+impl JobHandler for LoggingJob {
+    fn type_key() -> &'static str {
+        "logging"
+    }
+
+    async fn execute(&self, ctx: &JobContext) -> Result<JobResult, QueueError> {
+        ctx.log(LogLevel::Info, &self.message, self)?;
+        Ok(JobResult::success())
+    }
+}
+
+/// Version 1 of [`GreetingJob`], before `title` was renamed to `name`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GreetingJobV1 {
+    pub title: String,
+}
+
+impl JobHandler for GreetingJobV1 {
+    fn type_key() -> &'static str {
+        "greeting"
+    }
+
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::success_with_output(self.title.clone()))
+    }
+}
+
+/// Version 2 of the `greeting` payload: outputs "Hello, {name}".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GreetingJob {
+    pub name: String,
+}
+
+impl JobHandler for GreetingJob {
+    fn type_key() -> &'static str {
+        "greeting"
+    }
+
+    fn payload_version() -> u32 {
+        2
+    }
+
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::success_with_output(format!(
+            "Hello, {}",
+            self.name
+        )))
+    }
+}
+
+/// Overrides `job_type()` with a display label for logs; routing still uses `type_key()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelledJob;
+
+impl JobHandler for LabelledJob {
+    fn type_key() -> &'static str {
+        "labelled"
+    }
+
+    fn job_type(&self) -> &str {
+        "Labelled job"
+    }
+
+    async fn execute(&self, _ctx: &JobContext) -> Result<JobResult, QueueError> {
+        Ok(JobResult::success())
+    }
+}